@@ -0,0 +1,155 @@
+//! Compares the naive and greedy meshers on representative chunks.
+//!
+//! Run it with `cargo bench -p base --bench mesher`.
+
+use {
+    base::{
+        chunk::{size, ChunkData},
+        mesher::{self, Block, MeshBuffer, Mode, Neighbours, ShapeRef, Sprite},
+        point::BlockPoint,
+        shape::Shape,
+        side::{Side, Sides},
+    },
+    std::{
+        hint,
+        time::{Duration, Instant},
+    },
+};
+
+const ITERATIONS: u32 = 50;
+
+#[derive(Clone, Copy)]
+enum Stone {
+    Air,
+    Grass,
+    Dirt,
+    Rock,
+}
+
+impl Block for Stone {
+    fn shape(&self) -> Option<ShapeRef<'_>> {
+        match self {
+            Self::Air => None,
+            _ => Some(Shape::S1.into()),
+        }
+    }
+
+    fn covers(&self) -> Sides {
+        match self {
+            Self::Air => Sides::empty(),
+            _ => Sides::all(),
+        }
+    }
+
+    fn sprite(&self, face: usize) -> Option<Sprite> {
+        let side = Shape::S1.data()[face].side;
+        let id = match (self, side) {
+            (Self::Air, _) => return None,
+            (Self::Grass, Some(Side::Up)) => 0,
+            (Self::Grass, _) => 1,
+            (Self::Dirt, _) => 2,
+            (Self::Rock, _) => 3,
+        };
+
+        Some(Sprite {
+            id,
+            ..Sprite::default()
+        })
+    }
+}
+
+type Chunk = ChunkData<Stone>;
+
+fn make<F>(f: F) -> Chunk
+where
+    F: Fn(u8, u8, u8) -> Stone,
+{
+    let mut chunk = Chunk::new(Stone::Air);
+    for z in 0..size::DEPTH as u8 {
+        for y in 0..size::HEIGHT as u8 {
+            for x in 0..size::WIDTH as u8 {
+                chunk[BlockPoint::new(x, y, z).expect("point")] = f(x, y, z);
+            }
+        }
+    }
+
+    chunk
+}
+
+/// A deterministic height map of smooth hills.
+fn height(x: u8, z: u8) -> u8 {
+    let (x, z) = (f32::from(x), f32::from(z));
+    let h = 8. + 3. * (x * 0.4).sin() + 2. * (z * 0.3).cos() + (x * z * 0.05).sin();
+    h as u8
+}
+
+fn chunks() -> Vec<(&'static str, Chunk)> {
+    vec![
+        (
+            "flat floor",
+            make(|_, y, _| match y {
+                0..=2 => Stone::Rock,
+                3 => Stone::Grass,
+                _ => Stone::Air,
+            }),
+        ),
+        (
+            "hills",
+            make(|x, y, z| {
+                let h = height(x, z);
+                match y {
+                    _ if y > h => Stone::Air,
+                    _ if y == h => Stone::Grass,
+                    _ if y + 3 > h => Stone::Dirt,
+                    _ => Stone::Rock,
+                }
+            }),
+        ),
+        (
+            "walls",
+            make(|x, y, z| match (x % 8, z % 8, y) {
+                (0, _, 1..=6) | (_, 0, 1..=6) | (_, _, 0) => Stone::Rock,
+                _ => Stone::Air,
+            }),
+        ),
+        (
+            "checkerboard",
+            make(|x, y, z| match (x + y + z) % 2 {
+                0 => Stone::Rock,
+                _ => Stone::Air,
+            }),
+        ),
+    ]
+}
+
+fn measure(chunk: &Chunk, mode: Mode) -> (usize, Duration) {
+    let neighbours = Neighbours::new();
+    let triangles = |buffers: &[MeshBuffer]| -> usize {
+        buffers.iter().map(|buffer| buffer.faces().len()).sum()
+    };
+
+    let n = triangles(&mesher::mesh(chunk, &neighbours, mode));
+    let mut times: Vec<_> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            hint::black_box(mesher::mesh(hint::black_box(chunk), &neighbours, mode));
+            start.elapsed()
+        })
+        .collect();
+
+    times.sort_unstable();
+    (n, times[times.len() / 2])
+}
+
+fn main() {
+    println!(
+        "{:<14} {:>10} {:>12} {:>10} {:>12}",
+        "chunk", "naive", "naive time", "greedy", "greedy time",
+    );
+
+    for (name, chunk) in chunks() {
+        let (naive, naive_time) = measure(&chunk, Mode::Naive);
+        let (greedy, greedy_time) = measure(&chunk, Mode::Greedy);
+        println!("{name:<14} {naive:>10} {naive_time:>12.2?} {greedy:>10} {greedy_time:>12.2?}");
+    }
+}
@@ -1,3 +1,4 @@
+pub mod codec;
 mod data;
 pub mod layout;
 pub(crate) mod point;
@@ -0,0 +1,436 @@
+//! Binary chunk format.
+//!
+//! An encoded chunk consists of a header, a palette of distinct values
+//! and the payload of palette indices in the layout order:
+//!
+//! | Field     | Size                             |
+//! |-----------|----------------------------------|
+//! | version   | 1 byte                           |
+//! | layout id | 1 byte                           |
+//! | encoding  | 1 byte                           |
+//! | palette   | 2 bytes length + values          |
+//! | payload   | depends on the encoding          |
+//!
+//! All numbers are little-endian. The `Packed` payload is a sequence of
+//! indices of the minimal bit width, the `RunLength` payload is a sequence
+//! of runs, each one is a pair of varints: a run length and an index.
+
+use {
+    crate::chunk::{layout::Layout, ChunkData},
+    std::{fmt, hash::Hash},
+};
+
+/// The current version of the format.
+pub const VERSION: u8 = 1;
+
+/// A value which can be stored in an encoded chunk.
+pub trait Value: Copy + Eq + Hash {
+    const SIZE: usize;
+
+    fn write(self, buf: &mut Vec<u8>);
+
+    /// Reads a value from the slice of `SIZE` length.
+    fn read(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_value {
+    ($($t:ty),*) => {
+        $(
+            impl Value for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                fn write(self, buf: &mut Vec<u8>) {
+                    buf.extend(self.to_le_bytes());
+                }
+
+                fn read(bytes: &[u8]) -> Self {
+                    Self::from_le_bytes(bytes.try_into().expect("value size"))
+                }
+            }
+        )*
+    };
+}
+
+impl_value!(u8, u16, u32);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// Bit-packed palette indices.
+    Packed = 0,
+    /// Runs of the same palette index.
+    RunLength = 1,
+}
+
+impl<T, L> ChunkData<T, L>
+where
+    T: Value,
+    L: Layout,
+{
+    /// Encodes the chunk to the end of the `buf`.
+    pub fn encode(&self, encoding: Encoding, buf: &mut Vec<u8>) {
+        use fxhash::FxHashMap as Map;
+
+        let values = self.data.as_slice();
+        let mut palette = Vec::new();
+        let mut lookup = Map::default();
+        let indices: Vec<u16> = values
+            .iter()
+            .map(|&value| {
+                *lookup.entry(value).or_insert_with(|| {
+                    palette.push(value);
+                    u16::try_from(palette.len() - 1).expect("palette length")
+                })
+            })
+            .collect();
+
+        buf.extend([VERSION, L::ID, encoding as u8]);
+        let len = u16::try_from(palette.len()).expect("palette length");
+        buf.extend(len.to_le_bytes());
+        for value in palette {
+            value.write(buf);
+        }
+
+        match encoding {
+            Encoding::Packed => {
+                let bits = index_bits(len);
+                let mut acc = 0_u64;
+                let mut n_bits = 0;
+                for index in indices {
+                    acc |= u64::from(index) << n_bits;
+                    n_bits += bits;
+                    while n_bits >= 8 {
+                        buf.push(acc as u8);
+                        acc >>= 8;
+                        n_bits -= 8;
+                    }
+                }
+
+                if n_bits > 0 {
+                    buf.push(acc as u8);
+                }
+            }
+            Encoding::RunLength => {
+                let mut runs = indices.iter().peekable();
+                while let Some(&index) = runs.next() {
+                    let mut len = 1;
+                    while runs.next_if_eq(&&index).is_some() {
+                        len += 1;
+                    }
+
+                    write_varint(len, buf);
+                    write_varint(u32::from(index), buf);
+                }
+            }
+        }
+    }
+
+    /// Decodes a chunk from the `bytes`.
+    ///
+    /// The whole slice must be consumed by the chunk.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(DecodeError::Version(version));
+        }
+
+        let layout = reader.byte()?;
+        if layout != L::ID {
+            return Err(DecodeError::Layout(layout));
+        }
+
+        let encoding = match reader.byte()? {
+            0 => Encoding::Packed,
+            1 => Encoding::RunLength,
+            en => return Err(DecodeError::Encoding(en)),
+        };
+
+        let len = u16::from_le_bytes(reader.array()?);
+        let mut palette = Vec::with_capacity(usize::from(len));
+        for _ in 0..len {
+            palette.push(T::read(reader.take(T::SIZE)?));
+        }
+
+        let first = *palette.first().ok_or(DecodeError::EmptyPalette)?;
+        let mut chunk = Self::new(first);
+        let values = chunk.data.as_mut_slice();
+        let get = |index: u32| {
+            palette
+                .get(index as usize)
+                .copied()
+                .ok_or(DecodeError::Index(index))
+        };
+
+        match encoding {
+            Encoding::Packed => {
+                let bits = index_bits(len);
+                let mask = (1 << bits) - 1;
+                let mut acc = 0_u64;
+                let mut n_bits = 0;
+                for value in values {
+                    while n_bits < bits {
+                        acc |= u64::from(reader.byte()?) << n_bits;
+                        n_bits += 8;
+                    }
+
+                    *value = get((acc & mask) as u32)?;
+                    acc >>= bits;
+                    n_bits -= bits;
+                }
+            }
+            Encoding::RunLength => {
+                let mut values = values.iter_mut();
+                while values.len() > 0 {
+                    let len = reader.varint()?;
+                    let value = get(reader.varint()?)?;
+                    if len == 0 || len as usize > values.len() {
+                        return Err(DecodeError::Run(len));
+                    }
+
+                    values.by_ref().take(len as usize).for_each(|v| *v = value);
+                }
+            }
+        }
+
+        if !reader.0.is_empty() {
+            return Err(DecodeError::TrailingBytes(reader.0.len()));
+        }
+
+        Ok(chunk)
+    }
+}
+
+/// Returns the bit width of an index of the palette of `len` length.
+fn index_bits(len: u16) -> u32 {
+    u16::BITS - len.saturating_sub(1).leading_zeros()
+}
+
+fn write_varint(mut value: u32, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    buf.push(value as u8);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+
+        let (left, right) = self.0.split_at(n);
+        self.0 = right;
+        Ok(left)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        self.take(N).map(|bytes| bytes.try_into().expect("array"))
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        self.array().map(|[b]| b)
+    }
+
+    fn varint(&mut self) -> Result<u32, DecodeError> {
+        let mut value = 0;
+        for shift in (0..32).step_by(7) {
+            let b = self.byte()?;
+            value |= u32::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError::Varint)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    Truncated,
+    Version(u8),
+    Layout(u8),
+    Encoding(u8),
+    EmptyPalette,
+    Index(u32),
+    Run(u32),
+    Varint,
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "unexpected end of chunk data"),
+            Self::Version(v) => write!(f, "unsupported chunk version {v}"),
+            Self::Layout(id) => write!(f, "unexpected chunk layout {id}"),
+            Self::Encoding(en) => write!(f, "unknown chunk encoding {en}"),
+            Self::EmptyPalette => write!(f, "empty chunk palette"),
+            Self::Index(index) => write!(f, "palette index {index} out of bounds"),
+            Self::Run(len) => write!(f, "wrong run length {len}"),
+            Self::Varint => write!(f, "too long varint"),
+            Self::TrailingBytes(n) => write!(f, "{n} trailing bytes after chunk data"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            chunk::{
+                layout::{Curve, Straight},
+                size::*,
+            },
+            point::BlockPoint,
+        },
+    };
+
+    type StraightChunk = ChunkData<u16, Straight<WIDTH, HEIGHT>>;
+    type CurveChunk = ChunkData<u16, Curve<WIDTH, HEIGHT, DEPTH>>;
+
+    const ENCODINGS: [Encoding; 2] = [Encoding::Packed, Encoding::RunLength];
+
+    fn points() -> impl Iterator<Item = BlockPoint> {
+        (0..DEPTH as u8).flat_map(|z| {
+            (0..HEIGHT as u8).flat_map(move |y| {
+                (0..WIDTH as u8).map(move |x| BlockPoint::new(x, y, z).expect("point"))
+            })
+        })
+    }
+
+    /// Fills the chunk by some value patterns.
+    fn fill<L>(chunk: &mut ChunkData<u16, L>, n_values: u32)
+    where
+        L: Layout,
+    {
+        let mut seed = 7_u32;
+        for (n, point) in points().enumerate() {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let (_, y, _) = point.into();
+            chunk[point] = if y < 4 {
+                1
+            } else if n % 5 == 0 {
+                ((seed >> 16) % n_values) as u16
+            } else {
+                0
+            };
+        }
+    }
+
+    fn round_trip<L>(n_values: u32)
+    where
+        L: Layout,
+    {
+        let mut chunk = ChunkData::<u16, L>::new(0);
+        fill(&mut chunk, n_values);
+
+        for encoding in ENCODINGS {
+            let mut buf = vec![];
+            chunk.encode(encoding, &mut buf);
+            let decoded = ChunkData::<u16, L>::decode(&buf).unwrap();
+            for point in points() {
+                assert_eq!(chunk[point], decoded[point]);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_straight() {
+        for n_values in [1, 2, 3, 17, 1000] {
+            round_trip::<Straight<WIDTH, HEIGHT>>(n_values);
+        }
+    }
+
+    #[test]
+    fn round_trip_curve() {
+        for n_values in [1, 2, 3, 17, 1000] {
+            round_trip::<Curve<WIDTH, HEIGHT, DEPTH>>(n_values);
+        }
+    }
+
+    #[test]
+    fn round_trip_uniform() {
+        let chunk = StraightChunk::new(42);
+        for encoding in ENCODINGS {
+            let mut buf = vec![];
+            chunk.encode(encoding, &mut buf);
+            let decoded = StraightChunk::decode(&buf).unwrap();
+            assert!(points().all(|point| decoded[point] == 42));
+        }
+    }
+
+    #[test]
+    fn run_length_is_compact() {
+        let chunk = StraightChunk::new(42);
+        let mut buf = vec![];
+        chunk.encode(Encoding::RunLength, &mut buf);
+        assert!(buf.len() < 16);
+    }
+
+    #[test]
+    fn truncated() {
+        let mut chunk = StraightChunk::new(0);
+        fill(&mut chunk, 17);
+
+        for encoding in ENCODINGS {
+            let mut buf = vec![];
+            chunk.encode(encoding, &mut buf);
+            let lens = (0..buf.len()).step_by(13).chain([buf.len() - 1]);
+            for len in lens {
+                assert_eq!(
+                    StraightChunk::decode(&buf[..len]).err(),
+                    Some(DecodeError::Truncated),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted() {
+        let chunk = StraightChunk::new(1);
+        let mut buf = vec![];
+        chunk.encode(Encoding::RunLength, &mut buf);
+
+        let mut wrong = buf.clone();
+        wrong[0] = VERSION + 1;
+        assert_eq!(
+            StraightChunk::decode(&wrong).err(),
+            Some(DecodeError::Version(VERSION + 1)),
+        );
+
+        assert_eq!(CurveChunk::decode(&buf).err(), Some(DecodeError::Layout(0)),);
+
+        let mut wrong = buf.clone();
+        wrong[2] = 9;
+        assert_eq!(
+            StraightChunk::decode(&wrong).err(),
+            Some(DecodeError::Encoding(9)),
+        );
+
+        let mut wrong = buf.clone();
+        *wrong.last_mut().unwrap() = 1;
+        assert_eq!(
+            StraightChunk::decode(&wrong).err(),
+            Some(DecodeError::Index(1)),
+        );
+
+        let mut wrong = buf.clone();
+        wrong.push(0);
+        assert_eq!(
+            StraightChunk::decode(&wrong).err(),
+            Some(DecodeError::TrailingBytes(1)),
+        );
+
+        let wrong = [VERSION, 0, 0, 0, 0];
+        assert_eq!(
+            StraightChunk::decode(&wrong).err(),
+            Some(DecodeError::EmptyPalette),
+        );
+    }
+}
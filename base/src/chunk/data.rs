@@ -19,6 +19,16 @@ where
         }
     }
 
+    /// Returns values in the layout order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.inner
+    }
+
+    /// Returns mutable values in the layout order.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.inner
+    }
+
     /// # Safety
     ///
     /// Calling this method with an out-of-bounds of chunk is undefined behavior.
@@ -1,4 +1,7 @@
 pub trait Layout {
+    /// The layout identifier used in encoded chunks.
+    const ID: u8;
+
     fn to_index(point: (u32, u32, u32)) -> u32;
     fn to_point(index: u32) -> (u32, u32, u32);
 }
@@ -6,6 +9,8 @@ pub trait Layout {
 pub struct Straight<const X: u32, const Y: u32>;
 
 impl<const X: u32, const Y: u32> Layout for Straight<X, Y> {
+    const ID: u8 = 0;
+
     fn to_index((x, y, z): (u32, u32, u32)) -> u32 {
         z * X * Y + y * X + x
     }
@@ -18,32 +23,77 @@ impl<const X: u32, const Y: u32> Layout for Straight<X, Y> {
     }
 }
 
-pub struct Curve;
+/// The Z-order curve layout.
+///
+/// The bits of coordinates are interleaved until each of them is exhausted,
+/// so all dimensions have to be powers of two, but they may be different.
+pub struct Curve<const X: u32, const Y: u32, const Z: u32>;
+
+impl<const X: u32, const Y: u32, const Z: u32> Layout for Curve<X, Y, Z> {
+    const ID: u8 = 1;
 
-impl Layout for Curve {
     fn to_index((mut x, mut y, mut z): (u32, u32, u32)) -> u32 {
+        debug_assert!(X.is_power_of_two());
+        debug_assert!(Y.is_power_of_two());
+        debug_assert!(Z.is_power_of_two());
+
+        let (mut w, mut h, mut d) = (X, Y, Z);
         let mut index = 0;
         let mut step = 0;
-        while x != 0 || y != 0 || z != 0 {
-            index |= ((x & 1) | ((y & 1) << 1) | ((z & 1) << 2)) << step;
-            step += 3;
-            x >>= 1;
-            y >>= 1;
-            z >>= 1;
+        while w > 1 || h > 1 || d > 1 {
+            if w > 1 {
+                index |= (x & 1) << step;
+                step += 1;
+                x >>= 1;
+                w >>= 1;
+            }
+
+            if h > 1 {
+                index |= (y & 1) << step;
+                step += 1;
+                y >>= 1;
+                h >>= 1;
+            }
+
+            if d > 1 {
+                index |= (z & 1) << step;
+                step += 1;
+                z >>= 1;
+                d >>= 1;
+            }
         }
 
         index
     }
 
     fn to_point(mut index: u32) -> (u32, u32, u32) {
+        debug_assert!(X.is_power_of_two());
+        debug_assert!(Y.is_power_of_two());
+        debug_assert!(Z.is_power_of_two());
+
+        let (mut w, mut h, mut d) = (X, Y, Z);
         let (mut x, mut y, mut z) = (0, 0, 0);
         let mut step = 0;
-        while index != 0 {
-            x |= (index & 0b001) << step;
-            y |= ((index & 0b010) >> 1) << step;
-            z |= ((index & 0b100) >> 2) << step;
+        while w > 1 || h > 1 || d > 1 {
+            if w > 1 {
+                x |= (index & 1) << step;
+                index >>= 1;
+                w >>= 1;
+            }
+
+            if h > 1 {
+                y |= (index & 1) << step;
+                index >>= 1;
+                h >>= 1;
+            }
+
+            if d > 1 {
+                z |= (index & 1) << step;
+                index >>= 1;
+                d >>= 1;
+            }
+
             step += 1;
-            index >>= 3;
         }
 
         (x, y, z)
@@ -122,14 +172,31 @@ mod tests {
     #[test]
     fn curve_to_point() {
         for (index, point) in zip(0.., CURVE_ORDER) {
-            assert_eq!(Curve::to_point(index), point);
+            assert_eq!(Curve::<4, 4, 2>::to_point(index), point);
         }
     }
 
     #[test]
     fn curve_to_index() {
         for (index, point) in zip(0.., CURVE_ORDER) {
-            assert_eq!(Curve::to_index(point), index);
+            assert_eq!(Curve::<4, 4, 2>::to_index(point), index);
+        }
+    }
+
+    #[test]
+    fn curve_bijection() {
+        type L = Curve<16, 32, 16>;
+
+        let mut seen = vec![false; 16 * 32 * 16];
+        for z in 0..16 {
+            for y in 0..32 {
+                for x in 0..16 {
+                    let index = L::to_index((x, y, z));
+                    assert!(!seen[index as usize]);
+                    seen[index as usize] = true;
+                    assert_eq!(L::to_point(index), (x, y, z));
+                }
+            }
         }
     }
 }
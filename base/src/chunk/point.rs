@@ -45,7 +45,7 @@ impl InnerPoint {
                 if v < WIDTH {
                     Ok((v, y, z))
                 } else {
-                    Err((WIDTH - v, y, z))
+                    Err((v - WIDTH, y, z))
                 }
             }
             Side::Right => {
@@ -62,7 +62,7 @@ impl InnerPoint {
                 if v < HEIGHT {
                     Ok((x, v, z))
                 } else {
-                    Err((x, HEIGHT - v, z))
+                    Err((x, v - HEIGHT, z))
                 }
             }
             Side::Down => {
@@ -115,7 +115,7 @@ impl fmt::Debug for InnerPoint {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, crate::side::Sides};
 
     fn point(x: u8, y: u8, z: u8) -> InnerPoint {
         InnerPoint::new(x, y, z).unwrap()
@@ -147,4 +147,40 @@ mod tests {
         assert_eq!(a.to(Side::Back, 1), Ok(point(0, 0, 0)));
         assert_eq!(a.to(Side::Back, 2), Err(point(0, 0, DEPTH - 1)));
     }
+
+    #[test]
+    fn to_wrap() {
+        // Every step from every coordinate lands on the same point modulo the chunk size
+        for side in Sides::all() {
+            let (size, axis, sign) = match side {
+                Side::Left => (WIDTH, 0, 1),
+                Side::Right => (WIDTH, 0, -1),
+                Side::Up => (HEIGHT, 1, 1),
+                Side::Down => (HEIGHT, 1, -1),
+                Side::Forth => (DEPTH, 2, 1),
+                Side::Back => (DEPTH, 2, -1),
+            };
+
+            for v in 0..size {
+                for n in 0..size {
+                    let mut coords = [0; 3];
+                    coords[axis] = v;
+                    let a = point(coords[0], coords[1], coords[2]);
+
+                    let target = i32::from(v) + sign * i32::from(n);
+                    coords[axis] = target.rem_euclid(i32::from(size)) as u8;
+                    let b = point(coords[0], coords[1], coords[2]);
+                    if (0..i32::from(size)).contains(&target) {
+                        assert_eq!(a.to(side, n), Ok(b));
+                    } else {
+                        assert_eq!(a.to(side, n), Err(b));
+                    }
+                }
+            }
+        }
+
+        let a = point(5, 20, 0);
+        assert_eq!(a.to(Side::Left, 13), Err(point(2, 20, 0)));
+        assert_eq!(a.to(Side::Up, 15), Err(point(5, 3, 0)));
+    }
 }
@@ -0,0 +1,292 @@
+use {
+    crate::{
+        chunk::{
+            layout::{Layout, Straight},
+            size, ChunkData,
+        },
+        point::{BlockPoint, ChunkPoint, WorldPoint},
+        raycast::{self, Hit, Ray},
+    },
+    fxhash::FxHashMap as Map,
+    std::ops,
+};
+
+/// Loaded chunks of a world.
+///
+/// Blocks are indexed by `WorldPoint`, indexing a block of an unloaded chunk panics.
+pub struct Cluster<T, L = Straight<{ size::WIDTH }, { size::HEIGHT }>> {
+    chunks: Map<ChunkPoint, ChunkData<T, L>>,
+}
+
+impl<T, L> Cluster<T, L> {
+    pub fn new() -> Self {
+        Self {
+            chunks: Map::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn contains(&self, point: ChunkPoint) -> bool {
+        self.chunks.contains_key(&point)
+    }
+
+    pub fn get(&self, point: ChunkPoint) -> Option<&ChunkData<T, L>> {
+        self.chunks.get(&point)
+    }
+
+    pub fn get_mut(&mut self, point: ChunkPoint) -> Option<&mut ChunkData<T, L>> {
+        self.chunks.get_mut(&point)
+    }
+
+    /// Returns the chunk at the `point` or creates it with the `create` function.
+    pub fn get_or_insert_with<F>(&mut self, point: ChunkPoint, create: F) -> &mut ChunkData<T, L>
+    where
+        F: FnOnce() -> ChunkData<T, L>,
+    {
+        self.chunks.entry(point).or_insert_with(create)
+    }
+
+    /// Returns the chunk at the `point` or creates it filled with the `val`.
+    pub fn get_or_create(&mut self, point: ChunkPoint, val: T) -> &mut ChunkData<T, L>
+    where
+        T: Copy,
+        L: Layout,
+    {
+        self.get_or_insert_with(point, || ChunkData::new(val))
+    }
+
+    /// Inserts the chunk and returns the replaced one.
+    pub fn insert(&mut self, point: ChunkPoint, chunk: ChunkData<T, L>) -> Option<ChunkData<T, L>> {
+        self.chunks.insert(point, chunk)
+    }
+
+    pub fn remove(&mut self, point: ChunkPoint) -> Option<ChunkData<T, L>> {
+        self.chunks.remove(&point)
+    }
+
+    /// Iterates over loaded chunks in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkPoint, &ChunkData<T, L>)> {
+        self.chunks.iter().map(|(&point, chunk)| (point, chunk))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ChunkPoint, &mut ChunkData<T, L>)> {
+        self.chunks.iter_mut().map(|(&point, chunk)| (point, chunk))
+    }
+
+    /// Returns the block at the `point` or `None` if its chunk isn't loaded.
+    pub fn block(&self, point: WorldPoint) -> Option<&T>
+    where
+        L: Layout,
+    {
+        self.get(point.chunk_point())
+            .map(|chunk| &chunk[point.block_point()])
+    }
+
+    pub fn block_mut(&mut self, point: WorldPoint) -> Option<&mut T>
+    where
+        L: Layout,
+    {
+        self.get_mut(point.chunk_point())
+            .map(|chunk| &mut chunk[point.block_point()])
+    }
+
+    /// Iterates over loaded blocks in the box of absolute coordinates from `min`
+    /// to `max` inclusive.
+    ///
+    /// Blocks of unloaded chunks and blocks outside of the world are skipped.
+    pub fn blocks(
+        &self,
+        min: (i32, i32, i32),
+        max: (i32, i32, i32),
+    ) -> impl Iterator<Item = (WorldPoint, &T)> + '_
+    where
+        L: Layout,
+    {
+        const SIZE: (i32, i32, i32) = (size::WIDTH as i32, size::HEIGHT as i32, size::DEPTH as i32);
+
+        // Ranges of chunk coordinates which intersect the box
+        let chunks = |min: i32, max: i32, size: i32| {
+            let lo = min.div_euclid(size).max(i32::from(i8::MIN) + 1);
+            let hi = max.div_euclid(size).min(i32::from(i8::MAX));
+            (lo..=hi).map(|v| v as i8)
+        };
+
+        // The range of block coordinates of the chunk `v` which is inside the box
+        let blocks = |v: i8, min: i32, max: i32, size: i32| {
+            let start = i32::from(v) * size;
+            let lo = (min - start).max(0);
+            let hi = (max - start).min(size - 1);
+            (lo..=hi).map(|v| v as u8)
+        };
+
+        chunks(min.0, max.0, SIZE.0)
+            .flat_map(move |x| chunks(min.1, max.1, SIZE.1).map(move |y| (x, y)))
+            .flat_map(move |(x, y)| chunks(min.2, max.2, SIZE.2).map(move |z| (x, y, z)))
+            .filter_map(|(x, y, z)| {
+                let point = ChunkPoint::new(x, y, z)?;
+                Some((point, self.get(point)?))
+            })
+            .flat_map(move |(point, chunk)| {
+                let (x, y, z) = point.into();
+                blocks(z, min.2, max.2, SIZE.2).flat_map(move |bz| {
+                    blocks(y, min.1, max.1, SIZE.1).flat_map(move |by| {
+                        blocks(x, min.0, max.0, SIZE.0).map(move |bx| {
+                            let block = BlockPoint::new(bx, by, bz).expect("block point");
+                            (WorldPoint::new(block, point), &chunk[block])
+                        })
+                    })
+                })
+            })
+    }
+
+    /// Casts the `ray` up to the `max` distance and returns the first loaded block
+    /// for which `solid` returns `true`.
+    ///
+    /// Blocks of unloaded chunks are passed through.
+    pub fn raycast<S>(&self, ray: Ray, max: f32, mut solid: S) -> Option<Hit>
+    where
+        L: Layout,
+        S: FnMut(&T) -> bool,
+    {
+        raycast::cast(ray, max, |point| self.block(point).is_some_and(&mut solid))
+    }
+}
+
+impl<T, L> Default for Cluster<T, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, L> ops::Index<WorldPoint> for Cluster<T, L>
+where
+    L: Layout,
+{
+    type Output = T;
+
+    fn index(&self, point: WorldPoint) -> &Self::Output {
+        self.block(point).expect("the chunk is not loaded")
+    }
+}
+
+impl<T, L> ops::IndexMut<WorldPoint> for Cluster<T, L>
+where
+    L: Layout,
+{
+    fn index_mut(&mut self, point: WorldPoint) -> &mut Self::Output {
+        self.block_mut(point).expect("the chunk is not loaded")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(x: i8, y: i8, z: i8) -> ChunkPoint {
+        ChunkPoint::new(x, y, z).unwrap()
+    }
+
+    fn world(x: i32, y: i32, z: i32) -> WorldPoint {
+        WorldPoint::from_absolute(x, y, z).unwrap()
+    }
+
+    #[test]
+    fn chunks() {
+        let mut cluster = Cluster::<u8>::new();
+        assert!(cluster.is_empty());
+        assert!(cluster.insert(chunk(0, 0, 0), ChunkData::new(1)).is_none());
+        assert!(cluster.insert(chunk(0, 0, 0), ChunkData::new(2)).is_some());
+        assert_eq!(cluster[world(3, 4, 5)], 2);
+
+        // The existing chunk is kept
+        cluster.get_or_create(chunk(0, 0, 0), 3);
+        assert_eq!(cluster[world(3, 4, 5)], 2);
+
+        cluster.get_or_create(chunk(-1, 0, 0), 3);
+        assert_eq!(cluster[world(-1, 0, 0)], 3);
+        assert_eq!(cluster.len(), 2);
+
+        let mut points: Vec<(i8, i8, i8)> = cluster.iter().map(|(point, _)| point.into()).collect();
+        points.sort_unstable();
+        assert_eq!(points, [(-1, 0, 0), (0, 0, 0)]);
+
+        assert!(cluster.remove(chunk(-1, 0, 0)).is_some());
+        assert!(!cluster.contains(chunk(-1, 0, 0)));
+        assert!(cluster.block(world(-1, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn index() {
+        let mut cluster = Cluster::<u8>::new();
+        cluster.get_or_create(chunk(-1, -1, 2), 0);
+
+        let point = world(-16, -1, 40);
+        cluster[point] = 7;
+        assert_eq!(cluster[point], 7);
+        assert_eq!(cluster.block(point), Some(&7));
+        assert_eq!(cluster[world(-15, -1, 40)], 0);
+        assert_eq!(
+            cluster.get(chunk(-1, -1, 2)).unwrap()[point.block_point()],
+            7
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the chunk is not loaded")]
+    fn index_unloaded() {
+        let cluster = Cluster::<u8>::new();
+        let _ = cluster[world(0, 0, 0)];
+    }
+
+    #[test]
+    fn blocks() {
+        let mut cluster = Cluster::<u8>::new();
+        cluster.get_or_create(chunk(0, 0, 0), 1);
+        cluster.get_or_create(chunk(-1, 0, 0), 2);
+
+        // The box crosses both chunks and an unloaded one above them
+        let (min, max) = ((-2, 30, 3), (1, 33, 4));
+        let blocks: Vec<_> = cluster.blocks(min, max).collect();
+        assert_eq!(blocks.len(), 4 * 2 * 2);
+        for (point, &block) in blocks {
+            let (x, y, z) = point.absolute();
+            assert!((min.0..=max.0).contains(&x));
+            assert!((30..=31).contains(&y));
+            assert!((min.2..=max.2).contains(&z));
+            assert_eq!(block, if x < 0 { 2 } else { 1 });
+        }
+
+        // An empty box
+        assert_eq!(cluster.blocks((1, 0, 0), (0, 0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn raycast() {
+        use crate::side::Side;
+
+        let mut cluster = Cluster::<u8>::new();
+        cluster.get_or_create(chunk(0, 0, 0), 0);
+        cluster.get_or_create(chunk(0, 0, 1), 0);
+        cluster[world(4, 4, 20)] = 1;
+
+        // The ray passes the unloaded chunk below and hits the block
+        let ray = Ray {
+            origin: [4.5, -40., 20.5],
+            dir: [0., 1., 0.],
+        };
+
+        let hit = cluster.raycast(ray, 100., |&block| block != 0).unwrap();
+        assert_eq!(hit.point, world(4, 4, 20));
+        assert_eq!(hit.side, Some(Side::Down));
+        assert_eq!(hit.distance, 44.);
+
+        assert!(cluster.raycast(ray, 40., |&block| block != 0).is_none());
+    }
+}
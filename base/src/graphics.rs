@@ -1,20 +1,42 @@
 use {
     bytemuck::{Pod, Zeroable},
+    serde::Deserialize,
     std::num::NonZeroU32,
 };
 
 pub type Size = (NonZeroU32, NonZeroU32);
 
+#[derive(Clone, Copy)]
 pub struct MeshData<'a> {
     pub verts: &'a [Vert],
     pub faces: &'a [Face],
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[derive(Clone, Copy, Debug, Deserialize, Pod, Zeroable)]
 pub struct Vert {
     pub pos: [f32; 3],
     pub tex: [f32; 2],
+    /// The brightness of the vertex from 0 to 1, including ambient occlusion.
+    #[serde(default = "Vert::full_light")]
+    pub light: f32,
+    /// The sprite rect in the texture as `[x, y, width, height]`.
+    ///
+    /// Texture coordinates wrap inside of the rect, it's the whole texture by default.
+    #[serde(default = "Vert::full_rect")]
+    pub rect: [f32; 4],
+}
+
+impl Vert {
+    pub const FULL_RECT: [f32; 4] = [0., 0., 1., 1.];
+
+    const fn full_light() -> f32 {
+        1.
+    }
+
+    const fn full_rect() -> [f32; 4] {
+        Self::FULL_RECT
+    }
 }
 
 impl AsBytes for [Vert] {
@@ -25,6 +47,15 @@ impl AsBytes for [Vert] {
 
 pub type Face = [u16; 3];
 
+/// A column major 4x4 matrix.
+pub type Mat = [[f32; 4]; 4];
+
+impl AsBytes for Mat {
+    fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(self)
+    }
+}
+
 impl AsBytes for [Face] {
     fn as_bytes(&self) -> &[u8] {
         bytemuck::cast_slice(self)
@@ -35,6 +66,22 @@ impl AsBytes for [Face] {
 pub struct TextureData<'a> {
     pub bytes: &'a [u8],
     pub size: (u32, u32),
+    /// Bytes of mip levels after the base one, each is half the size of the previous.
+    pub mipmaps: &'a [&'a [u8]],
+    pub filter: Filter,
+}
+
+/// A texture filtering.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Filter {
+    /// Texels are never blended.
+    #[default]
+    Nearest,
+    /// Texels of a level are never blended, but adjacent mip levels are.
+    ///
+    /// It keeps blocks sharp, prevents shimmering of distant ones
+    /// and never blends neighbour sprites of an atlas.
+    Mipmap,
 }
 
 pub trait AsBytes {
@@ -1,7 +1,17 @@
 mod asset;
+mod hash;
+mod json;
+pub mod model;
 mod resources;
+pub mod shape;
+mod sprites;
+pub mod tile;
 
 pub use self::{
     asset::{Asset, Kind},
+    hash::{Hash, ParseError as ParseHashError},
+    json::{from_str as from_json, Error as JsonError},
+    model::Model,
     resources::{Key, ParseError as ParseKeyError, Resources},
+    sprites::Sprites,
 };
@@ -16,6 +16,7 @@ impl Asset {
 
         let kind = match kind {
             "tiles" => Kind::Tile,
+            "shapes" => Kind::Shape,
             _ => return None,
         };
 
@@ -28,4 +29,5 @@ impl Asset {
 
 pub enum Kind {
     Tile,
+    Shape,
 }
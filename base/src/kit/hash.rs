@@ -0,0 +1,98 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::{fmt, str},
+};
+
+/// A content hash of a kit.
+///
+/// It's a 64-bit FNV-1a hash which is stable between builds and platforms,
+/// so it can be stored on disk and sent over the network.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hash(u64);
+
+impl Hash {
+    pub fn new(bytes: &[u8]) -> Self {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let hash = bytes
+            .iter()
+            .fold(OFFSET, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(PRIME));
+
+        Self(hash)
+    }
+
+    pub const fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl str::FromStr for Hash {
+    type Err = ();
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        if src.len() != 16 {
+            return Err(());
+        }
+
+        u64::from_str_radix(src, 16).map(Self).map_err(drop)
+    }
+}
+
+impl TryFrom<String> for Hash {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse().map_err(|_| ParseError(value))
+    }
+}
+
+impl From<Hash> for String {
+    fn from(hash: Hash) -> Self {
+        hash.to_string()
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid hash {}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known() {
+        assert_eq!(Hash::new(b"").get(), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Hash::new(b"a").get(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn parse() {
+        let hash = Hash::new(b"germina");
+        assert_eq!(hash.to_string().parse(), Ok(hash));
+        assert_eq!("".parse::<Hash>(), Err(()));
+        assert_eq!("zz".parse::<Hash>(), Err(()));
+    }
+}
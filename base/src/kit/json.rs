@@ -0,0 +1,110 @@
+use {serde::de::DeserializeOwned, std::fmt};
+
+/// Parses a JSON `src` of a kit file.
+/// The `filename` is used only to describe an error.
+pub fn from_str<T>(src: &str, filename: Option<&str>) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    json::from_str(src).map_err(|err| Error {
+        err,
+        src: src.to_owned(),
+        filename: filename.map(str::to_owned),
+    })
+}
+
+/// A JSON parsing error with the source to point where it happened.
+///
+/// Its `Display` writes plain text, a terminal can style the error
+/// from its parts instead.
+pub struct Error {
+    pub err: json::Error,
+    pub src: String,
+    pub filename: Option<String>,
+}
+
+impl Error {
+    /// The number of source lines shown up to the error.
+    const SHOW_LINES: usize = 3;
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Returns the line and the column where the error happened.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        let json::Error::Message { location, .. } = &self.err;
+        location
+            .as_ref()
+            .filter(|_| !self.src.trim().is_empty())
+            .map(|location| (location.line, location.column))
+    }
+
+    /// Returns the source lines up to the error line,
+    /// or nothing if the location is unknown.
+    pub fn excerpt(&self) -> impl Iterator<Item = &str> {
+        let line = self.location().map_or(0, |(line, _)| line);
+        let start = line.saturating_sub(Self::SHOW_LINES);
+        self.src
+            .lines()
+            .skip(start)
+            .take(line.min(Self::SHOW_LINES))
+    }
+
+    /// Returns the error message without the parser's format.
+    pub fn message(&self) -> &str {
+        let json::Error::Message { msg, .. } = &self.err;
+
+        // Trim pest error format
+        msg.rsplit_once('=')
+            .map_or_else(|| msg.as_str(), |(_, right)| right.trim())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "while parsing")?;
+        if let Some(filename) = self.filename() {
+            write!(f, " {filename}")?;
+        }
+
+        match self.location() {
+            Some((line, column)) => {
+                writeln!(f, " at line {line}:")?;
+                for line in self.excerpt() {
+                    writeln!(f, "{line}")?;
+                }
+
+                writeln!(f, "{:>column$}^", "")?;
+            }
+            None => writeln!(f, ":")?,
+        }
+
+        write!(f, "{}", self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::collections::HashMap};
+
+    #[test]
+    fn plain() {
+        let src = "{\n  a: 1,\n  b: ]\n}";
+        let err = from_str::<HashMap<String, u32>>(src, Some("tiles/t.json"))
+            .err()
+            .unwrap();
+
+        assert_eq!(err.filename(), Some("tiles/t.json"));
+        let (line, _) = err.location().unwrap();
+        assert_eq!(line, 3);
+        assert_eq!(
+            err.excerpt().collect::<Vec<_>>(),
+            ["{", "  a: 1,", "  b: ]"]
+        );
+
+        let text = err.to_string();
+        assert!(text.starts_with("while parsing tiles/t.json at line 3:\n"));
+        assert!(!text.contains('\u{1b}'));
+    }
+}
@@ -0,0 +1,347 @@
+mod palette;
+pub mod tile;
+
+pub use self::palette::{Palette, PaletteBlock};
+
+use {
+    crate::{
+        kit::{
+            self,
+            model::tile::{Block, Tile},
+            shape::{self, Shape},
+            Asset, JsonError, Key, Kind, Resources, Sprites,
+        },
+        sprite,
+    },
+    fxhash::FxHashSet,
+    image::ImageError,
+    std::{
+        fmt,
+        io::{self, Cursor, Read},
+        rc::Rc,
+    },
+    zip::{result::ZipError, ZipArchive},
+};
+
+/// Pixels which repeat the border around every sprite of the kit atlas.
+///
+/// The atlas is mipmapped and it keeps mip levels of sprites apart.
+const EXTRUDE: u16 = 4;
+
+/// A compiled kit.
+pub struct Model {
+    pub tiles: Resources<Tile>,
+    /// Shapes of the kit, they're shared with blocks of tiles.
+    pub shapes: Resources<Rc<Shape>>,
+    pub tile_sprites: Sprites,
+}
+
+impl Model {
+    /// Loads the model from a kit `archive`.
+    ///
+    /// The `skipped` callback is called with the path of every entry which isn't
+    /// a tile, a shape or a sprite used by tiles.
+    pub fn load<F>(archive: &[u8], mut skipped: F) -> Result<Self, Error>
+    where
+        F: FnMut(&str),
+    {
+        let mut sources = vec![];
+        let mut others = vec![];
+        let mut shapes = Resources::default();
+        let mut arch = ZipArchive::new(Cursor::new(archive))?;
+        let mut content = String::with_capacity(128);
+
+        for i in 0..arch.len() {
+            let mut file = arch.by_index(i)?;
+            if !file.is_file() {
+                continue;
+            }
+
+            let Asset { name, kind } = match Asset::parse_path(file.name()) {
+                Some(asset) => asset,
+                None => {
+                    others.push(file.name().to_owned());
+                    continue;
+                }
+            };
+
+            content.clear();
+            file.read_to_string(&mut content)?;
+            match kind {
+                Kind::Tile => {
+                    let source: kit::tile::Tile = kit::from_json(&content, Some(file.name()))?;
+                    sources.push((name, file.name().to_owned(), source));
+                }
+                Kind::Shape => {
+                    let shape: Shape = kit::from_json(&content, Some(file.name()))?;
+                    shape.validate().map_err(|err| Error::Shape {
+                        err,
+                        filename: file.name().into(),
+                    })?;
+
+                    shapes.insert(name, Rc::new(shape));
+                }
+            }
+        }
+
+        // Tiles are compiled after all shapes are loaded to resolve their keys
+        let mut tiles = Resources::default();
+        for (name, filename, source) in sources {
+            let tile =
+                Tile::compile(source, &shapes).map_err(|err| Error::Tile { err, filename })?;
+
+            tiles.insert(name, tile);
+        }
+
+        // Sort keys to get a stable atlas
+        let mut sprite_keys = vec![];
+        for (_, tile) in tiles.iter() {
+            tile.sprites(|key| sprite_keys.push(key.clone()));
+        }
+
+        sprite_keys.sort_unstable();
+        sprite_keys.dedup();
+
+        let sprite_paths: Vec<_> = sprite_keys
+            .iter()
+            .map(|key| format!("sprites/tiles/{key}.png"))
+            .collect();
+
+        let used: FxHashSet<_> = sprite_paths.iter().map(String::as_str).collect();
+        for path in &others {
+            if !used.contains(path.as_str()) {
+                skipped(path);
+            }
+        }
+
+        let mut sprites = Vec::with_capacity(sprite_keys.len());
+        for (key, path) in sprite_keys.into_iter().zip(sprite_paths) {
+            let mut file = arch.by_name(&path).map_err(|err| match err {
+                ZipError::FileNotFound => Error::NoSprite(path.clone()),
+                err => err.into(),
+            })?;
+
+            let mut buf = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut buf)?;
+            let image = image::load_from_memory_with_format(&buf, image::ImageFormat::Png)
+                .map_err(|err| Error::Sprite { err, path })?;
+
+            sprites.push((key, image.to_rgba8()));
+        }
+
+        Ok(Self {
+            tiles,
+            shapes,
+            tile_sprites: Sprites::pack(
+                sprites,
+                sprite::Options {
+                    extrude: EXTRUDE,
+                    ..sprite::Options::default()
+                },
+            )?,
+        })
+    }
+
+    /// Returns tiles sorted by their keys.
+    ///
+    /// Blocks of a world made from the kit are numbered in this order.
+    pub fn sorted_tiles(&self) -> Vec<(&Key, &Tile)> {
+        let mut tiles: Vec<_> = self.tiles.iter().collect();
+        tiles.sort_unstable_by_key(|&(key, _)| key);
+        tiles
+    }
+
+    /// Returns blocks of all tiles in the order of their ids.
+    ///
+    /// The id `n` of a world made from the kit refers to the `n - 1` block.
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> {
+        self.sorted_tiles()
+            .into_iter()
+            .flat_map(|(_, tile)| tile.blocks())
+    }
+}
+
+pub enum Error {
+    Io(io::Error),
+    Json(JsonError),
+    Tile { err: tile::Error, filename: String },
+    Shape { err: shape::Error, filename: String },
+    NoSprite(String),
+    Sprite { err: ImageError, path: String },
+    Atlas(sprite::Error),
+    Arch(&'static str),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<JsonError> for Error {
+    fn from(err: JsonError) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<sprite::Error> for Error {
+    fn from(err: sprite::Error) -> Self {
+        Self::Atlas(err)
+    }
+}
+
+impl From<ZipError> for Error {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::Io(err) => err.into(),
+            ZipError::InvalidArchive(arch) | ZipError::UnsupportedArchive(arch) => Self::Arch(arch),
+            ZipError::FileNotFound => Self::Arch("file not found"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Json(json) => write!(f, "{json}"),
+            Self::Tile { err, filename } => write!(f, "in tile {filename}: {err}"),
+            Self::Shape { err, filename } => write!(f, "in shape {filename}: {err}"),
+            Self::NoSprite(path) => write!(f, "the sprite {path} is not found"),
+            Self::Sprite { err, path } => write!(f, "failed to decode sprite {path}: {err}"),
+            Self::Atlas(err) => write!(f, "failed to build a sprite atlas: {err}"),
+            Self::Arch(arch) => write!(f, "archive error: {arch}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            chunk::ChunkData,
+            mesher::{self, Mode, Neighbours, Pass},
+            point::BlockPoint,
+        },
+        image::{ImageOutputFormat, Rgba, RgbaImage},
+        std::io::Write,
+        zip::{write::FileOptions, ZipWriter},
+    };
+
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut arch = ZipWriter::new(Cursor::new(vec![]));
+        for &(path, content) in files {
+            arch.start_file(path, FileOptions::default()).unwrap();
+            arch.write_all(content).unwrap();
+        }
+
+        arch.finish().unwrap().into_inner()
+    }
+
+    fn png(color: Rgba<u8>) -> Vec<u8> {
+        let mut buf = Cursor::new(vec![]);
+        RgbaImage::from_pixel(16, 16, color)
+            .write_to(&mut buf, ImageOutputFormat::Png)
+            .unwrap();
+
+        buf.into_inner()
+    }
+
+    const TILE: &str = "{
+        layout: ['a', 'b'],
+        blocks: {
+            a: { shape: { id: 1, sprites: { name: 'x', offset: [0.5, 0] } } },
+            b: { shape: { id: 'quad', sprites: { name: 'y', discard: true } } },
+        },
+    }";
+
+    const QUAD: &str = "{
+        parts: [{
+            verts: [
+                { pos: [0, 0.5, 0], tex: [0, 0] },
+                { pos: [0, 0.5, 1], tex: [0, 1] },
+                { pos: [1, 0.5, 1], tex: [1, 1] },
+                { pos: [1, 0.5, 0], tex: [1, 0] },
+            ],
+            faces: [[0, 1, 2], [0, 2, 3]],
+        }],
+    }";
+
+    #[test]
+    fn load() {
+        let (x, y) = (png(Rgba([255, 0, 0, 255])), png(Rgba([0, 255, 0, 0])));
+        let arch = archive(&[
+            ("tiles/t.json", TILE.as_bytes()),
+            ("shapes/quad.json", QUAD.as_bytes()),
+            ("sprites/tiles/x.png", &x),
+            ("sprites/tiles/y.png", &y),
+            ("readme.txt", b"skipped"),
+        ]);
+
+        let mut skipped = vec![];
+        let model = Model::load(&arch, |path| skipped.push(path.to_owned()))
+            .unwrap_or_else(|err| panic!("{err}"));
+        assert_eq!(skipped, ["readme.txt"]);
+        assert_eq!(model.blocks().count(), 2);
+        assert!(model.shapes.get("quad").is_some());
+
+        let no_shape = archive(&[("tiles/t.json", TILE.as_bytes())]);
+        assert!(matches!(
+            Model::load(&no_shape, |_| {}),
+            Err(Error::Tile {
+                err: tile::Error::UnknownShape(_),
+                ..
+            }),
+        ));
+
+        let no_sprites = archive(&[
+            ("tiles/t.json", TILE.as_bytes()),
+            ("shapes/quad.json", QUAD.as_bytes()),
+        ]);
+        assert!(matches!(
+            Model::load(&no_sprites, |_| {}),
+            Err(Error::NoSprite(path)) if path == "sprites/tiles/x.png",
+        ));
+    }
+
+    #[test]
+    fn palette() {
+        let (x, y) = (png(Rgba([255, 0, 0, 255])), png(Rgba([0, 255, 0, 0])));
+        let arch = archive(&[
+            ("tiles/t.json", TILE.as_bytes()),
+            ("shapes/quad.json", QUAD.as_bytes()),
+            ("sprites/tiles/x.png", &x),
+            ("sprites/tiles/y.png", &y),
+        ]);
+
+        let model = Model::load(&arch, |_| {}).unwrap_or_else(|err| panic!("{err}"));
+        let palette = Palette::new(&model);
+        let atlas = &model.tile_sprites;
+
+        let mut chunk: ChunkData<_> = ChunkData::new(palette.get(0));
+        chunk[BlockPoint::new(0, 0, 0).unwrap()] = palette.get(1);
+        chunk[BlockPoint::new(2, 0, 0).unwrap()] = palette.get(2);
+        chunk[BlockPoint::new(4, 0, 0).unwrap()] = palette.get(3);
+
+        let buffers = mesher::mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        assert_eq!(buffers.len(), 2);
+
+        // The cube samples the shifted sprite inside of its atlas rect
+        let solid = &buffers[0];
+        assert_eq!(solid.pass(), Pass::Solid);
+        assert_eq!(solid.faces().len(), 12);
+        for vert in solid.verts() {
+            assert_eq!(vert.rect, atlas.uv("x").unwrap().rect());
+            assert!((0.5..=1.5).contains(&vert.tex[0]));
+        }
+
+        let cutout = &buffers[1];
+        assert_eq!(cutout.pass(), Pass::Cutout);
+        assert_eq!(cutout.faces().len(), 2);
+        for vert in cutout.verts() {
+            assert_eq!(vert.rect, atlas.uv("y").unwrap().rect());
+            assert!(vert.pos[0] >= 2. && vert.pos[0] <= 3.);
+        }
+    }
+}
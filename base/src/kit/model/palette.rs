@@ -0,0 +1,85 @@
+use crate::{
+    kit::model::{tile::BlockShape, Model},
+    mesher::{Block, ShapeRef, Sprite},
+    side::Sides,
+    transform::Transform,
+};
+
+/// Blocks of a world made from the kit, ready to be meshed.
+///
+/// The id `n` refers to the `n - 1` block of the model and zero is an empty place.
+pub struct Palette {
+    blocks: Vec<Entry>,
+}
+
+struct Entry {
+    shape: BlockShape,
+    transform: Transform,
+    covers: Sides,
+    /// Sprites of shape faces in order.
+    sprites: Vec<Option<Sprite>>,
+}
+
+impl Palette {
+    /// Creates the palette of the `model`, sprites are placed in its atlas.
+    ///
+    /// A face whose sprite isn't in the atlas has no sprite.
+    pub fn new(model: &Model) -> Self {
+        let atlas = &model.tile_sprites;
+        let blocks = model
+            .blocks()
+            .map(|block| {
+                let shape = block.shape.get();
+                let sprites = (0..shape.faces().count())
+                    .map(|face| {
+                        let ptr = block.sprites.get(face);
+                        let key = ptr.key()?;
+                        let id = u32::try_from(atlas.index(key)?).expect("cast");
+                        ptr.to_sprite(id, atlas.uv(key)?)
+                    })
+                    .collect();
+
+                Entry {
+                    shape: block.shape.clone(),
+                    transform: block.transform,
+                    covers: block.transform.sides(shape.covers()),
+                    sprites,
+                }
+            })
+            .collect();
+
+        Self { blocks }
+    }
+
+    /// Returns the block by its id. An unknown id is an empty place.
+    pub fn get(&self, id: u16) -> PaletteBlock<'_> {
+        let entry = usize::from(id)
+            .checked_sub(1)
+            .and_then(|index| self.blocks.get(index));
+
+        PaletteBlock(entry)
+    }
+}
+
+/// A block of a [`Palette`].
+#[derive(Clone, Copy)]
+pub struct PaletteBlock<'a>(Option<&'a Entry>);
+
+impl Block for PaletteBlock<'_> {
+    fn shape(&self) -> Option<ShapeRef<'_>> {
+        self.0.map(|entry| entry.shape.get())
+    }
+
+    fn covers(&self) -> Sides {
+        self.0.map_or(Sides::empty(), |entry| entry.covers)
+    }
+
+    fn transform(&self) -> Transform {
+        self.0.map_or(Transform::IDENTITY, |entry| entry.transform)
+    }
+
+    fn sprite(&self, face: usize) -> Option<Sprite> {
+        self.0
+            .and_then(|entry| entry.sprites.get(face).copied().flatten())
+    }
+}
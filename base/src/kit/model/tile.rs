@@ -0,0 +1,385 @@
+use {
+    crate::{
+        kit::{
+            shape::Shape,
+            tile::{self, BlockPointer, Layout, ShapePointer, Sprites, Tile as Source},
+            Key, Resources,
+        },
+        mesher::ShapeRef,
+        shape::Shape as ShapeId,
+        transform::Transform,
+    },
+    std::{fmt, rc::Rc},
+};
+
+/// A compiled tile.
+///
+/// It stores a dense grid of block indices where every
+/// `BlockPointer::Key` is already resolved, as well as shapes of blocks.
+pub struct Tile {
+    size: (u32, u32, u32),
+    blocks: Vec<Block>,
+    grid: Vec<Option<u16>>,
+}
+
+impl Tile {
+    /// Compiles the tile, its kit shapes are taken from the `shapes`.
+    pub fn compile(source: Source, shapes: &Resources<Rc<Shape>>) -> Result<Self, Error> {
+        let Source {
+            layout,
+            blocks: keyed,
+        } = source;
+
+        // Sort keys to get stable block indices
+        let mut keyed: Vec<_> = keyed.into_iter().collect();
+        keyed.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut keys = Vec::with_capacity(keyed.len());
+        let mut blocks = Vec::with_capacity(keyed.len());
+        for (key, block) in keyed {
+            keys.push(key);
+            blocks.push(Block::resolve(block, shapes)?);
+        }
+
+        let rows = match layout {
+            Layout::D1(ptr) => vec![vec![ptr]],
+            Layout::D2(row) => vec![row],
+            Layout::D3(rows) => rows,
+        };
+
+        let width = rows.first().map(Vec::len).unwrap_or_default();
+        if width == 0 {
+            return Err(Error::Empty);
+        }
+
+        let mut grid = Vec::with_capacity(width * rows.len());
+        for (n, row) in rows.into_iter().enumerate() {
+            if row.len() != width {
+                return Err(Error::RaggedRow {
+                    row: n,
+                    len: row.len(),
+                    expected: width,
+                });
+            }
+
+            for ptr in row {
+                let index = match ptr {
+                    BlockPointer::None => None,
+                    BlockPointer::Key(key) => match keys.binary_search(&key) {
+                        Ok(index) => Some(index),
+                        Err(_) => return Err(Error::UnknownBlock(key)),
+                    },
+                    BlockPointer::Block(block) => {
+                        blocks.push(Block::resolve(block, shapes)?);
+                        Some(blocks.len() - 1)
+                    }
+                };
+
+                let index = index
+                    .map(u16::try_from)
+                    .transpose()
+                    .map_err(|_| Error::TooManyBlocks)?;
+
+                grid.push(index);
+            }
+        }
+
+        let width = u32::try_from(width).map_err(|_| Error::TooLarge)?;
+        let depth = u32::try_from(grid.len()).map_err(|_| Error::TooLarge)? / width;
+
+        Ok(Self {
+            size: (width, 1, depth),
+            blocks,
+            grid,
+        })
+    }
+
+    /// Returns the tile size in blocks.
+    pub fn size(&self) -> (u32, u32, u32) {
+        self.size
+    }
+
+    /// Returns all blocks of the tile including unused ones.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Returns a block index at the given point.
+    ///
+    /// Returns `None` if the point is out of the tile or the place is empty.
+    pub fn get(&self, (x, y, z): (u32, u32, u32)) -> Option<u16> {
+        let (width, height, depth) = self.size;
+        if x >= width || y >= height || z >= depth {
+            return None;
+        }
+
+        let index = (z * height + y) * width + x;
+        self.grid[index as usize]
+    }
+
+    pub fn sprites<F>(&self, mut callback: F)
+    where
+        F: FnMut(&Key),
+    {
+        for block in &self.blocks {
+            block.sprites.keys(&mut callback);
+        }
+    }
+}
+
+/// A block of a compiled tile.
+pub struct Block {
+    pub shape: BlockShape,
+    pub sprites: Sprites,
+    pub transform: Transform,
+}
+
+impl Block {
+    fn resolve(block: tile::Block, shapes: &Resources<Rc<Shape>>) -> Result<Self, Error> {
+        let tile::Block {
+            shape: tile::Shape { id, sprites },
+            transform,
+        } = block;
+
+        let shape = match id {
+            ShapePointer::Key(key) => match shapes.get(&key) {
+                Some(shape) => BlockShape::Kit(Rc::clone(shape)),
+                None => return Err(Error::UnknownShape(key)),
+            },
+            ptr @ ShapePointer::Id(_) => match ptr.builtin().expect("built-in shape") {
+                Ok(shape) => BlockShape::Builtin(shape),
+                Err(id) => return Err(Error::UnknownShapeId(id)),
+            },
+        };
+
+        Ok(Self {
+            shape,
+            sprites,
+            transform,
+        })
+    }
+}
+
+/// A shape of a block resolved at load time.
+#[derive(Clone)]
+pub enum BlockShape {
+    Builtin(ShapeId),
+    Kit(Rc<Shape>),
+}
+
+impl BlockShape {
+    pub fn get(&self) -> ShapeRef<'_> {
+        match self {
+            Self::Builtin(shape) => ShapeRef::Builtin(*shape),
+            Self::Kit(shape) => ShapeRef::Kit(shape),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Empty,
+    UnknownBlock(Key),
+    UnknownShape(Key),
+    UnknownShapeId(u32),
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    TooManyBlocks,
+    TooLarge,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the layout is empty"),
+            Self::UnknownBlock(key) => write!(f, "the block {key} is not defined"),
+            Self::UnknownShape(key) => write!(f, "the shape {key} is not defined"),
+            Self::UnknownShapeId(id) => write!(f, "the shape id {id} is unknown"),
+            Self::RaggedRow { row, len, expected } => write!(
+                f,
+                "the layout row {row} has length {len}, but {expected} expected",
+            ),
+            Self::TooManyBlocks => write!(f, "too many blocks"),
+            Self::TooLarge => write!(f, "the layout is too large"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(src: &str) -> Result<Tile, Error> {
+        let source = json::from_str(src).expect("parse");
+        let mut shapes = Resources::default();
+        for key in ["slope", "stairs"] {
+            let shape = Shape {
+                parts: vec![],
+                covers: vec![],
+            };
+
+            shapes.insert(key.parse().unwrap(), Rc::new(shape));
+        }
+
+        Tile::compile(source, &shapes)
+    }
+
+    const BLOCKS: &str = "{
+        a: { shape: { id: 0, sprites: 'x' } },
+        b: { shape: { id: 0, sprites: 'y' } },
+    }";
+
+    #[test]
+    fn single() {
+        let tile = compile(&format!("{{ layout: 'b', blocks: {BLOCKS} }}")).unwrap();
+        assert_eq!(tile.size(), (1, 1, 1));
+        assert_eq!(tile.get((0, 0, 0)), Some(1));
+        assert_eq!(tile.get((1, 0, 0)), None);
+    }
+
+    #[test]
+    fn grid() {
+        let src = format!(
+            "{{
+                layout: [
+                    ['a', null, 'b'],
+                    [{{ shape: {{ id: 0, sprites: 'z' }} }}, 'a', null],
+                ],
+                blocks: {BLOCKS},
+            }}"
+        );
+
+        let tile = compile(&src).unwrap();
+        assert_eq!(tile.size(), (3, 1, 2));
+        assert_eq!(tile.blocks().len(), 3);
+        assert_eq!(tile.get((0, 0, 0)), Some(0));
+        assert_eq!(tile.get((1, 0, 0)), None);
+        assert_eq!(tile.get((2, 0, 0)), Some(1));
+        assert_eq!(tile.get((0, 0, 1)), Some(2));
+        assert_eq!(tile.get((1, 0, 1)), Some(0));
+        assert_eq!(tile.get((2, 0, 1)), None);
+    }
+
+    #[test]
+    fn shape_keys() {
+        let src = "{
+            layout: ['a', 'b', { shape: { id: 'slope', sprites: 'z' } }],
+            blocks: {
+                a: { shape: { id: 1, sprites: 'x' } },
+                b: { shape: { id: 'stairs', sprites: 'y' } },
+            },
+        }";
+
+        let tile = compile(src).unwrap();
+        let [a, b, c] = [0, 1, 2].map(|n| tile.blocks()[n].shape.get());
+        assert!(a == ShapeRef::Builtin(ShapeId::S1));
+        assert!(matches!(b, ShapeRef::Kit(_)));
+        assert!(matches!(c, ShapeRef::Kit(_)));
+        assert!(b != c);
+
+        // Blocks share shapes of the kit
+        let src = "{
+            layout: ['a', { shape: { id: 'slope', sprites: 'y' } }],
+            blocks: { a: { shape: { id: 'slope', sprites: 'x' } } },
+        }";
+
+        let tile = compile(src).unwrap();
+        assert!(tile.blocks()[0].shape.get() == tile.blocks()[1].shape.get());
+    }
+
+    #[test]
+    fn transform() {
+        use crate::side::Side;
+
+        let src = "{
+            layout: ['a', 'b', 'c'],
+            blocks: {
+                a: { shape: { id: 4, sprites: 'x' } },
+                b: { shape: { id: 4, sprites: 'x' }, transform: { y: 1 } },
+                c: { shape: { id: 4, sprites: 'x' }, transform: { mirror: 'z', x: 2 } },
+            },
+        }";
+
+        let tile = compile(src).unwrap();
+        let [a, b, c] = [0, 1, 2].map(|n| tile.blocks()[n].transform);
+        assert!(a.is_identity());
+        assert_eq!(b.side(Side::Forth), Side::Left);
+        assert_eq!(c.side(Side::Up), Side::Down);
+        assert_eq!(c.side(Side::Forth), Side::Forth);
+        assert!(c.is_mirrored());
+    }
+
+    #[test]
+    fn sprites() {
+        use crate::sprite::Uv;
+
+        let src = "{
+            layout: ['a', 'b'],
+            blocks: {
+                a: { shape: { id: 1, sprites: ['x', { name: 'y' }, null] } },
+                b: { shape: { id: 5, sprites: { name: 'z', offset: [0.5, 0], discard: true } } },
+            },
+        }";
+
+        let tile = compile(src).unwrap();
+        let [a, b] = [0, 1].map(|n| &tile.blocks()[n].sprites);
+
+        let x = a.get(0).to_sprite(1, Uv::default()).unwrap();
+        assert_eq!(a.get(0).key().map(|key| &**key), Some("x"));
+        assert_eq!(x.offset, [0., 0.]);
+        assert!(!x.discard);
+
+        let y = a.get(1).to_sprite(2, Uv::default()).unwrap();
+        assert_eq!(y.offset, [0., 0.]);
+        assert!(!y.discard);
+        assert!(a.get(2).to_sprite(3, Uv::default()).is_none());
+        assert!(a.get(3).to_sprite(3, Uv::default()).is_none());
+
+        for face in 0..4 {
+            let z = b.get(face).to_sprite(4, Uv::default()).unwrap();
+            assert_eq!(z.id, 4);
+            assert_eq!(z.offset, [0.5, 0.]);
+            assert!(z.discard);
+        }
+    }
+
+    #[test]
+    fn unknown_block() {
+        let src = format!("{{ layout: ['a', 'c'], blocks: {BLOCKS} }}");
+        assert!(matches!(compile(&src), Err(Error::UnknownBlock(key)) if &*key == "c"));
+    }
+
+    #[test]
+    fn unknown_shape() {
+        let src = "{ layout: ['a'], blocks: { a: { shape: { id: 'arch', sprites: 'x' } } } }";
+        assert!(matches!(compile(src), Err(Error::UnknownShape(key)) if &*key == "arch"));
+    }
+
+    #[test]
+    fn unknown_shape_id() {
+        let src = "{ layout: ['a'], blocks: { a: { shape: { id: 250, sprites: 'x' } } } }";
+        assert!(matches!(compile(src), Err(Error::UnknownShapeId(250))));
+    }
+
+    #[test]
+    fn ragged_row() {
+        let src = format!("{{ layout: [['a', 'b'], ['a']], blocks: {BLOCKS} }}");
+        assert!(matches!(
+            compile(&src),
+            Err(Error::RaggedRow {
+                row: 1,
+                len: 1,
+                expected: 2,
+            }),
+        ));
+    }
+
+    #[test]
+    fn empty() {
+        let src = format!("{{ layout: [], blocks: {BLOCKS} }}");
+        assert!(matches!(compile(&src), Err(Error::Empty)));
+    }
+}
@@ -4,7 +4,7 @@ use {
     std::{borrow, fmt, ops, rc::Rc, str},
 };
 
-#[derive(Clone, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Clone, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(try_from = "String")]
 pub struct Key {
     inner: Rc<str>,
@@ -77,6 +77,7 @@ impl fmt::Debug for Key {
     }
 }
 
+#[derive(Debug)]
 pub struct ParseError(pub String);
 
 impl fmt::Display for ParseError {
@@ -0,0 +1,189 @@
+use {
+    crate::{
+        graphics::{Face, MeshData, Vert},
+        shape::Data,
+        side::{Side, Sides},
+    },
+    serde::Deserialize,
+    std::fmt,
+};
+
+/// A shape defined by a kit.
+///
+/// It's an alternative to the built-in `Shape` for models
+/// which aren't known in advance.
+#[derive(Deserialize)]
+pub struct Shape {
+    pub parts: Vec<Part>,
+    /// Sides which the shape covers completely.
+    #[serde(default)]
+    pub covers: Vec<Side>,
+}
+
+impl Shape {
+    pub fn covers(&self) -> Sides {
+        self.covers.iter().copied().collect()
+    }
+
+    /// Checks that the shape can be meshed.
+    ///
+    /// Every vertex must lie in the unit cube and every face must refer to
+    /// vertices of its part.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.parts.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        for (n, part) in self.parts.iter().enumerate() {
+            if part.verts.len() > usize::from(u16::MAX) + 1 {
+                return Err(Error::TooManyVerts { part: n });
+            }
+
+            let inside = |vert: &Vert| vert.pos.iter().all(|v| (0. ..=1.).contains(v));
+            if let Some(vert) = part.verts.iter().position(|vert| !inside(vert)) {
+                return Err(Error::OutOfBounds { part: n, vert });
+            }
+
+            let len = part.verts.len();
+            if let Some(&index) = part
+                .faces
+                .iter()
+                .flatten()
+                .find(|&&i| usize::from(i) >= len)
+            {
+                return Err(Error::WrongIndex { part: n, index });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A part of a shape.
+///
+/// All faces of a part are hidden together if it has a `side`
+/// and the neighbour on this side covers it.
+#[derive(Deserialize)]
+pub struct Part {
+    pub verts: Vec<Vert>,
+    pub faces: Vec<Face>,
+    #[serde(default)]
+    pub side: Option<Side>,
+}
+
+impl Part {
+    /// Returns the part as a face of a built-in shape.
+    pub fn data(&self) -> Data<'_> {
+        Data {
+            mesh: MeshData {
+                verts: &self.verts,
+                faces: &self.faces,
+            },
+            side: self.side,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Empty,
+    TooManyVerts { part: usize },
+    OutOfBounds { part: usize, vert: usize },
+    WrongIndex { part: usize, index: u16 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the shape has no parts"),
+            Self::TooManyVerts { part } => write!(f, "the part {part} has too many vertices"),
+            Self::OutOfBounds { part, vert } => write!(
+                f,
+                "the vertex {vert} of the part {part} is out of the block bounds",
+            ),
+            Self::WrongIndex { part, index } => {
+                write!(f, "the part {part} has no vertex with index {index}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(side: Option<Side>) -> Part {
+        Part {
+            verts: vec![
+                Vert {
+                    pos: [0., 1., 1.],
+                    tex: [0., 1.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
+                },
+                Vert {
+                    pos: [1., 1., 1.],
+                    tex: [1., 1.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
+                },
+                Vert {
+                    pos: [1., 1., 0.],
+                    tex: [1., 0.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
+                },
+                Vert {
+                    pos: [0., 1., 0.],
+                    tex: [0., 0.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
+                },
+            ],
+            faces: vec![[0, 1, 2], [0, 2, 3]],
+            side,
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let shape = Shape {
+            parts: vec![quad(Some(Side::Up)), quad(None)],
+            covers: vec![Side::Up],
+        };
+
+        assert!(shape.validate().is_ok());
+        assert_eq!(shape.covers(), Side::Up.into());
+
+        let shape = Shape {
+            parts: vec![],
+            covers: vec![],
+        };
+
+        assert!(matches!(shape.validate(), Err(Error::Empty)));
+
+        let mut part = quad(None);
+        part.verts[2].pos[1] = 1.5;
+        let shape = Shape {
+            parts: vec![quad(None), part],
+            covers: vec![],
+        };
+
+        assert!(matches!(
+            shape.validate(),
+            Err(Error::OutOfBounds { part: 1, vert: 2 }),
+        ));
+
+        let mut part = quad(None);
+        part.faces.push([2, 3, 4]);
+        let shape = Shape {
+            parts: vec![part],
+            covers: vec![],
+        };
+
+        assert!(matches!(
+            shape.validate(),
+            Err(Error::WrongIndex { part: 0, index: 4 }),
+        ));
+    }
+}
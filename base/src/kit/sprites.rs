@@ -0,0 +1,131 @@
+use {
+    crate::{
+        kit::{Key, Resources},
+        sprite::{mipmap, Error, Options, Rect, SpriteMap, Uv},
+    },
+    image::RgbaImage as Image,
+};
+
+/// Sprites of a kit packed into atlas pages.
+pub struct Sprites {
+    map: SpriteMap,
+    indices: Resources<usize>,
+}
+
+impl Sprites {
+    /// Packs the sprites in the given order, so the same sprites
+    /// always make the same atlas.
+    pub fn pack(sprites: Vec<(Key, Image)>, options: Options) -> Result<Self, Error> {
+        use image::GenericImageView;
+
+        let views: Vec<_> = sprites
+            .iter()
+            .map(|(_, image)| image.view(0, 0, image.width(), image.height()))
+            .collect();
+
+        let map = SpriteMap::new(&views, options)?;
+        let mut indices = Resources::default();
+        for (index, (key, _)) in sprites.into_iter().enumerate() {
+            indices.insert(key, index);
+        }
+
+        Ok(Self { map, indices })
+    }
+
+    /// Returns the index of the sprite in the packing order.
+    pub fn index(&self, key: &str) -> Option<usize> {
+        self.indices.get(key).copied()
+    }
+
+    /// Returns the sprite rect in its atlas page.
+    pub fn rect(&self, key: &str) -> Option<Rect> {
+        self.indices.get(key).map(|&index| self.map.rects[index])
+    }
+
+    /// Returns normalized texture coordinates of the sprite in its atlas page.
+    pub fn uv(&self, key: &str) -> Option<Uv> {
+        self.indices.get(key).and_then(|&index| self.map.uv(index))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.indices.iter().map(|(key, _)| key)
+    }
+
+    pub fn pages(&self) -> &[Image] {
+        &self.map.pages
+    }
+
+    /// Generates mip levels of the atlas page after the base one.
+    ///
+    /// A texel of the level `n` spans `2^n` pixels of a sprite, so only levels
+    /// which don't reach neighbour sprites past the extrusion are generated.
+    pub fn mipmaps(&self, page: usize) -> Vec<Image> {
+        let extrude = u32::from(self.map.extrude());
+        let size = self.map.pages[page].dimensions();
+        let levels = mipmap::levels((extrude + 1, 1)).min(mipmap::levels(size));
+        self.map.mipmaps(page, levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, image::Rgba};
+
+    #[test]
+    fn lookup() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        let sprites = Sprites::pack(
+            vec![
+                ("red".parse().unwrap(), Image::from_pixel(16, 16, red)),
+                ("blue".parse().unwrap(), Image::from_pixel(8, 16, blue)),
+            ],
+            Options::default(),
+        )
+        .unwrap();
+
+        assert_eq!(sprites.keys().count(), 2);
+        assert_eq!(sprites.pages().len(), 1);
+        assert_eq!(sprites.pages()[0].dimensions(), (32, 16));
+        assert!(sprites.rect("green").is_none());
+        assert!(sprites.uv("green").is_none());
+
+        for (key, color) in [("red", red), ("blue", blue)] {
+            let Rect {
+                pos: (x, y),
+                size,
+                page,
+            } = sprites.rect(key).unwrap();
+
+            assert_eq!(size.1, 16);
+            assert_eq!(page, 0);
+            assert_eq!(sprites.pages()[0].get_pixel(x.into(), y.into()), &color);
+
+            let uv = sprites.uv(key).unwrap();
+            assert_eq!(uv.map([0., 0.]), [f32::from(x) / 32., f32::from(y) / 16.]);
+            assert_eq!(uv.size, [f32::from(size.0) / 32., 1.]);
+        }
+    }
+
+    #[test]
+    fn mipmaps() {
+        let pack = |extrude| {
+            let image = Image::from_pixel(16, 16, Rgba([255, 0, 0, 255]));
+            let options = Options {
+                extrude,
+                ..Options::default()
+            };
+
+            Sprites::pack(vec![("red".parse().unwrap(), image)], options).unwrap()
+        };
+
+        assert!(pack(0).mipmaps(0).is_empty());
+        assert_eq!(pack(1).mipmaps(0).len(), 1);
+
+        let sprites = pack(4);
+        let mips = sprites.mipmaps(0);
+        let (width, height) = sprites.pages()[0].dimensions();
+        assert_eq!(mips.len(), 2);
+        assert_eq!(mips[1].dimensions(), (width / 4, height / 4));
+    }
+}
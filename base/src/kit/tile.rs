@@ -0,0 +1,243 @@
+use {
+    crate::{kit::Key, mesher::Sprite, shape::Shape as ShapeId, sprite::Uv, transform::Transform},
+    fxhash::FxHashMap as Map,
+    serde::Deserialize,
+};
+
+#[derive(Deserialize)]
+pub struct Tile {
+    pub layout: Layout,
+    pub blocks: Map<Key, Block>,
+}
+
+impl Tile {
+    pub fn sprites<F>(&self, mut callback: F)
+    where
+        F: FnMut(&Key),
+    {
+        self.for_each_block(|block| block.sprites(&mut callback));
+    }
+
+    /// Calls the `callback` for every key of a kit shape used by the tile.
+    pub fn shapes<F>(&self, mut callback: F)
+    where
+        F: FnMut(&Key),
+    {
+        self.for_each_block(|block| {
+            if let ShapePointer::Key(key) = &block.shape.id {
+                callback(key);
+            }
+        });
+    }
+
+    /// Calls the `callback` for every built-in shape used by the tile,
+    /// or with `Err` of an unknown shape id.
+    pub fn builtin_shapes<F>(&self, mut callback: F)
+    where
+        F: FnMut(Result<ShapeId, u32>),
+    {
+        self.for_each_block(|block| {
+            if let Some(shape) = block.shape.id.builtin() {
+                callback(shape);
+            }
+        });
+    }
+
+    fn for_each_block<F>(&self, mut block: F)
+    where
+        F: FnMut(&Block),
+    {
+        match &self.layout {
+            Layout::D1(ptr) => {
+                ptr.block().map(&mut block);
+            }
+            Layout::D2(v) => v.iter().for_each(|ptr| {
+                ptr.block().map(&mut block);
+            }),
+            Layout::D3(v) => v.iter().flatten().for_each(|ptr| {
+                ptr.block().map(&mut block);
+            }),
+        }
+
+        self.blocks.values().for_each(&mut block);
+    }
+}
+
+/// A tile layout.
+///
+/// * `D1` is a single block.
+/// * `D2` is a row of blocks along the X axis.
+/// * `D3` is a list of rows along the X axis placed along the Z axis.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Layout {
+    D1(BlockPointer),
+    D2(Vec<BlockPointer>),
+    D3(Vec<Vec<BlockPointer>>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum BlockPointer {
+    None,
+    Key(Key),
+    Block(Block),
+}
+
+impl BlockPointer {
+    fn block(&self) -> Option<&Block> {
+        match self {
+            Self::Block(block) => Some(block),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Block {
+    pub shape: Shape,
+    /// The orientation of the shape, the identity by default.
+    #[serde(default)]
+    pub transform: Transform,
+}
+
+impl Block {
+    pub fn sprites<F>(&self, mut callback: F)
+    where
+        F: FnMut(&Key),
+    {
+        self.shape.sprites.keys(&mut callback);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Shape {
+    pub id: ShapePointer,
+    pub sprites: Sprites,
+}
+
+/// A built-in shape id or a key of a shape defined by the kit.
+///
+/// The id is kept raw, so an unknown one doesn't fail parsing of the whole
+/// tile and can be reported on its own.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ShapePointer {
+    Id(u32),
+    Key(Key),
+}
+
+impl ShapePointer {
+    /// Returns the built-in shape or `Err` with an unknown id.
+    /// Returns `None` if the pointer is a key.
+    pub fn builtin(&self) -> Option<Result<ShapeId, u32>> {
+        match *self {
+            Self::Id(id) => Some(
+                u8::try_from(id)
+                    .ok()
+                    .and_then(|n| ShapeId::from_id(n).ok())
+                    .ok_or(id),
+            ),
+            Self::Key(_) => None,
+        }
+    }
+}
+
+/// Sprites of shape faces.
+///
+/// * `Single` is used for every face of the shape.
+/// * `Multiple` is a list of sprites for each face in the shape order.
+///   Faces past the end of the list have no sprite.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Sprites {
+    Single(SpritePointer),
+    Multiple(Vec<SpritePointer>),
+}
+
+impl Sprites {
+    /// Calls the `callback` for every sprite key.
+    pub fn keys<F>(&self, mut callback: F)
+    where
+        F: FnMut(&Key),
+    {
+        let mut sprite = |ptr: &SpritePointer| {
+            if let Some(key) = ptr.key() {
+                callback(key);
+            }
+        };
+
+        match self {
+            Self::Single(ptr) => sprite(ptr),
+            Self::Multiple(v) => v.iter().for_each(sprite),
+        }
+    }
+
+    /// Returns the sprite of the face with the given index.
+    pub fn get(&self, face: usize) -> &SpritePointer {
+        match self {
+            Self::Single(ptr) => ptr,
+            Self::Multiple(v) => v.get(face).unwrap_or(&SpritePointer::None),
+        }
+    }
+}
+
+/// A sprite of a face.
+///
+/// * `None`, written as `null`, leaves the face without a sprite.
+/// * `Key` is a sprite name, like `'bricks'`.
+/// * `Sprite` is a sprite with options, like
+///   `{ name: 'leaves', offset: [0, 0.5], discard: true }`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SpritePointer {
+    None,
+    Key(Key),
+    Sprite {
+        name: Key,
+        /// The shift of texture coordinates in sprite sizes, `[0, 0]` by default.
+        ///
+        /// It moves the window which the face samples from the sprite.
+        /// Coordinates wrap inside of the sprite, so it's sampled as if it's repeated.
+        #[serde(default)]
+        offset: (f32, f32),
+        /// Whether transparent pixels of the sprite are discarded, `false` by default.
+        ///
+        /// Such faces are alpha tested and drawn in the cutout pass.
+        /// They never hide faces of neighbour blocks, even if the shape covers
+        /// their side.
+        #[serde(default)]
+        discard: bool,
+    },
+}
+
+impl SpritePointer {
+    pub fn key(&self) -> Option<&Key> {
+        match self {
+            Self::None => None,
+            Self::Key(key) | Self::Sprite { name: key, .. } => Some(key),
+        }
+    }
+
+    /// Returns the mesher sprite with the given `id` and `uv` if the face has a sprite.
+    pub fn to_sprite(&self, id: u32, uv: Uv) -> Option<Sprite> {
+        match *self {
+            Self::None => None,
+            Self::Key(_) => Some(Sprite {
+                id,
+                uv,
+                ..Sprite::default()
+            }),
+            Self::Sprite {
+                offset: (u, v),
+                discard,
+                ..
+            } => Some(Sprite {
+                id,
+                uv,
+                offset: [u, v],
+                discard,
+            }),
+        }
+    }
+}
@@ -1,7 +1,13 @@
 pub mod chunk;
+pub mod cluster;
 pub mod graphics;
 pub mod kit;
+pub mod light;
+pub mod mesher;
+pub mod net;
 pub mod point;
+pub mod raycast;
 pub mod shape;
 pub mod side;
 pub mod sprite;
+pub mod transform;
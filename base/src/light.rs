@@ -0,0 +1,594 @@
+use {
+    crate::{
+        chunk::{layout::Layout, size, ChunkData},
+        cluster::Cluster,
+        point::{BlockPoint, ChunkPoint, WorldPoint},
+        side::{Side, Sides},
+    },
+    std::collections::VecDeque,
+};
+
+/// The maximum light level.
+pub const MAX: u8 = 15;
+
+/// Returns the block light level of a packed light value.
+pub const fn block_level(light: u8) -> u8 {
+    light & 0xF
+}
+
+/// Returns the sky light level of a packed light value.
+pub const fn sky_level(light: u8) -> u8 {
+    light >> 4
+}
+
+/// Packs light levels in a byte, the block light is in the low nibble
+/// and the sky light is in the high one.
+pub const fn pack(block: u8, sky: u8) -> u8 {
+    block & 0xF | sky << 4
+}
+
+/// A block which affects light.
+pub trait Block {
+    /// Returns the level of light emitted by the block.
+    fn emission(&self) -> u8 {
+        0
+    }
+
+    /// Returns `true` if light doesn't pass through the block.
+    ///
+    /// An opaque block is still lit by its own emission.
+    fn opaque(&self) -> bool;
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Channel {
+    Block,
+    Sky,
+}
+
+impl Channel {
+    const ALL: [Self; 2] = [Self::Block, Self::Sky];
+
+    fn get(self, light: u8) -> u8 {
+        match self {
+            Self::Block => block_level(light),
+            Self::Sky => sky_level(light),
+        }
+    }
+
+    fn set(self, light: &mut u8, level: u8) {
+        *light = match self {
+            Self::Block => pack(level, sky_level(*light)),
+            Self::Sky => pack(block_level(*light), level),
+        };
+    }
+
+    /// Full sky light goes down without fading.
+    fn falls(self, side: Side, level: u8) -> bool {
+        self == Self::Sky && side == Side::Down && level == MAX
+    }
+}
+
+/// Light levels of loaded chunks.
+///
+/// Every lit chunk has a `ChunkData<u8>` of packed levels parallel to its blocks.
+/// Light spreads from emitting blocks and from the sky, which shines down
+/// on the top layer of a chunk if there is no lit chunk above it.
+/// Levels fade by one per block, except full sky light going down.
+pub struct Lighting {
+    light: Cluster<u8>,
+}
+
+impl Lighting {
+    pub fn new() -> Self {
+        Self {
+            light: Cluster::new(),
+        }
+    }
+
+    pub fn light(&self) -> &Cluster<u8> {
+        &self.light
+    }
+
+    pub fn chunk(&self, point: ChunkPoint) -> Option<&ChunkData<u8>> {
+        self.light.get(point)
+    }
+
+    /// Returns packed light levels at the `point` or zero if its chunk isn't lit.
+    pub fn get(&self, point: WorldPoint) -> u8 {
+        self.light.block(point).copied().unwrap_or(0)
+    }
+
+    /// Lights the chunk at the `point` and updates light of adjacent chunks.
+    ///
+    /// The chunk must be loaded in `blocks`, blocks of unloaded chunks are opaque.
+    pub fn load<T, L>(&mut self, blocks: &Cluster<T, L>, point: ChunkPoint)
+    where
+        T: Block,
+        L: Layout,
+    {
+        self.light.insert(point, ChunkData::new(0));
+        let (min, max) = bounds(point);
+        let seeds: Vec<_> = blocks
+            .blocks(min, max)
+            .map(|(point, _)| point)
+            .chain(border(point))
+            .collect();
+
+        self.relight(blocks, &seeds);
+    }
+
+    /// Removes light of the chunk at the `point` and updates light of adjacent chunks.
+    ///
+    /// Light which came from the chunk fades, and the chunk below it
+    /// is lit by the sky again.
+    pub fn unload<T, L>(&mut self, blocks: &Cluster<T, L>, point: ChunkPoint)
+    where
+        T: Block,
+        L: Layout,
+    {
+        if self.light.remove(point).is_some() {
+            let seeds: Vec<_> = border(point).collect();
+            self.relight(blocks, &seeds);
+        }
+    }
+
+    /// Updates light after the block at the `point` was placed or removed.
+    pub fn update<T, L>(&mut self, blocks: &Cluster<T, L>, point: WorldPoint)
+    where
+        T: Block,
+        L: Layout,
+    {
+        self.relight(blocks, &[point]);
+    }
+
+    /// Resets light of `seeds` to their own sources and spreads the change.
+    ///
+    /// First, light which may have come through the seeds is removed,
+    /// then it's refilled from remaining sources at the border of the removed area.
+    fn relight<T, L>(&mut self, blocks: &Cluster<T, L>, seeds: &[WorldPoint])
+    where
+        T: Block,
+        L: Layout,
+    {
+        for channel in Channel::ALL {
+            let mut removal = VecDeque::new();
+            let mut spread = VecDeque::new();
+            for &point in seeds {
+                if let Some(old) = self.level(channel, point) {
+                    if self.reset(blocks, channel, point) > 0 {
+                        spread.push_back(point);
+                    }
+
+                    removal.push_back((point, old));
+                }
+            }
+
+            while let Some((point, level)) = removal.pop_front() {
+                for (side, next) in point.neighbours() {
+                    let old = match self.level(channel, next) {
+                        Some(0) | None => continue,
+                        Some(old) => old,
+                    };
+
+                    if old < level || old == level && channel.falls(side, level) {
+                        // The light may have come from the point, so remove it
+                        if self.reset(blocks, channel, next) > 0 {
+                            spread.push_back(next);
+                        }
+
+                        removal.push_back((next, old));
+                    } else {
+                        // The light has another source, so it fills the removed area
+                        spread.push_back(next);
+                    }
+                }
+            }
+
+            while let Some(point) = spread.pop_front() {
+                let level = self.level(channel, point).unwrap_or(0);
+                for (side, next) in point.neighbours() {
+                    let new = if channel.falls(side, level) {
+                        level
+                    } else {
+                        level.saturating_sub(1)
+                    };
+
+                    let lower = self.level(channel, next).is_some_and(|old| old < new);
+                    if lower && !opaque(blocks, next) {
+                        self.set_level(channel, next, new);
+                        spread.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets the level of the `point` to its own source level and returns it.
+    fn reset<T, L>(&mut self, blocks: &Cluster<T, L>, channel: Channel, point: WorldPoint) -> u8
+    where
+        T: Block,
+        L: Layout,
+    {
+        let level = match channel {
+            Channel::Block => blocks
+                .block(point)
+                .map_or(0, |block| block.emission().min(MAX)),
+            Channel::Sky => {
+                let (_, y, _) = point.block_point().into();
+                let top = u32::from(y) == size::HEIGHT - 1;
+                let open = || {
+                    let above = point.chunk_point().to(Side::Up);
+                    !above.is_some_and(|above| self.light.contains(above))
+                };
+
+                if top && open() && !opaque(blocks, point) {
+                    MAX
+                } else {
+                    0
+                }
+            }
+        };
+
+        self.set_level(channel, point, level);
+        level
+    }
+
+    fn level(&self, channel: Channel, point: WorldPoint) -> Option<u8> {
+        self.light.block(point).map(|&light| channel.get(light))
+    }
+
+    fn set_level(&mut self, channel: Channel, point: WorldPoint, level: u8) {
+        if let Some(light) = self.light.block_mut(point) {
+            channel.set(light, level);
+        }
+    }
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn opaque<T, L>(blocks: &Cluster<T, L>, point: WorldPoint) -> bool
+where
+    T: Block,
+    L: Layout,
+{
+    blocks.block(point).is_none_or(Block::opaque)
+}
+
+/// Returns the box of absolute coordinates of the chunk.
+fn bounds(point: ChunkPoint) -> ((i32, i32, i32), (i32, i32, i32)) {
+    let origin = BlockPoint::new(0, 0, 0).expect("block point");
+    let (x, y, z) = WorldPoint::new(origin, point).absolute();
+    let max = (
+        x + size::WIDTH as i32 - 1,
+        y + size::HEIGHT as i32 - 1,
+        z + size::DEPTH as i32 - 1,
+    );
+
+    ((x, y, z), max)
+}
+
+/// Iterates over points of adjacent chunks which touch the chunk.
+fn border(point: ChunkPoint) -> impl Iterator<Item = WorldPoint> {
+    let (w, h, d) = (size::WIDTH as u8, size::HEIGHT as u8, size::DEPTH as u8);
+    Sides::all().into_iter().flat_map(move |side| {
+        // The layer of the chunk which faces the side
+        let (xs, ys, zs) = match side {
+            Side::Left => (w - 1..w, 0..h, 0..d),
+            Side::Right => (0..1, 0..h, 0..d),
+            Side::Up => (0..w, h - 1..h, 0..d),
+            Side::Down => (0..w, 0..1, 0..d),
+            Side::Forth => (0..w, 0..h, d - 1..d),
+            Side::Back => (0..w, 0..h, 0..1),
+        };
+
+        zs.flat_map(move |z| ys.clone().map(move |y| (y, z)))
+            .flat_map(move |(y, z)| xs.clone().map(move |x| (x, y, z)))
+            .filter_map(move |(x, y, z)| {
+                let block = BlockPoint::new(x, y, z).expect("block point");
+                WorldPoint::new(block, point).to(side, 1)
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum TestBlock {
+        Air,
+        Stone,
+        Lamp(u8),
+    }
+
+    impl Block for TestBlock {
+        fn emission(&self) -> u8 {
+            match self {
+                Self::Lamp(level) => *level,
+                _ => 0,
+            }
+        }
+
+        fn opaque(&self) -> bool {
+            *self == Self::Stone
+        }
+    }
+
+    fn chunk(x: i8, y: i8, z: i8) -> ChunkPoint {
+        ChunkPoint::new(x, y, z).unwrap()
+    }
+
+    fn world(x: i32, y: i32, z: i32) -> WorldPoint {
+        WorldPoint::from_absolute(x, y, z).unwrap()
+    }
+
+    fn block(lighting: &Lighting, x: i32, y: i32, z: i32) -> u8 {
+        block_level(lighting.get(world(x, y, z)))
+    }
+
+    fn sky(lighting: &Lighting, x: i32, y: i32, z: i32) -> u8 {
+        sky_level(lighting.get(world(x, y, z)))
+    }
+
+    /// Covers the top layer of the chunk with stone, so the sky doesn't light it.
+    fn roof(blocks: &mut Cluster<TestBlock>, point: ChunkPoint) {
+        let ((x0, _, z0), (x1, y, z1)) = bounds(point);
+        for z in z0..=z1 {
+            for x in x0..=x1 {
+                blocks[world(x, y, z)] = TestBlock::Stone;
+            }
+        }
+    }
+
+    /// Checks that the light is the same as the light computed from scratch.
+    fn assert_consistent(blocks: &Cluster<TestBlock>, lighting: &Lighting) {
+        let mut points: Vec<_> = lighting.light().iter().map(|(point, _)| point).collect();
+        points.sort_unstable_by_key(|&point| <(i8, i8, i8)>::from(point));
+
+        let mut fresh = Lighting::new();
+        for &point in &points {
+            fresh.load(blocks, point);
+        }
+
+        for point in points {
+            let (min, max) = bounds(point);
+            for (point, _) in blocks.blocks(min, max) {
+                assert_eq!(lighting.get(point), fresh.get(point), "at {point}");
+            }
+        }
+    }
+
+    #[test]
+    fn packing() {
+        let light = pack(3, 12);
+        assert_eq!(block_level(light), 3);
+        assert_eq!(sky_level(light), 12);
+        assert_eq!(pack(MAX, MAX), u8::MAX);
+        assert_eq!(pack(0, 0), 0);
+    }
+
+    #[test]
+    fn open_sky() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        blocks.get_or_create(chunk(0, 0, 0), TestBlock::Air);
+        let mut lighting = Lighting::new();
+        lighting.load(&blocks, chunk(0, 0, 0));
+
+        let light = lighting.chunk(chunk(0, 0, 0)).unwrap();
+        for (point, _) in blocks.blocks((0, 0, 0), (15, 31, 15)) {
+            assert_eq!(light[point.block_point()], pack(0, MAX));
+        }
+    }
+
+    #[test]
+    fn shadow() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        blocks.get_or_create(chunk(0, 0, 0), TestBlock::Air);
+        for z in 4..=8 {
+            for x in 4..=8 {
+                blocks[world(x, 20, z)] = TestBlock::Stone;
+            }
+        }
+
+        let mut lighting = Lighting::new();
+        lighting.load(&blocks, chunk(0, 0, 0));
+
+        // Full sky light goes down, but fades going sideways under the roof
+        assert_eq!(sky(&lighting, 6, 21, 6), MAX);
+        assert_eq!(sky(&lighting, 6, 20, 6), 0);
+        assert_eq!(sky(&lighting, 3, 19, 6), MAX);
+        assert_eq!(sky(&lighting, 4, 19, 6), MAX - 1);
+        assert_eq!(sky(&lighting, 6, 19, 6), MAX - 3);
+        assert_eq!(sky(&lighting, 6, 0, 6), MAX - 3);
+        assert_eq!(sky(&lighting, 5, 10, 5), MAX - 2);
+    }
+
+    #[test]
+    fn emission() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        blocks.get_or_create(chunk(0, 0, 0), TestBlock::Air);
+        roof(&mut blocks, chunk(0, 0, 0));
+        blocks[world(8, 8, 8)] = TestBlock::Lamp(14);
+
+        let mut lighting = Lighting::new();
+        lighting.load(&blocks, chunk(0, 0, 0));
+        assert_eq!(block(&lighting, 8, 8, 8), 14);
+        assert_eq!(block(&lighting, 9, 8, 8), 13);
+        assert_eq!(block(&lighting, 10, 9, 7), 10);
+        assert_eq!(block(&lighting, 8, 20, 8), 2);
+        assert_eq!(block(&lighting, 8, 30, 8), 0);
+        assert_eq!(sky(&lighting, 8, 8, 8), 0);
+
+        // An opaque emitter is lit, but light doesn't pass through it
+        blocks[world(8, 8, 8)] = TestBlock::Air;
+        blocks[world(2, 2, 2)] = TestBlock::Stone;
+        blocks[world(3, 2, 2)] = TestBlock::Lamp(5);
+        lighting.update(&blocks, world(8, 8, 8));
+        lighting.update(&blocks, world(2, 2, 2));
+        lighting.update(&blocks, world(3, 2, 2));
+        assert_eq!(block(&lighting, 8, 8, 8), 0);
+        assert_eq!(block(&lighting, 3, 2, 2), 5);
+        assert_eq!(block(&lighting, 2, 2, 2), 0);
+        assert_eq!(block(&lighting, 4, 2, 2), 4);
+
+        // The light goes around the stone
+        assert_eq!(block(&lighting, 1, 2, 2), 1);
+        assert_consistent(&blocks, &lighting);
+    }
+
+    #[test]
+    fn place_and_remove() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        blocks.get_or_create(chunk(0, 0, 0), TestBlock::Air);
+        roof(&mut blocks, chunk(0, 0, 0));
+        blocks[world(8, 8, 8)] = TestBlock::Lamp(10);
+
+        let mut lighting = Lighting::new();
+        lighting.load(&blocks, chunk(0, 0, 0));
+
+        // A wall around the lamp blocks its light
+        let wall: Vec<_> = world(8, 8, 8)
+            .neighbours()
+            .map(|(_, point)| point)
+            .collect();
+        for &point in &wall {
+            blocks[point] = TestBlock::Stone;
+            lighting.update(&blocks, point);
+        }
+
+        assert_eq!(block(&lighting, 8, 8, 8), 10);
+        assert_eq!(block(&lighting, 10, 8, 8), 0);
+        assert_consistent(&blocks, &lighting);
+
+        // Removing one block of the wall lets the light out
+        blocks[wall[0]] = TestBlock::Air;
+        lighting.update(&blocks, wall[0]);
+        assert_eq!(lighting.get(wall[0]), pack(9, 0));
+        assert_consistent(&blocks, &lighting);
+
+        // Removing the lamp removes all its light
+        blocks[world(8, 8, 8)] = TestBlock::Air;
+        lighting.update(&blocks, world(8, 8, 8));
+        for (point, _) in blocks.blocks((0, 0, 0), (15, 31, 15)) {
+            assert_eq!(block_level(lighting.get(point)), 0);
+        }
+
+        // Opening the roof lets the sky light in
+        blocks[world(0, 31, 0)] = TestBlock::Air;
+        lighting.update(&blocks, world(0, 31, 0));
+        assert_eq!(sky(&lighting, 0, 0, 0), MAX);
+        assert_eq!(sky(&lighting, 1, 0, 0), MAX - 1);
+        assert_consistent(&blocks, &lighting);
+    }
+
+    #[test]
+    fn across_chunks() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        for point in [chunk(0, 0, 0), chunk(-1, 0, 0)] {
+            blocks.get_or_create(point, TestBlock::Air);
+            roof(&mut blocks, point);
+        }
+
+        blocks[world(1, 8, 8)] = TestBlock::Lamp(14);
+
+        // The light comes to the chunk loaded later
+        let mut lighting = Lighting::new();
+        lighting.load(&blocks, chunk(0, 0, 0));
+        assert_eq!(block(&lighting, 1, 8, 8), 14);
+        lighting.load(&blocks, chunk(-1, 0, 0));
+        assert_eq!(block(&lighting, -1, 8, 8), 12);
+        assert_eq!(block(&lighting, -4, 9, 8), 8);
+        assert_consistent(&blocks, &lighting);
+
+        // Placing a block in one chunk updates light of another
+        blocks[world(0, 8, 8)] = TestBlock::Stone;
+        lighting.update(&blocks, world(0, 8, 8));
+        assert_eq!(block(&lighting, -1, 8, 8), 10);
+        assert_consistent(&blocks, &lighting);
+
+        // The light goes away with the chunk
+        lighting.unload(&blocks, chunk(0, 0, 0));
+        assert!(lighting.chunk(chunk(0, 0, 0)).is_none());
+        assert_eq!(block(&lighting, -1, 8, 8), 0);
+        assert_consistent(&blocks, &lighting);
+    }
+
+    #[test]
+    fn sky_across_chunks() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        blocks.get_or_create(chunk(0, 0, 0), TestBlock::Air);
+        blocks.get_or_create(chunk(0, 1, 0), TestBlock::Air);
+        roof(&mut blocks, chunk(0, 1, 0));
+
+        let mut lighting = Lighting::new();
+        lighting.load(&blocks, chunk(0, 0, 0));
+        assert_eq!(sky(&lighting, 5, 0, 5), MAX);
+
+        // The chunk above covers the sky
+        lighting.load(&blocks, chunk(0, 1, 0));
+        assert_eq!(sky(&lighting, 5, 0, 5), 0);
+        assert_eq!(sky(&lighting, 5, 40, 5), 0);
+        assert_consistent(&blocks, &lighting);
+
+        // A hole in the roof lights the column down to the bottom chunk
+        blocks[world(5, 63, 5)] = TestBlock::Air;
+        lighting.update(&blocks, world(5, 63, 5));
+        assert_eq!(sky(&lighting, 5, 0, 5), MAX);
+        assert_eq!(sky(&lighting, 7, 0, 5), MAX - 2);
+        assert_consistent(&blocks, &lighting);
+
+        // Unloading the chunk above opens the sky again
+        blocks[world(5, 63, 5)] = TestBlock::Stone;
+        lighting.update(&blocks, world(5, 63, 5));
+        assert_eq!(sky(&lighting, 5, 0, 5), 0);
+        lighting.unload(&blocks, chunk(0, 1, 0));
+        assert_eq!(sky(&lighting, 5, 0, 5), MAX);
+        assert_eq!(sky(&lighting, 9, 31, 2), MAX);
+        assert_consistent(&blocks, &lighting);
+    }
+
+    #[test]
+    fn random_updates() {
+        let mut blocks = Cluster::<TestBlock>::new();
+        let chunks = [chunk(0, 0, 0), chunk(1, 0, 0), chunk(0, -1, 0)];
+        for point in chunks {
+            blocks.get_or_create(point, TestBlock::Air);
+        }
+
+        let mut lighting = Lighting::new();
+        for point in chunks {
+            lighting.load(&blocks, point);
+        }
+
+        // A deterministic sequence of placements and removals
+        let mut seed: u32 = 1;
+        let mut random = |n: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % n
+        };
+
+        for _ in 0..60 {
+            let (x, y, z) = chunks[random(chunks.len() as u32) as usize].into();
+            let point = world(
+                i32::from(x) * 16 + random(16) as i32,
+                i32::from(y) * 32 + random(32) as i32,
+                i32::from(z) * 16 + random(16) as i32,
+            );
+
+            blocks[point] = match random(3) {
+                0 => TestBlock::Air,
+                1 => TestBlock::Stone,
+                _ => TestBlock::Lamp(random(MAX as u32 + 1) as u8),
+            };
+
+            lighting.update(&blocks, point);
+        }
+
+        assert_consistent(&blocks, &lighting);
+    }
+}
@@ -0,0 +1,1553 @@
+use crate::{
+    chunk::{
+        layout::{Layout, Straight},
+        size, ChunkData,
+    },
+    graphics::{Face, MeshData, Vert},
+    kit, light,
+    point::BlockPoint,
+    shape::{Data, Shape},
+    side::{Side, Sides},
+    sprite::Uv,
+    transform::Transform,
+};
+
+const SIZE: [usize; 3] = [
+    size::WIDTH as usize,
+    size::HEIGHT as usize,
+    size::DEPTH as usize,
+];
+
+/// A block which can be meshed.
+pub trait Block {
+    /// Returns the block shape or `None` if the place is empty.
+    fn shape(&self) -> Option<ShapeRef<'_>>;
+
+    /// Returns sides which the block covers completely.
+    ///
+    /// A face of a neighbour block adjacent to a covered side is hidden.
+    /// The sides are already transformed, so they're in the chunk orientation.
+    fn covers(&self) -> Sides;
+
+    /// Returns the orientation of the block shape.
+    fn transform(&self) -> Transform {
+        Transform::IDENTITY
+    }
+
+    /// Returns the sprite of the shape face with the given index.
+    ///
+    /// In the greedy mode only faces with the same sprite are merged.
+    /// If it returns `None`, the face is never merged and it's drawn as is.
+    fn sprite(&self, _: usize) -> Option<Sprite> {
+        None
+    }
+}
+
+/// A shape of a block, built-in or defined by a kit.
+#[derive(Clone, Copy)]
+pub enum ShapeRef<'a> {
+    Builtin(Shape),
+    /// Every part of the shape is a face.
+    Kit(&'a kit::shape::Shape),
+}
+
+impl<'a> ShapeRef<'a> {
+    /// Returns faces of the shape in order.
+    pub fn faces(self) -> impl Iterator<Item = Data<'a>> {
+        let (builtin, parts) = match self {
+            Self::Builtin(shape) => (shape.data(), &[][..]),
+            Self::Kit(shape) => (&[][..], &shape.parts[..]),
+        };
+
+        builtin
+            .iter()
+            .copied()
+            .chain(parts.iter().map(kit::shape::Part::data))
+    }
+
+    fn face(self, n: usize) -> Data<'a> {
+        match self {
+            Self::Builtin(shape) => shape.data()[n],
+            Self::Kit(shape) => shape.parts[n].data(),
+        }
+    }
+
+    /// Returns sides which the shape covers completely.
+    pub fn covers(self) -> Sides {
+        match self {
+            Self::Builtin(shape) => shape.covers(),
+            Self::Kit(shape) => shape.covers(),
+        }
+    }
+}
+
+impl From<Shape> for ShapeRef<'_> {
+    fn from(shape: Shape) -> Self {
+        Self::Builtin(shape)
+    }
+}
+
+/// Kit shapes are the same if they're the same object.
+impl PartialEq for ShapeRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Builtin(a), Self::Builtin(b)) => a == b,
+            (Self::Kit(a), Self::Kit(b)) => std::ptr::eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ShapeRef<'_> {}
+
+/// A sprite of a block face.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sprite {
+    pub id: u32,
+    /// The sprite rect in the texture, the whole texture by default.
+    ///
+    /// Faces are split in buffers by its page.
+    pub uv: Uv,
+    /// The shift of texture coordinates in sprite sizes.
+    ///
+    /// Coordinates wrap inside of the sprite rect, so the face samples
+    /// the sprite as if it's repeated.
+    pub offset: [f32; 2],
+    /// Whether the face is alpha tested.
+    ///
+    /// Such faces are put in the cutout pass and never hide faces of neighbours,
+    /// even if the block covers their side.
+    pub discard: bool,
+}
+
+impl Sprite {
+    fn pass(self) -> Pass {
+        if self.discard {
+            Pass::Cutout
+        } else {
+            Pass::Solid
+        }
+    }
+}
+
+/// A render pass of a mesh buffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pass {
+    /// Opaque faces.
+    Solid,
+    /// Alpha tested faces, their transparent fragments must be discarded.
+    Cutout,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// Every visible face is emitted as is.
+    Naive,
+    /// Coplanar full faces with the same sprite are merged into larger quads.
+    ///
+    /// Texture coordinates of a merged quad are scaled by its size and they wrap
+    /// inside of the sprite rect, so a sprite repeats once per block.
+    Greedy,
+}
+
+/// Adjacent chunks of a meshed chunk.
+pub struct Neighbours<'a, T, L> {
+    chunks: [Option<&'a ChunkData<T, L>>; 6],
+}
+
+impl<'a, T, L> Neighbours<'a, T, L> {
+    pub fn new() -> Self {
+        Self { chunks: [None; 6] }
+    }
+
+    /// Sets the adjacent chunk on the `side`.
+    pub fn with(mut self, side: Side, chunk: &'a ChunkData<T, L>) -> Self {
+        self.chunks[side as usize] = Some(chunk);
+        self
+    }
+
+    pub fn get(&self, side: Side) -> Option<&'a ChunkData<T, L>> {
+        self.chunks[side as usize]
+    }
+}
+
+impl<T, L> Default for Neighbours<'_, T, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Light levels of a meshed chunk and its adjacent chunks.
+///
+/// Levels are packed as in the [`light`] module.
+pub struct Light<'a, L = Straight<{ size::WIDTH }, { size::HEIGHT }>> {
+    chunk: &'a ChunkData<u8, L>,
+    neighbours: Neighbours<'a, u8, L>,
+}
+
+impl<'a, L> Light<'a, L> {
+    pub fn new(chunk: &'a ChunkData<u8, L>, neighbours: Neighbours<'a, u8, L>) -> Self {
+        Self { chunk, neighbours }
+    }
+}
+
+/// A mesh buffer.
+///
+/// Its vertex count never exceeds the range of `u16` indices.
+pub struct MeshBuffer {
+    verts: Vec<Vert>,
+    faces: Vec<Face>,
+    pass: Pass,
+    page: u16,
+}
+
+impl MeshBuffer {
+    const MAX_VERTS: usize = u16::MAX as usize + 1;
+
+    fn new(pass: Pass, page: u16) -> Self {
+        Self {
+            verts: vec![],
+            faces: vec![],
+            pass,
+            page,
+        }
+    }
+
+    pub fn verts(&self) -> &[Vert] {
+        &self.verts
+    }
+
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    pub fn pass(&self) -> Pass {
+        self.pass
+    }
+
+    /// Returns the texture page of sprites of the buffer.
+    pub fn page(&self) -> u16 {
+        self.page
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    pub fn as_data(&self) -> MeshData<'_> {
+        MeshData {
+            verts: &self.verts,
+            faces: &self.faces,
+        }
+    }
+
+    fn push<V, F>(&mut self, verts: V, faces: F)
+    where
+        V: IntoIterator<Item = Vert>,
+        F: IntoIterator<Item = Face>,
+    {
+        let offset = u16::try_from(self.verts.len()).expect("vertex offset");
+        self.verts.extend(verts);
+        self.faces
+            .extend(faces.into_iter().map(|face| face.map(|i| i + offset)));
+    }
+}
+
+#[derive(Default)]
+struct Buffers {
+    solid: Vec<MeshBuffer>,
+    cutout: Vec<MeshBuffer>,
+}
+
+impl Buffers {
+    /// Returns a buffer of the sprite pass and page with enough space for `n` vertices.
+    fn get(&mut self, sprite: Sprite, n: usize) -> &mut MeshBuffer {
+        let pass = sprite.pass();
+        let page = sprite.uv.page;
+        let buffers = match pass {
+            Pass::Solid => &mut self.solid,
+            Pass::Cutout => &mut self.cutout,
+        };
+
+        let index = buffers
+            .iter()
+            .rposition(|buffer| buffer.page == page)
+            .filter(|&index| buffers[index].verts.len() + n <= MeshBuffer::MAX_VERTS);
+
+        match index {
+            Some(index) => &mut buffers[index],
+            None => {
+                buffers.push(MeshBuffer::new(pass, page));
+                buffers.last_mut().expect("buffer")
+            }
+        }
+    }
+
+    /// Pushes the face translated to the block position.
+    ///
+    /// The `lights` are lights of the face vertices in the same order.
+    fn push(
+        &mut self,
+        data: &Data,
+        transform: Transform,
+        [x, y, z]: [f32; 3],
+        sprite: Sprite,
+        lights: &[f32],
+    ) {
+        let [ou, ov] = sprite.offset;
+        let rect = sprite.uv.rect();
+        let verts = data.mesh.verts.iter().zip(lights).map(|(vert, &light)| {
+            let [vx, vy, vz] = transform.pos(vert.pos);
+            let [u, v] = vert.tex;
+            Vert {
+                pos: [vx + x, vy + y, vz + z],
+                tex: [u + ou, v + ov],
+                light,
+                rect,
+            }
+        });
+
+        let buffer = self.get(sprite, data.mesh.verts.len());
+        match quad_faces(data, transform, lights) {
+            Some(faces) => buffer.push(verts, faces),
+            None => buffer.push(verts, faces(data, transform)),
+        }
+    }
+
+    fn into_vec(self) -> Vec<MeshBuffer> {
+        let mut buffers = self.solid;
+        buffers.extend(self.cutout);
+        buffers
+    }
+}
+
+/// Checks if the `block` hides faces of its neighbour on the `side`.
+///
+/// A covered side doesn't hide anything if the block has an alpha tested face on it.
+fn occludes<T>(block: &T, side: Side) -> bool
+where
+    T: Block,
+{
+    if !block.covers().contains(side) {
+        return false;
+    }
+
+    let shape = match block.shape() {
+        Some(shape) => shape,
+        None => return true,
+    };
+
+    let transform = block.transform();
+    !shape.faces().enumerate().any(|(face, data)| {
+        data.side.map(|side| transform.side(side)) == Some(side)
+            && block.sprite(face).is_some_and(|sprite| sprite.discard)
+    })
+}
+
+/// Returns faces of the `data` with the winding which keeps them facing outside
+/// after the `transform`.
+fn faces<'a>(data: &Data<'a>, transform: Transform) -> impl Iterator<Item = Face> + 'a {
+    let mirrored = transform.is_mirrored();
+    data.mesh.faces.iter().map(
+        move |&[a, b, c]| {
+            if mirrored {
+                [a, c, b]
+            } else {
+                [a, b, c]
+            }
+        },
+    )
+}
+
+/// Returns faces of a quad split along the diagonal with the brighter ends.
+///
+/// Otherwise the light of a dark corner is stretched along the diagonal.
+/// The `lights` are lights of the quad vertices.
+/// Returns `None` if the `data` isn't a quad of two triangles.
+fn quad_faces(data: &Data, transform: Transform, lights: &[f32]) -> Option<[Face; 2]> {
+    let verts = data.mesh.verts;
+    if verts.len() != 4 || data.mesh.faces.len() != 2 {
+        return None;
+    }
+
+    let mut faces = faces(data, transform);
+    let (first, second) = (faces.next()?, faces.next()?);
+
+    // Rotate the first triangle so the corner which isn't on the diagonal is in the middle
+    let mid = first.iter().position(|i| !second.contains(i))?;
+    let [a, b, c] = [0, 1, 2].map(|n| first[(mid + 2 + n) % 3]);
+    let d = *second.iter().find(|i| !first.contains(i))?;
+
+    let light = |i: u16| lights[usize::from(i)];
+    if light(b) + light(d) > light(a) + light(c) {
+        Some([[b, c, d], [b, d, a]])
+    } else {
+        Some([first, second])
+    }
+}
+
+/// Blocks and light around faces of a meshed chunk.
+///
+/// Positions are relative to the chunk. Only the chunk and its adjacent chunks
+/// are available, so blocks of edge and corner chunks are unknown.
+struct Around<'a, T, L> {
+    chunk: &'a ChunkData<T, L>,
+    neighbours: &'a Neighbours<'a, T, L>,
+    light: Option<&'a Light<'a, L>>,
+}
+
+impl<T, L> Around<'_, T, L>
+where
+    T: Block,
+    L: Layout,
+{
+    /// Darkening of a vertex by the number of solid blocks around it.
+    const OCCLUSION: [f32; 4] = [0.4, 0.6, 0.8, 1.];
+
+    /// Returns the side of the chunk which contains the `pos` and its point in that chunk.
+    ///
+    /// The side is `None` if the position is inside of the meshed chunk.
+    fn locate(pos: [i32; 3]) -> Option<(Option<Side>, BlockPoint)> {
+        const POSITIVE: [Side; 3] = [Side::Left, Side::Up, Side::Forth];
+        const NEGATIVE: [Side; 3] = [Side::Right, Side::Down, Side::Back];
+
+        let mut side = None;
+        let mut point = [0; 3];
+        for axis in 0..3 {
+            let size = SIZE[axis] as i32;
+            let out = if pos[axis] < 0 {
+                Some(NEGATIVE[axis])
+            } else if pos[axis] >= size {
+                Some(POSITIVE[axis])
+            } else {
+                None
+            };
+
+            if out.is_some() {
+                if side.is_some() {
+                    return None;
+                }
+
+                side = out;
+            }
+
+            point[axis] = pos[axis].rem_euclid(size) as u8;
+        }
+
+        let [x, y, z] = point;
+        Some((side, BlockPoint::new(x, y, z).expect("block point")))
+    }
+
+    fn solid(&self, pos: [i32; 3]) -> bool {
+        let block = Self::locate(pos).and_then(|(side, point)| match side {
+            Some(side) => self.neighbours.get(side).map(|chunk| &chunk[point]),
+            None => Some(&self.chunk[point]),
+        });
+
+        block.is_some_and(|block| block.covers() == Sides::all())
+    }
+
+    /// Returns the light level at the `pos` or `None` if it's unknown.
+    ///
+    /// Without light data everything is fully lit.
+    fn level(&self, pos: [i32; 3]) -> Option<u8> {
+        let Some(light) = self.light else {
+            return Some(light::MAX);
+        };
+
+        let (side, point) = Self::locate(pos)?;
+        let chunk = match side {
+            Some(side) => light.neighbours.get(side)?,
+            None => light.chunk,
+        };
+
+        let level = chunk[point];
+        Some(light::block_level(level).max(light::sky_level(level)))
+    }
+
+    /// Returns the light of a face vertex.
+    ///
+    /// The light of a face with a `side` is averaged over four blocks in front of
+    /// the vertex and darkened by solid ones among them. The light of other faces
+    /// is the light of their block.
+    fn vertex(&self, [x, y, z]: [usize; 3], side: Option<Side>, vert: [f32; 3]) -> f32 {
+        let pos = [x, y, z].map(|v| v as i32);
+        let Some(side) = side else {
+            return brightness(f32::from(self.level(pos).unwrap_or(light::MAX)));
+        };
+
+        let (n, u, v) = axes(side);
+        let mut front = pos;
+        front[n] += match side {
+            Side::Left | Side::Up | Side::Forth => 1,
+            Side::Right | Side::Down | Side::Back => -1,
+        };
+
+        let shift = |mut pos: [i32; 3], axis: usize| {
+            pos[axis] += if vert[axis] > 0.5 { 1 } else { -1 };
+            pos
+        };
+
+        let (a, b) = (shift(front, u), shift(front, v));
+        let c = shift(a, v);
+
+        // The corner doesn't matter if both sides are solid
+        let (solid_a, solid_b) = (self.solid(a), self.solid(b));
+        let solid_c = solid_a && solid_b || self.solid(c);
+        let occlusion = if solid_a && solid_b {
+            0
+        } else {
+            3 - usize::from(solid_a) - usize::from(solid_b) - usize::from(solid_c)
+        };
+
+        let (sum, n) = [(front, false), (a, solid_a), (b, solid_b), (c, solid_c)]
+            .into_iter()
+            .filter(|&(_, solid)| !solid)
+            .filter_map(|(pos, _)| self.level(pos))
+            .fold((0, 0), |(sum, n), level| (sum + u32::from(level), n + 1));
+
+        let level = if n == 0 {
+            f32::from(light::MAX)
+        } else {
+            sum as f32 / n as f32
+        };
+
+        brightness(level) * Self::OCCLUSION[occlusion]
+    }
+}
+
+/// Converts a light level to the brightness, every level is 80% as bright as the next one.
+fn brightness(level: f32) -> f32 {
+    0.8_f32.powf(f32::from(light::MAX) - level)
+}
+
+/// Meshes the chunk.
+///
+/// Every block's shape faces are transformed to the block orientation and translated
+/// to the block position in the chunk.
+/// A face is skipped if its side is covered by a neighbour block, including blocks
+/// of adjacent chunks. If there is no adjacent chunk on some side, faces on that
+/// border are kept.
+///
+/// Texture coordinates of a face are shifted by its sprite offset and vertices
+/// keep the sprite rect. Faces with alpha tested sprites go to buffers of
+/// the cutout pass, which follow buffers of the solid pass. Faces with sprites
+/// on different texture pages never share a buffer.
+///
+/// Vertices are darkened by ambient occlusion of solid blocks around them,
+/// but otherwise they are fully lit. In the greedy mode only faces with
+/// the same light at all vertices are merged.
+///
+/// The mesh is split in several buffers if it has too many vertices.
+/// An empty chunk has no buffers.
+pub fn mesh<T, L>(
+    chunk: &ChunkData<T, L>,
+    neighbours: &Neighbours<T, L>,
+    mode: Mode,
+) -> Vec<MeshBuffer>
+where
+    T: Block,
+    L: Layout,
+{
+    build(chunk, neighbours, None, mode)
+}
+
+/// Meshes the chunk like [`mesh`] with smooth `light`.
+///
+/// The light of a vertex is the average light of blocks around it.
+pub fn mesh_lit<T, L>(
+    chunk: &ChunkData<T, L>,
+    neighbours: &Neighbours<T, L>,
+    light: &Light<L>,
+    mode: Mode,
+) -> Vec<MeshBuffer>
+where
+    T: Block,
+    L: Layout,
+{
+    build(chunk, neighbours, Some(light), mode)
+}
+
+fn build<T, L>(
+    chunk: &ChunkData<T, L>,
+    neighbours: &Neighbours<T, L>,
+    light: Option<&Light<L>>,
+    mode: Mode,
+) -> Vec<MeshBuffer>
+where
+    T: Block,
+    L: Layout,
+{
+    let around = Around {
+        chunk,
+        neighbours,
+        light,
+    };
+
+    let mut buffers = Buffers::default();
+    let mut lights = Vec::with_capacity(4);
+    let mut layers = match mode {
+        Mode::Naive => None,
+        Mode::Greedy => Some(Layers::new()),
+    };
+
+    for z in 0..size::DEPTH as u8 {
+        for y in 0..size::HEIGHT as u8 {
+            for x in 0..size::WIDTH as u8 {
+                let point = BlockPoint::new(x, y, z).expect("block point");
+                let block = &chunk[point];
+                let shape = match block.shape() {
+                    Some(shape) => shape,
+                    None => continue,
+                };
+
+                let transform = block.transform();
+                for (face, data) in shape.faces().enumerate() {
+                    let side = data.side.map(|side| transform.side(side));
+                    if let Some(side) = side {
+                        let neighbour = match point.to(side, 1) {
+                            Ok(point) => Some(&chunk[point]),
+                            Err(point) => neighbours.get(side).map(|chunk| &chunk[point]),
+                        };
+
+                        if neighbour.is_some_and(|block| occludes(block, side.opposite())) {
+                            continue;
+                        }
+                    }
+
+                    let pos = [x, y, z].map(usize::from);
+                    lights.clear();
+                    lights.extend(
+                        data.mesh
+                            .verts
+                            .iter()
+                            .map(|vert| around.vertex(pos, side, transform.pos(vert.pos))),
+                    );
+
+                    let sprite = block.sprite(face);
+                    if let (Some(layers), Some(side), Some(sprite)) = (&mut layers, side, sprite) {
+                        let face = ShapeFace {
+                            shape,
+                            face,
+                            transform,
+                        };
+
+                        // Faces with different light at vertices can't be stretched
+                        let uniform = match lights.split_first() {
+                            Some((&first, rest)) if rest.iter().all(|&light| light == first) => {
+                                Some(first)
+                            }
+                            _ => None,
+                        };
+
+                        if let (Some(_), Some(light)) = (layers.full_face(face), uniform) {
+                            layers.set(
+                                side,
+                                pos,
+                                Key {
+                                    sprite,
+                                    face,
+                                    light,
+                                },
+                            );
+                            continue;
+                        }
+                    }
+
+                    let sprite = sprite.unwrap_or_default();
+                    buffers.push(&data, transform, pos.map(|v| v as f32), sprite, &lights);
+                }
+            }
+        }
+    }
+
+    if let Some(layers) = &mut layers {
+        layers.merge(&mut buffers);
+    }
+
+    buffers.into_vec()
+}
+
+/// A key of a mergeable face.
+#[derive(Clone, Copy, PartialEq)]
+struct Key<'a> {
+    sprite: Sprite,
+    face: ShapeFace<'a>,
+    /// The light of all vertices of the face.
+    light: f32,
+}
+
+/// A face of an oriented shape.
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct ShapeFace<'a> {
+    shape: ShapeRef<'a>,
+    face: usize,
+    transform: Transform,
+}
+
+impl<'a> ShapeFace<'a> {
+    fn data(self) -> Data<'a> {
+        self.shape.face(self.face)
+    }
+}
+
+/// Returns indices of the normal axis and two axes of the `side` plane.
+const fn axes(side: Side) -> (usize, usize, usize) {
+    match side {
+        Side::Left | Side::Right => (0, 1, 2),
+        Side::Up | Side::Down => (1, 0, 2),
+        Side::Forth | Side::Back => (2, 0, 1),
+    }
+}
+
+/// A full face is a quad which completely covers the side of a block.
+///
+/// Its texture coordinates must be an affine map of the plane coordinates,
+/// so the quad can be stretched to tile its sprite.
+#[derive(Clone, Copy)]
+struct FullFace {
+    tex: [f32; 2],
+    tex_u: [f32; 2],
+    tex_v: [f32; 2],
+}
+
+impl FullFace {
+    fn new(face: ShapeFace) -> Option<Self> {
+        let data = face.data();
+        let side = face.transform.side(data.side?);
+        let (n, u, v) = axes(side);
+        let plane = match side {
+            Side::Left | Side::Up | Side::Forth => 1.,
+            Side::Right | Side::Down | Side::Back => 0.,
+        };
+
+        let verts = data.mesh.verts;
+        if verts.len() != 4 {
+            return None;
+        }
+
+        let pos = |vert: &Vert| face.transform.pos(vert.pos);
+        let corner = |cu, cv| {
+            verts
+                .iter()
+                .find(|vert| pos(vert)[u] == cu && pos(vert)[v] == cv)
+                .map(|vert| vert.tex)
+        };
+
+        let on_plane = verts.iter().all(|vert| pos(vert)[n] == plane);
+        let (t00, t10, t01, t11) = (
+            corner(0., 0.)?,
+            corner(1., 0.)?,
+            corner(0., 1.)?,
+            corner(1., 1.)?,
+        );
+        let affine = (0..2).all(|i| t10[i] + t01[i] - t00[i] == t11[i]);
+        if !on_plane || !affine {
+            return None;
+        }
+
+        Some(Self {
+            tex: t00,
+            tex_u: [t10[0] - t00[0], t10[1] - t00[1]],
+            tex_v: [t01[0] - t00[0], t01[1] - t00[1]],
+        })
+    }
+}
+
+/// The maximum number of layers along any axis of a chunk.
+const MAX_LAYERS: usize = u8::MAX as usize + 1;
+
+/// Mergeable faces of a chunk.
+///
+/// Faces are merged side by side in a single mask, where cells are stored
+/// layer by layer along the side normal, so a layer is a contiguous 2D mask.
+struct Layers<'a> {
+    faces: Vec<(Side, [usize; 3], Key<'a>)>,
+    mask: Vec<Option<Key<'a>>>,
+    /// Numbers of faces in each layer of the mask to skip empty ones.
+    counts: [u16; MAX_LAYERS],
+    /// Cached full faces of shapes.
+    full_faces: Vec<(ShapeFace<'a>, Option<FullFace>)>,
+}
+
+impl<'a> Layers<'a> {
+    const LEN: usize = SIZE[0] * SIZE[1] * SIZE[2];
+
+    fn new() -> Self {
+        Self {
+            faces: vec![],
+            mask: vec![],
+            counts: [0; MAX_LAYERS],
+            full_faces: vec![],
+        }
+    }
+
+    fn full_face(&mut self, face: ShapeFace<'a>) -> Option<FullFace> {
+        full_face(&mut self.full_faces, face)
+    }
+
+    fn set(&mut self, side: Side, pos: [usize; 3], key: Key<'a>) {
+        self.faces.push((side, pos, key));
+    }
+
+    fn merge(&mut self, buffers: &mut Buffers) {
+        if self.faces.is_empty() {
+            return;
+        }
+
+        self.mask.resize(Self::LEN, None);
+        for side in Sides::all() {
+            let (n, u, v) = axes(side);
+            let (width, height) = (SIZE[u], SIZE[v]);
+            for &(_, pos, key) in self.faces.iter().filter(|&&(s, ..)| s == side) {
+                self.mask[(pos[n] * height + pos[v]) * width + pos[u]] = Some(key);
+                self.counts[pos[n]] += 1;
+            }
+
+            for layer in 0..SIZE[n] {
+                if self.counts[layer] == 0 {
+                    continue;
+                }
+
+                // Merged quads are cleared, so the layer is empty after it
+                self.counts[layer] = 0;
+                let start = layer * width * height;
+                let mask = &mut self.mask[start..start + width * height];
+                for pv in 0..height {
+                    for pu in 0..width {
+                        let key = match mask[pv * width + pu] {
+                            Some(key) => key,
+                            None => continue,
+                        };
+
+                        // Grow the quad along the first axis, then along the second one
+                        let row = |mask: &[Option<Key>], pv, len| {
+                            mask[pv * width + pu..][..len]
+                                .iter()
+                                .all(|&cell| cell == Some(key))
+                        };
+
+                        let mut quad_width = 1;
+                        while pu + quad_width < width && row(mask, pv, quad_width + 1) {
+                            quad_width += 1;
+                        }
+
+                        let mut quad_height = 1;
+                        while pv + quad_height < height && row(mask, pv + quad_height, quad_width) {
+                            quad_height += 1;
+                        }
+
+                        for qv in pv..pv + quad_height {
+                            mask[qv * width + pu..][..quad_width].fill(None);
+                        }
+
+                        let mut pos = [0; 3];
+                        pos[n] = layer;
+                        pos[u] = pu;
+                        pos[v] = pv;
+
+                        let full = full_face(&mut self.full_faces, key.face);
+                        push_quad(
+                            buffers,
+                            side,
+                            key,
+                            full.expect("full face"),
+                            pos,
+                            [quad_width, quad_height],
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn full_face<'a>(
+    cache: &mut Vec<(ShapeFace<'a>, Option<FullFace>)>,
+    face: ShapeFace<'a>,
+) -> Option<FullFace> {
+    match cache.iter().find(|&&(fa, _)| fa == face) {
+        Some(&(_, full)) => full,
+        None => {
+            let full = FullFace::new(face);
+            cache.push((face, full));
+            full
+        }
+    }
+}
+
+fn push_quad(
+    buffers: &mut Buffers,
+    side: Side,
+    Key {
+        sprite,
+        face,
+        light,
+    }: Key,
+    full: FullFace,
+    pos: [usize; 3],
+    [width, height]: [usize; 2],
+) {
+    let data = face.data();
+    let (n, u, v) = axes(side);
+    let (width, height) = (width as f32, height as f32);
+    let rect = sprite.uv.rect();
+    let verts = data.mesh.verts.iter().map(|vert| {
+        let vert = face.transform.pos(vert.pos);
+        let (cu, cv) = (vert[u] * width, vert[v] * height);
+        let mut quad = [0.; 3];
+        quad[n] = pos[n] as f32 + vert[n];
+        quad[u] = pos[u] as f32 + cu;
+        quad[v] = pos[v] as f32 + cv;
+
+        let tex = [0, 1]
+            .map(|i| full.tex[i] + full.tex_u[i] * cu + full.tex_v[i] * cv + sprite.offset[i]);
+
+        Vert {
+            pos: quad,
+            tex,
+            light,
+            rect,
+        }
+    });
+
+    buffers
+        .get(sprite, data.mesh.verts.len())
+        .push(verts, faces(&data, face.transform));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    enum TestBlock {
+        Empty,
+        Plane,
+        Solid,
+        Cube(u32),
+        Oriented(Shape, Transform),
+        Leaves,
+        Shifted([f32; 2]),
+        Paged(u16),
+    }
+
+    impl Block for TestBlock {
+        fn shape(&self) -> Option<ShapeRef<'_>> {
+            let shape = match self {
+                Self::Empty => return None,
+                Self::Plane => Shape::S0,
+                Self::Solid | Self::Cube(_) | Self::Leaves | Self::Shifted(_) | Self::Paged(_) => {
+                    Shape::S1
+                }
+                Self::Oriented(shape, _) => *shape,
+            };
+
+            Some(shape.into())
+        }
+
+        fn covers(&self) -> Sides {
+            match self {
+                Self::Empty | Self::Plane => Sides::empty(),
+                Self::Solid | Self::Cube(_) | Self::Leaves | Self::Shifted(_) | Self::Paged(_) => {
+                    Sides::all()
+                }
+                Self::Oriented(shape, transform) => transform.sides(shape.covers()),
+            }
+        }
+
+        fn transform(&self) -> Transform {
+            match self {
+                Self::Oriented(_, transform) => *transform,
+                _ => Transform::IDENTITY,
+            }
+        }
+
+        fn sprite(&self, _: usize) -> Option<Sprite> {
+            match self {
+                Self::Cube(id) => Some(Sprite {
+                    id: *id,
+                    ..Sprite::default()
+                }),
+                Self::Oriented(..) => Some(Sprite::default()),
+                Self::Leaves => Some(Sprite {
+                    discard: true,
+                    ..Sprite::default()
+                }),
+                Self::Shifted(offset) => Some(Sprite {
+                    offset: *offset,
+                    ..Sprite::default()
+                }),
+                Self::Paged(page) => Some(Sprite {
+                    uv: paged_uv(*page),
+                    ..Sprite::default()
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    type Chunk = ChunkData<TestBlock>;
+
+    fn paged_uv(page: u16) -> Uv {
+        Uv {
+            page,
+            pos: [0.5, 0.],
+            size: [0.5, 0.25],
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct KitBlock<'a>(Option<&'a kit::shape::Shape>);
+
+    impl Block for KitBlock<'_> {
+        fn shape(&self) -> Option<ShapeRef<'_>> {
+            self.0.map(ShapeRef::Kit)
+        }
+
+        fn covers(&self) -> Sides {
+            self.0.map_or(Sides::empty(), kit::shape::Shape::covers)
+        }
+
+        fn sprite(&self, _: usize) -> Option<Sprite> {
+            self.0.map(|_| Sprite::default())
+        }
+    }
+
+    fn point(x: u8, y: u8, z: u8) -> BlockPoint {
+        BlockPoint::new(x, y, z).unwrap()
+    }
+
+    fn n_faces(buffers: &[MeshBuffer]) -> usize {
+        buffers.iter().map(|buffer| buffer.faces().len()).sum()
+    }
+
+    #[test]
+    fn empty() {
+        let chunk = Chunk::new(TestBlock::Empty);
+        assert!(mesh(&chunk, &Neighbours::new(), Mode::Naive).is_empty());
+    }
+
+    #[test]
+    fn translated() {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(1, 2, 3)] = TestBlock::Plane;
+
+        let buffers = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        assert_eq!(buffers.len(), 1);
+
+        let [data] = Shape::S0.data() else {
+            panic!("single face shape expected");
+        };
+
+        let buffer = &buffers[0];
+        assert_eq!(buffer.faces(), data.mesh.faces);
+        for (vert, orig) in buffer.verts().iter().zip(data.mesh.verts) {
+            let [x, y, z] = orig.pos;
+            assert_eq!(vert.pos, [x + 1., y + 2., z + 3.]);
+            assert_eq!(vert.tex, orig.tex);
+        }
+    }
+
+    #[test]
+    fn culling() {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Solid;
+        assert_eq!(n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)), 12);
+
+        // Adjacent faces of solid blocks are covered
+        chunk[point(0, 1, 0)] = TestBlock::Solid;
+        assert_eq!(n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)), 20);
+
+        // The plane doesn't cover anything and it's never covered
+        chunk[point(0, 1, 0)] = TestBlock::Plane;
+        assert_eq!(n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)), 14);
+    }
+
+    #[test]
+    fn culling_across_chunks() {
+        let top = size::HEIGHT as u8 - 1;
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(5, top, 5)] = TestBlock::Solid;
+        assert_eq!(n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)), 12);
+
+        let mut above = Chunk::new(TestBlock::Empty);
+        let neighbours = Neighbours::new().with(Side::Up, &above);
+        assert_eq!(n_faces(&mesh(&chunk, &neighbours, Mode::Naive)), 12);
+
+        above[point(5, 0, 5)] = TestBlock::Solid;
+        let neighbours = Neighbours::new().with(Side::Up, &above);
+        assert_eq!(n_faces(&mesh(&chunk, &neighbours, Mode::Naive)), 10);
+
+        // Other sides don't affect the face
+        let neighbours = Neighbours::new().with(Side::Down, &above);
+        assert_eq!(n_faces(&mesh(&chunk, &neighbours, Mode::Naive)), 12);
+    }
+
+    /// Returns all 48 orientations of a block.
+    fn orientations() -> Vec<Transform> {
+        use crate::transform::Axis;
+
+        let mut all = vec![];
+        for mirror in [Transform::IDENTITY, Transform::mirror(Axis::X)] {
+            for x in 0..4 {
+                for y in 0..4 {
+                    for z in 0..4 {
+                        let tr = mirror
+                            .then(Transform::rotation(Axis::X, x))
+                            .then(Transform::rotation(Axis::Y, y))
+                            .then(Transform::rotation(Axis::Z, z));
+
+                        if !all.contains(&tr) {
+                            all.push(tr);
+                        }
+                    }
+                }
+            }
+        }
+
+        all
+    }
+
+    /// Returns the volume enclosed by the mesh.
+    ///
+    /// It's positive only if all faces look outside.
+    fn volume(buffers: &[MeshBuffer]) -> f32 {
+        let mut volume = 0.;
+        for buffer in buffers {
+            for face in buffer.faces() {
+                let [a, b, c] = face.map(|i| buffer.verts()[i as usize].pos);
+                volume += (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                    / 6.;
+            }
+        }
+
+        volume
+    }
+
+    #[test]
+    fn oriented() {
+        for transform in orientations() {
+            let mut chunk = Chunk::new(TestBlock::Empty);
+            chunk[point(1, 1, 1)] = TestBlock::Oriented(Shape::S4, transform);
+            for mode in [Mode::Naive, Mode::Greedy] {
+                let buffers = mesh(&chunk, &Neighbours::new(), mode);
+                assert!((volume(&buffers) - 0.75).abs() < 1e-4);
+                for vert in buffers.iter().flat_map(MeshBuffer::verts) {
+                    assert!(vert.pos.iter().all(|v| (1. ..=2.).contains(v)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oriented_culling() {
+        use crate::transform::Axis;
+
+        // The stairs cover the down and forth sides
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Solid;
+        chunk[point(1, 0, 0)] = TestBlock::Oriented(Shape::S4, Transform::IDENTITY);
+        let stairs = 20;
+        assert_eq!(
+            n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)),
+            12 + stairs - 4,
+        );
+
+        // Turned stairs cover the right side, so the left face of the solid is hidden
+        let turned = Transform::rotation(Axis::Y, 3);
+        assert_eq!(turned.side(Side::Forth), Side::Right);
+        chunk[point(1, 0, 0)] = TestBlock::Oriented(Shape::S4, turned);
+        assert_eq!(
+            n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)),
+            12 - 2 + stairs - 2,
+        );
+    }
+
+    fn floor(sprite: impl Fn(u8, u8) -> u32) -> Chunk {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        for z in 0..size::DEPTH as u8 {
+            for x in 0..size::WIDTH as u8 {
+                chunk[point(x, 0, z)] = TestBlock::Cube(sprite(x, z));
+            }
+        }
+
+        chunk
+    }
+
+    fn area(buffers: &[MeshBuffer]) -> f32 {
+        let mut area = 0.;
+        for buffer in buffers {
+            for face in buffer.faces() {
+                let [a, b, c] = face.map(|i| buffer.verts()[i as usize].pos);
+                let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+                let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+                let cross = [
+                    ab[1] * ac[2] - ab[2] * ac[1],
+                    ab[2] * ac[0] - ab[0] * ac[2],
+                    ab[0] * ac[1] - ab[1] * ac[0],
+                ];
+
+                area += (cross[0].powi(2) + cross[1].powi(2) + cross[2].powi(2)).sqrt() / 2.;
+            }
+        }
+
+        area
+    }
+
+    #[test]
+    fn greedy_floor() {
+        let chunk = floor(|_, _| 0);
+        let naive = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        let greedy = mesh(&chunk, &Neighbours::new(), Mode::Greedy);
+
+        // Top, bottom and 4 borders
+        let (width, depth) = (size::WIDTH as usize, size::DEPTH as usize);
+        assert_eq!(
+            n_faces(&naive),
+            (width * depth * 2 + (width + depth) * 2) * 2
+        );
+        assert_eq!(n_faces(&greedy), 6 * 2);
+        assert_eq!(area(&naive), area(&greedy));
+
+        // The top quad tiles the sprite once per block
+        let buffer = &greedy[0];
+        let mut n_top = 0;
+        for face in buffer.faces() {
+            let verts = face.map(|i| buffer.verts()[i as usize]);
+            if verts.iter().all(|vert| vert.pos[1] == 1.) {
+                n_top += 1;
+                for vert in verts {
+                    let [x, _, z] = vert.pos;
+                    assert_eq!(vert.tex, [x, z]);
+                }
+            }
+        }
+
+        assert_eq!(n_top, 2);
+    }
+
+    #[test]
+    fn greedy_sprites() {
+        // Two halves with different sprites aren't merged together
+        let half = size::WIDTH as u8 / 2;
+        let chunk = floor(|x, _| u32::from(x < half));
+        let naive = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        let greedy = mesh(&chunk, &Neighbours::new(), Mode::Greedy);
+        assert_eq!(n_faces(&greedy), (2 + 2 + 2 + 2 + 1 + 1) * 2);
+        assert_eq!(area(&naive), area(&greedy));
+
+        // Faces which can't be merged are kept as is
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Plane;
+        chunk[point(2, 0, 0)] = TestBlock::Plane;
+        assert_eq!(
+            n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Greedy)),
+            n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)),
+        );
+    }
+
+    #[test]
+    fn offset() {
+        let offset = [0.25, 0.5];
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Shifted(offset);
+
+        let buffers = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        let orig = Shape::S1.data().iter().flat_map(|data| data.mesh.verts);
+        for (vert, orig) in buffers[0].verts().iter().zip(orig) {
+            let [u, v] = orig.tex;
+            assert_eq!(vert.tex, [u + offset[0], v + offset[1]]);
+        }
+
+        // Merged quads are shifted the same way
+        chunk[point(1, 0, 0)] = TestBlock::Shifted(offset);
+        let shifted = mesh(&chunk, &Neighbours::new(), Mode::Greedy);
+        chunk[point(0, 0, 0)] = TestBlock::Cube(0);
+        chunk[point(1, 0, 0)] = TestBlock::Cube(0);
+        let plain = mesh(&chunk, &Neighbours::new(), Mode::Greedy);
+        assert_eq!(n_faces(&shifted), 6 * 2);
+        for (vert, orig) in shifted[0].verts().iter().zip(plain[0].verts()) {
+            let [u, v] = orig.tex;
+            assert_eq!(vert.pos, orig.pos);
+            assert_eq!(vert.tex, [u + offset[0], v + offset[1]]);
+        }
+    }
+
+    #[test]
+    fn discard() {
+        let passes = |buffers: &[MeshBuffer]| {
+            let n = |pass| {
+                let buffers: Vec<_> = buffers.iter().filter(|b| b.pass() == pass).collect();
+                buffers
+                    .iter()
+                    .map(|buffer| buffer.faces().len())
+                    .sum::<usize>()
+            };
+
+            (n(Pass::Solid), n(Pass::Cutout))
+        };
+
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Leaves;
+        for mode in [Mode::Naive, Mode::Greedy] {
+            assert_eq!(passes(&mesh(&chunk, &Neighbours::new(), mode)), (0, 12));
+        }
+
+        // Leaves don't hide the solid face, but the solid block hides the leaves face
+        chunk[point(1, 0, 0)] = TestBlock::Solid;
+        for mode in [Mode::Naive, Mode::Greedy] {
+            let buffers = mesh(&chunk, &Neighbours::new(), mode);
+            assert_eq!(passes(&buffers), (12, 10));
+
+            // Cutout buffers follow solid ones
+            assert_eq!(buffers.first().unwrap().pass(), Pass::Solid);
+            assert_eq!(buffers.last().unwrap().pass(), Pass::Cutout);
+        }
+
+        // Adjacent leaves don't hide each other
+        chunk[point(1, 0, 0)] = TestBlock::Leaves;
+        assert_eq!(
+            passes(&mesh(&chunk, &Neighbours::new(), Mode::Naive)),
+            (0, 24)
+        );
+
+        // Leaves are merged with each other in the greedy mode
+        assert_eq!(
+            passes(&mesh(&chunk, &Neighbours::new(), Mode::Greedy)),
+            (0, 16),
+        );
+
+        // Leaves in an adjacent chunk don't hide faces either
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Solid;
+        let mut right = Chunk::new(TestBlock::Empty);
+        right[point(size::WIDTH as u8 - 1, 0, 0)] = TestBlock::Leaves;
+        let neighbours = Neighbours::new().with(Side::Right, &right);
+        assert_eq!(passes(&mesh(&chunk, &neighbours, Mode::Naive)), (12, 0));
+    }
+
+    /// Returns triangles of the top face of the block at the `pos`.
+    fn top(buffers: &[MeshBuffer], [x, y, z]: [f32; 3]) -> Vec<[Vert; 3]> {
+        let above = |vert: &Vert| {
+            let [vx, vy, vz] = vert.pos;
+            vy == y + 1. && (x..=x + 1.).contains(&vx) && (z..=z + 1.).contains(&vz)
+        };
+
+        buffers
+            .iter()
+            .flat_map(|buffer| {
+                buffer
+                    .faces()
+                    .iter()
+                    .map(|face| face.map(|i| buffer.verts()[usize::from(i)]))
+            })
+            .filter(|verts| verts.iter().all(above))
+            .filter(|verts| {
+                let [a, b, c] = verts.map(|vert| vert.pos);
+                let normal = (b[2] - a[2]) * (c[0] - a[0]) - (b[0] - a[0]) * (c[2] - a[2]);
+                normal > 0.
+            })
+            .collect()
+    }
+
+    fn light_at(top: &[[Vert; 3]], x: f32, z: f32) -> f32 {
+        top.iter()
+            .flatten()
+            .find(|vert| vert.pos[0] == x && vert.pos[2] == z)
+            .expect("vertex")
+            .light
+    }
+
+    #[test]
+    fn occlusion() {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(1, 0, 1)] = TestBlock::Solid;
+        let verts = top(&mesh(&chunk, &Neighbours::new(), Mode::Naive), [1., 0., 1.]);
+        assert_eq!(verts.len(), 2);
+        assert!(verts.iter().flatten().all(|vert| vert.light == 1.));
+
+        // A solid block at a side darkens two vertices
+        chunk[point(2, 1, 1)] = TestBlock::Solid;
+        let verts = top(&mesh(&chunk, &Neighbours::new(), Mode::Naive), [1., 0., 1.]);
+        assert_eq!(light_at(&verts, 1., 1.), 1.);
+        assert_eq!(light_at(&verts, 1., 2.), 1.);
+        assert_eq!(light_at(&verts, 2., 1.), 0.8);
+        assert_eq!(light_at(&verts, 2., 2.), 0.8);
+
+        // Two sides darken the vertex completely, regardless of the corner
+        chunk[point(1, 1, 2)] = TestBlock::Solid;
+        let verts = top(&mesh(&chunk, &Neighbours::new(), Mode::Naive), [1., 0., 1.]);
+        assert_eq!(light_at(&verts, 2., 2.), 0.4);
+        chunk[point(2, 1, 2)] = TestBlock::Solid;
+        let verts = top(&mesh(&chunk, &Neighbours::new(), Mode::Naive), [1., 0., 1.]);
+        assert_eq!(light_at(&verts, 2., 2.), 0.4);
+
+        // The plane doesn't occlude anything
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(1, 0, 1)] = TestBlock::Solid;
+        chunk[point(2, 1, 2)] = TestBlock::Plane;
+        let verts = top(&mesh(&chunk, &Neighbours::new(), Mode::Naive), [1., 0., 1.]);
+        assert_eq!(verts.len(), 2);
+        assert!(verts.iter().flatten().all(|vert| vert.light == 1.));
+
+        // Blocks of adjacent chunks occlude too
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 1)] = TestBlock::Solid;
+        let mut right = Chunk::new(TestBlock::Empty);
+        right[point(size::WIDTH as u8 - 1, 1, 1)] = TestBlock::Solid;
+        let neighbours = Neighbours::new().with(Side::Right, &right);
+        let verts = top(&mesh(&chunk, &neighbours, Mode::Naive), [0., 0., 1.]);
+        assert_eq!(light_at(&verts, 0., 1.), 0.8);
+        assert_eq!(light_at(&verts, 1., 1.), 1.);
+    }
+
+    #[test]
+    fn flip() {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(1, 0, 1)] = TestBlock::Solid;
+        chunk[point(2, 1, 2)] = TestBlock::Solid;
+        let buffers = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+
+        // The dark corner is a vertex of only one triangle of the top quad
+        let top = top(&buffers, [1., 0., 1.]);
+        assert_eq!(top.len(), 2);
+        let dark = |verts: &[Vert; 3]| verts.iter().any(|vert| vert.light < 1.);
+        assert_eq!(top.iter().filter(|verts| dark(verts)).count(), 1);
+
+        assert_eq!(light_at(&top, 2., 2.), 0.8);
+    }
+
+    #[test]
+    fn smooth_light() {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(1, 0, 1)] = TestBlock::Solid;
+
+        // Sky light everywhere except a dim block light above
+        let mut light = ChunkData::new(light::pack(0, light::MAX));
+        light[point(2, 1, 2)] = light::pack(11, 0);
+
+        let buffers = mesh_lit(
+            &chunk,
+            &Neighbours::new(),
+            &Light::new(&light, Neighbours::new()),
+            Mode::Naive,
+        );
+
+        // The vertex light is the average of four levels: 15, 15, 15 and 11
+        let verts = top(&buffers, [1., 0., 1.]);
+        assert_eq!(light_at(&verts, 1., 1.), 1.);
+        assert_eq!(light_at(&verts, 2., 2.), 0.8);
+
+        // Faces without sides are lit by their block
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(2, 1, 2)] = TestBlock::Plane;
+        let buffers = mesh_lit(
+            &chunk,
+            &Neighbours::new(),
+            &Light::new(&light, Neighbours::new()),
+            Mode::Naive,
+        );
+
+        let level = 0.8_f32.powi(4);
+        assert!(buffers[0]
+            .verts()
+            .iter()
+            .all(|vert| (vert.light - level).abs() < 1e-6));
+    }
+
+    #[test]
+    fn greedy_light() {
+        let mut chunk = floor(|_, _| 0);
+        chunk[point(4, 1, 4)] = TestBlock::Cube(0);
+        let naive = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        let greedy = mesh(&chunk, &Neighbours::new(), Mode::Greedy);
+        assert_eq!(area(&naive), area(&greedy));
+
+        // Faces around the cube are occluded, so they aren't merged
+        assert!(n_faces(&greedy) < n_faces(&naive));
+        assert!(n_faces(&greedy) > 6 * 2 + 5 * 2);
+
+        // Merged quads around the cube are fully lit
+        for vert in greedy.iter().flat_map(|buffer| buffer.verts()) {
+            let [x, y, z] = vert.pos;
+            let near = (4. ..=5.).contains(&x) && (4. ..=5.).contains(&z);
+            if y == 1. && vert.light < 1. {
+                assert!(near, "{:?}", vert.pos);
+            }
+        }
+    }
+
+    #[test]
+    fn kit_shape() {
+        // Top and bottom quads of a block, they cover their sides
+        let shape: kit::shape::Shape = json::from_str(
+            "{
+                parts: [
+                    {
+                        verts: [
+                            { pos: [0, 1, 1], tex: [0, 1] },
+                            { pos: [1, 1, 1], tex: [1, 1] },
+                            { pos: [1, 1, 0], tex: [1, 0] },
+                            { pos: [0, 1, 0], tex: [0, 0] },
+                        ],
+                        faces: [[0, 1, 2], [0, 2, 3]],
+                        side: 'up',
+                    },
+                    {
+                        verts: [
+                            { pos: [0, 0, 0], tex: [0, 0] },
+                            { pos: [1, 0, 0], tex: [1, 0] },
+                            { pos: [1, 0, 1], tex: [1, 1] },
+                            { pos: [0, 0, 1], tex: [0, 1] },
+                        ],
+                        faces: [[0, 1, 2], [0, 2, 3]],
+                        side: 'down',
+                    },
+                ],
+                covers: ['up', 'down'],
+            }",
+        )
+        .unwrap();
+
+        assert!(shape.validate().is_ok());
+
+        let block = KitBlock(Some(&shape));
+        let mut chunk: ChunkData<_> = ChunkData::new(KitBlock(None));
+        chunk[point(0, 0, 0)] = block;
+        assert_eq!(n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)), 4);
+
+        // Parts between stacked blocks are hidden
+        chunk[point(0, 1, 0)] = block;
+        assert_eq!(n_faces(&mesh(&chunk, &Neighbours::new(), Mode::Naive)), 4);
+
+        // Parts of adjacent blocks are merged
+        chunk[point(0, 1, 0)] = KitBlock(None);
+        chunk[point(1, 0, 0)] = block;
+        let naive = mesh(&chunk, &Neighbours::new(), Mode::Naive);
+        let greedy = mesh(&chunk, &Neighbours::new(), Mode::Greedy);
+        assert_eq!(n_faces(&naive), 8);
+        assert_eq!(n_faces(&greedy), 4);
+        assert_eq!(area(&naive), area(&greedy));
+    }
+
+    #[test]
+    fn pages() {
+        let mut chunk = Chunk::new(TestBlock::Empty);
+        chunk[point(0, 0, 0)] = TestBlock::Paged(0);
+        chunk[point(2, 0, 0)] = TestBlock::Paged(1);
+        chunk[point(3, 0, 0)] = TestBlock::Paged(0);
+        for mode in [Mode::Naive, Mode::Greedy] {
+            let buffers = mesh(&chunk, &Neighbours::new(), mode);
+            assert_eq!(buffers.len(), 2);
+            assert_ne!(buffers[0].page(), buffers[1].page());
+
+            // Vertices keep the sprite rect of their page
+            for buffer in &buffers {
+                let rect = paged_uv(buffer.page()).rect();
+                assert!(buffer.verts().iter().all(|vert| vert.rect == rect));
+            }
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! The network protocol.
+//!
+//! Every message is sent in a frame: a 4-byte little-endian payload length
+//! followed by the payload. The payload starts with a message tag byte.
+//!
+//! A session starts with the handshake: a client sends `Hello` with its protocol
+//! version and the server answers `Welcome` with the kit it runs, or `Disconnect`
+//! with a reason. After that the client can request the kit archive, chunks and
+//! change blocks. The kit archive is sent in `KitPart` messages of at most
+//! `KIT_PART_LEN` bytes each. A chunk which the server fails to load is
+//! answered by `ChunkError`, unlike a missing one. Every block change is
+//! broadcasted to all clients by `BlockChanged`.
+
+use {
+    crate::{
+        kit::Hash,
+        point::{ChunkPoint, WorldPoint},
+    },
+    std::{
+        fmt,
+        io::{self, Read, Write},
+    },
+};
+
+/// The current protocol version.
+pub const VERSION: u16 = 3;
+
+/// The maximum payload length of a frame.
+pub const MAX_FRAME_LEN: u32 = 1 << 24;
+
+/// The maximum length of a kit archive part.
+pub const KIT_PART_LEN: usize = 1 << 20;
+
+/// A message sent by a client.
+#[derive(Debug, PartialEq)]
+pub enum ClientMessage {
+    Hello { version: u16 },
+    RequestKit,
+    RequestChunk(ChunkPoint),
+    SetBlock { point: WorldPoint, block: u16 },
+    Disconnect(Reason),
+}
+
+/// A message sent by the server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    Welcome {
+        kit: String,
+        hash: Hash,
+    },
+    /// A part of the kit archive of `size` bytes in total.
+    KitPart {
+        size: u32,
+        data: Vec<u8>,
+    },
+    /// An encoded chunk, or `None` if the chunk doesn't exist.
+    Chunk {
+        point: ChunkPoint,
+        data: Option<Vec<u8>>,
+    },
+    /// The chunk exists, but the server failed to load it.
+    ChunkError {
+        point: ChunkPoint,
+    },
+    BlockChanged {
+        point: WorldPoint,
+        block: u16,
+    },
+    Disconnect(Reason),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reason {
+    Quit = 0,
+    Version = 1,
+    Protocol = 2,
+    Shutdown = 3,
+}
+
+impl Reason {
+    fn from_u8(value: u8) -> Result<Self, Error> {
+        let reason = match value {
+            0 => Self::Quit,
+            1 => Self::Version,
+            2 => Self::Protocol,
+            3 => Self::Shutdown,
+            _ => return Err(Error::Value),
+        };
+
+        Ok(reason)
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Quit => write!(f, "quit"),
+            Self::Version => write!(f, "incompatible protocol version"),
+            Self::Protocol => write!(f, "protocol violation"),
+            Self::Shutdown => write!(f, "server shutdown"),
+        }
+    }
+}
+
+pub trait Message: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+    fn decode(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+impl Message for ClientMessage {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Hello { version } => {
+                buf.push(0);
+                buf.extend(version.to_le_bytes());
+            }
+            Self::RequestKit => buf.push(1),
+            Self::RequestChunk(point) => {
+                buf.push(2);
+                write_chunk_point(*point, buf);
+            }
+            Self::SetBlock { point, block } => {
+                buf.push(3);
+                write_world_point(*point, buf);
+                buf.extend(block.to_le_bytes());
+            }
+            Self::Disconnect(reason) => {
+                buf.push(4);
+                buf.push(*reason as u8);
+            }
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+        let message = match reader.byte()? {
+            0 => Self::Hello {
+                version: reader.u16()?,
+            },
+            1 => Self::RequestKit,
+            2 => Self::RequestChunk(reader.chunk_point()?),
+            3 => Self::SetBlock {
+                point: reader.world_point()?,
+                block: reader.u16()?,
+            },
+            4 => Self::Disconnect(Reason::from_u8(reader.byte()?)?),
+            tag => return Err(Error::Tag(tag)),
+        };
+
+        reader.finish()?;
+        Ok(message)
+    }
+}
+
+impl Message for ServerMessage {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Welcome { kit, hash } => {
+                buf.push(0);
+                write_bytes(kit.as_bytes(), buf);
+                buf.extend(hash.get().to_le_bytes());
+            }
+            Self::KitPart { size, data } => {
+                buf.push(1);
+                buf.extend(size.to_le_bytes());
+                write_bytes(data, buf);
+            }
+            Self::Chunk { point, data } => {
+                buf.push(2);
+                write_chunk_point(*point, buf);
+                match data {
+                    Some(data) => {
+                        buf.push(1);
+                        write_bytes(data, buf);
+                    }
+                    None => buf.push(0),
+                }
+            }
+            Self::BlockChanged { point, block } => {
+                buf.push(3);
+                write_world_point(*point, buf);
+                buf.extend(block.to_le_bytes());
+            }
+            Self::Disconnect(reason) => {
+                buf.push(4);
+                buf.push(*reason as u8);
+            }
+            Self::ChunkError { point } => {
+                buf.push(5);
+                write_chunk_point(*point, buf);
+            }
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+        let message = match reader.byte()? {
+            0 => Self::Welcome {
+                kit: String::from_utf8(reader.bytes()?.to_vec()).map_err(|_| Error::Value)?,
+                hash: Hash::from_u64(u64::from_le_bytes(reader.array()?)),
+            },
+            1 => Self::KitPart {
+                size: u32::from_le_bytes(reader.array()?),
+                data: reader.bytes()?.to_vec(),
+            },
+            2 => Self::Chunk {
+                point: reader.chunk_point()?,
+                data: match reader.byte()? {
+                    0 => None,
+                    1 => Some(reader.bytes()?.to_vec()),
+                    _ => return Err(Error::Value),
+                },
+            },
+            3 => Self::BlockChanged {
+                point: reader.world_point()?,
+                block: reader.u16()?,
+            },
+            4 => Self::Disconnect(Reason::from_u8(reader.byte()?)?),
+            5 => Self::ChunkError {
+                point: reader.chunk_point()?,
+            },
+            tag => return Err(Error::Tag(tag)),
+        };
+
+        reader.finish()?;
+        Ok(message)
+    }
+}
+
+/// Encodes the message in a frame.
+pub fn frame<M>(message: &M) -> Vec<u8>
+where
+    M: Message,
+{
+    let mut buf = vec![0; 4];
+    message.encode(&mut buf);
+    let len = u32::try_from(buf.len() - 4).expect("frame length");
+    assert!(len <= MAX_FRAME_LEN, "too large frame");
+    buf[..4].copy_from_slice(&len.to_le_bytes());
+    buf
+}
+
+/// Returns the payload length from a frame header.
+pub fn frame_len(header: [u8; 4]) -> Result<usize, Error> {
+    let len = u32::from_le_bytes(header);
+    if len > MAX_FRAME_LEN {
+        return Err(Error::TooLarge(len));
+    }
+
+    Ok(len as usize)
+}
+
+/// Writes the message in a frame.
+pub fn write<W, M>(mut writer: W, message: &M) -> io::Result<()>
+where
+    W: Write,
+    M: Message,
+{
+    writer.write_all(&frame(message))?;
+    writer.flush()
+}
+
+/// Reads a message from a frame.
+pub fn read<R, M>(mut reader: R) -> Result<M, Error>
+where
+    R: Read,
+    M: Message,
+{
+    let mut header = [0; 4];
+    reader.read_exact(&mut header)?;
+    let mut buf = vec![0; frame_len(header)?];
+    reader.read_exact(&mut buf)?;
+    M::decode(&buf)
+}
+
+fn write_bytes(bytes: &[u8], buf: &mut Vec<u8>) {
+    let len = u32::try_from(bytes.len()).expect("bytes length");
+    buf.extend(len.to_le_bytes());
+    buf.extend(bytes);
+}
+
+fn write_chunk_point(point: ChunkPoint, buf: &mut Vec<u8>) {
+    let (x, y, z) = point.into();
+    buf.extend([x as u8, y as u8, z as u8]);
+}
+
+fn write_world_point(point: WorldPoint, buf: &mut Vec<u8>) {
+    let (x, y, z) = point.absolute();
+    buf.extend(x.to_le_bytes());
+    buf.extend(y.to_le_bytes());
+    buf.extend(z.to_le_bytes());
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < n {
+            return Err(Error::Truncated);
+        }
+
+        let (left, right) = self.0.split_at(n);
+        self.0 = right;
+        Ok(left)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        self.take(N).map(|bytes| bytes.try_into().expect("array"))
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        self.array().map(|[b]| b)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = u32::from_le_bytes(self.array()?);
+        self.take(len as usize)
+    }
+
+    fn chunk_point(&mut self) -> Result<ChunkPoint, Error> {
+        let [x, y, z] = self.array()?;
+        ChunkPoint::new(x as i8, y as i8, z as i8).ok_or(Error::Value)
+    }
+
+    fn world_point(&mut self) -> Result<WorldPoint, Error> {
+        let (x, y, z) = (self.i32()?, self.i32()?, self.i32()?);
+        WorldPoint::from_absolute(x, y, z).ok_or(Error::Value)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TrailingBytes(self.0.len()))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    TooLarge(u32),
+    Truncated,
+    Tag(u8),
+    Value,
+    TrailingBytes(usize),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {err}"),
+            Self::TooLarge(len) => write!(f, "too large frame of {len} bytes"),
+            Self::Truncated => write!(f, "unexpected end of message"),
+            Self::Tag(tag) => write!(f, "unknown message tag {tag}"),
+            Self::Value => write!(f, "invalid value in message"),
+            Self::TrailingBytes(n) => write!(f, "{n} trailing bytes after message"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<M>(message: M)
+    where
+        M: Message + fmt::Debug + PartialEq,
+    {
+        let mut buf = vec![];
+        write(&mut buf, &message).unwrap();
+        let decoded: M = read(&buf[..]).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn client_messages() {
+        round_trip(ClientMessage::Hello { version: VERSION });
+        round_trip(ClientMessage::RequestKit);
+        round_trip(ClientMessage::RequestChunk(
+            ChunkPoint::new(-1, 127, 0).unwrap(),
+        ));
+        round_trip(ClientMessage::SetBlock {
+            point: WorldPoint::from_absolute(-45, 50, 32).unwrap(),
+            block: 3,
+        });
+        round_trip(ClientMessage::Disconnect(Reason::Quit));
+    }
+
+    #[test]
+    fn server_messages() {
+        round_trip(ServerMessage::Welcome {
+            kit: "base".into(),
+            hash: Hash::new(b"kit"),
+        });
+        round_trip(ServerMessage::KitPart {
+            size: 10,
+            data: vec![0; 4],
+        });
+        round_trip(ServerMessage::Chunk {
+            point: ChunkPoint::new(0, 0, 0).unwrap(),
+            data: Some(vec![1, 2, 3]),
+        });
+        round_trip(ServerMessage::Chunk {
+            point: ChunkPoint::new(0, 0, 0).unwrap(),
+            data: None,
+        });
+        round_trip(ServerMessage::ChunkError {
+            point: ChunkPoint::new(1, -2, 3).unwrap(),
+        });
+        round_trip(ServerMessage::BlockChanged {
+            point: WorldPoint::from_absolute(1, 2, 3).unwrap(),
+            block: 0,
+        });
+        round_trip(ServerMessage::Disconnect(Reason::Version));
+    }
+
+    #[test]
+    fn invalid() {
+        let read_client = |bytes: &[u8]| read::<_, ClientMessage>(bytes).err().unwrap();
+
+        assert!(matches!(read_client(&[1, 0, 0, 0, 9]), Error::Tag(9)));
+        assert!(matches!(read_client(&[1, 0, 0, 0, 0]), Error::Truncated));
+        assert!(matches!(
+            read_client(&[4, 0, 0, 0, 2, 128, 0, 0]),
+            Error::Value,
+        ));
+        assert!(matches!(
+            read_client(&[3, 0, 0, 0, 4, 0, 0]),
+            Error::TrailingBytes(1),
+        ));
+        assert!(matches!(
+            read_client(&[0xff, 0xff, 0xff, 0xff]),
+            Error::TooLarge(_),
+        ));
+        assert!(matches!(read_client(&[1, 0, 0]), Error::Io(_)));
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt;
+use {crate::side::Side, std::fmt};
 
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Point {
@@ -15,6 +15,21 @@ impl Point {
             None
         }
     }
+
+    /// Returns the neighbouring chunk at `side` or `None` if it's outside of the world.
+    pub fn to(self, side: Side) -> Option<Self> {
+        let Self { x, y, z } = self;
+        let (x, y, z) = match side {
+            Side::Left => (x.checked_add(1)?, y, z),
+            Side::Right => (x - 1, y, z),
+            Side::Up => (x, y.checked_add(1)?, z),
+            Side::Down => (x, y - 1, z),
+            Side::Forth => (x, y, z.checked_add(1)?),
+            Side::Back => (x, y, z - 1),
+        };
+
+        Self::new(x, y, z)
+    }
 }
 
 impl From<Point> for (i8, i8, i8) {
@@ -37,3 +52,26 @@ impl fmt::Debug for Point {
         write!(f, "[{x}, {y}, {z}]")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::side::Sides};
+
+    #[test]
+    fn to() {
+        let a = Point::new(0, 0, 0).unwrap();
+        for side in Sides::all() {
+            assert_eq!(a.to(side).and_then(|b| b.to(side.opposite())), Some(a));
+        }
+
+        assert_eq!(a.to(Side::Left), Point::new(1, 0, 0));
+        assert_eq!(a.to(Side::Down), Point::new(0, -1, 0));
+        assert_eq!(a.to(Side::Back), Point::new(0, 0, -1));
+
+        // The world bounds
+        let max = Point::new(i8::MAX, 0, 0).unwrap();
+        assert_eq!(max.to(Side::Left), None);
+        let min = Point::new(0, 0, i8::MIN + 1).unwrap();
+        assert_eq!(min.to(Side::Back), None);
+    }
+}
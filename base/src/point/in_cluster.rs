@@ -2,6 +2,7 @@ use {
     crate::{
         chunk::size::*,
         point::{BlockPoint, ChunkPoint},
+        side::{Side, Sides},
     },
     std::fmt,
 };
@@ -54,6 +55,66 @@ impl Point {
             i32::from(chz) * DEPTH as i32 + i32::from(blz),
         )
     }
+
+    /// Steps to `side` `n` times, carrying into the neighbouring chunk.
+    ///
+    /// Returns `None` if it goes outside of the world.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not in chunk bounds.
+    pub fn to(self, side: Side, n: u8) -> Option<Self> {
+        match self.bl.to(side, n) {
+            Ok(bl) => Some(Self::new(bl, self.ch)),
+            Err(bl) => Some(Self::new(bl, self.ch.to(side)?)),
+        }
+    }
+
+    /// Moves the point by the `offset` of blocks.
+    ///
+    /// Returns `None` if it goes outside of the world.
+    pub fn offset(self, (dx, dy, dz): (i32, i32, i32)) -> Option<Self> {
+        let (x, y, z) = self.absolute();
+        Self::from_absolute(x.checked_add(dx)?, y.checked_add(dy)?, z.checked_add(dz)?)
+    }
+
+    /// Returns the offset from this point to the `other`.
+    pub fn delta(self, other: Self) -> (i32, i32, i32) {
+        let (ax, ay, az) = self.absolute();
+        let (bx, by, bz) = other.absolute();
+        (bx - ax, by - ay, bz - az)
+    }
+
+    /// Returns the squared euclidean distance between points.
+    pub fn distance_squared(self, other: Self) -> u32 {
+        let (dx, dy, dz) = self.delta(other);
+        (dx * dx + dy * dy + dz * dz) as u32
+    }
+
+    /// Returns the number of steps between points through their faces.
+    pub fn manhattan(self, other: Self) -> u32 {
+        let (dx, dy, dz) = self.delta(other);
+        dx.unsigned_abs() + dy.unsigned_abs() + dz.unsigned_abs()
+    }
+
+    /// Iterates over points which share a face with this one.
+    ///
+    /// Points outside of the world are skipped.
+    pub fn neighbours(self) -> impl Iterator<Item = (Side, Self)> {
+        Sides::all()
+            .into_iter()
+            .filter_map(move |side| Some((side, self.to(side, 1)?)))
+    }
+
+    /// Iterates over points which share a face, an edge or a corner with this one.
+    ///
+    /// Points outside of the world are skipped.
+    pub fn around(self) -> impl Iterator<Item = Self> {
+        (-1..=1)
+            .flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| (x, y, z))))
+            .filter(|&offset| offset != (0, 0, 0))
+            .filter_map(move |offset| self.offset(offset))
+    }
 }
 
 impl fmt::Display for Point {
@@ -89,4 +150,109 @@ mod tests {
             assert_eq!(point.absolute(), (x, y, z));
         }
     }
+
+    fn shift(side: Side) -> (i32, i32, i32) {
+        match side {
+            Side::Left => (1, 0, 0),
+            Side::Right => (-1, 0, 0),
+            Side::Up => (0, 1, 0),
+            Side::Down => (0, -1, 0),
+            Side::Forth => (0, 0, 1),
+            Side::Back => (0, 0, -1),
+        }
+    }
+
+    /// Points on every face of the chunk `(0, -1, 1)` and its neighbours.
+    fn faces() -> impl Iterator<Item = Point> {
+        let (w, h, d) = (WIDTH as i32, HEIGHT as i32, DEPTH as i32);
+        let (ox, oy, oz) = (0, -h, d);
+        let xs = [-1, 0, 1, w / 2, w - 2, w - 1, w];
+        let ys = [-1, 0, 1, h / 2, h - 2, h - 1, h];
+        let zs = [-1, 0, 1, d / 2, d - 2, d - 1, d];
+        xs.into_iter()
+            .flat_map(move |x| ys.into_iter().map(move |y| (x, y)))
+            .flat_map(move |(x, y)| zs.into_iter().map(move |z| (x, y, z)))
+            .map(move |(x, y, z)| Point::from_absolute(ox + x, oy + y, oz + z).unwrap())
+    }
+
+    #[test]
+    fn to() {
+        for point in faces() {
+            let (x, y, z) = point.absolute();
+            for side in Sides::all() {
+                let (dx, dy, dz) = shift(side);
+                let size = match side {
+                    Side::Left | Side::Right => WIDTH,
+                    Side::Up | Side::Down => HEIGHT,
+                    Side::Forth | Side::Back => DEPTH,
+                } as u8;
+
+                for n in 0..size {
+                    let m = i32::from(n);
+                    let expected = Point::from_absolute(x + dx * m, y + dy * m, z + dz * m);
+                    assert_eq!(point.to(side, n), expected, "{point} to {side} by {n}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn to_bounds() {
+        let max = i32::from(i8::MAX);
+        let min = i32::from(i8::MIN) + 1;
+        let (w, h, d) = (WIDTH as i32, HEIGHT as i32, DEPTH as i32);
+
+        let edge = Point::from_absolute(max * w + w - 1, 0, 0).unwrap();
+        assert_eq!(edge.to(Side::Left, 1), None);
+        assert!(edge.to(Side::Right, 1).is_some());
+
+        let edge = Point::from_absolute(0, min * h, 0).unwrap();
+        assert_eq!(edge.to(Side::Down, 1), None);
+        assert!(edge.to(Side::Up, 1).is_some());
+
+        let edge = Point::from_absolute(0, 0, max * d + d - 1).unwrap();
+        assert_eq!(edge.to(Side::Forth, 3), None);
+        assert_eq!(edge.neighbours().count(), 5);
+        assert_eq!(edge.around().count(), 17);
+    }
+
+    #[test]
+    fn neighbours() {
+        for point in faces() {
+            let neighbours: Vec<_> = point.neighbours().collect();
+            assert_eq!(neighbours.len(), 6);
+            for (side, neighbour) in neighbours {
+                assert_eq!(point.delta(neighbour), shift(side));
+                assert_eq!(point.manhattan(neighbour), 1);
+                assert_eq!(neighbour.to(side.opposite(), 1), Some(point));
+            }
+
+            let around: Vec<_> = point.around().collect();
+            assert_eq!(around.len(), 26);
+            for neighbour in &around {
+                let (dx, dy, dz) = point.delta(*neighbour);
+                assert!(dx.abs() <= 1 && dy.abs() <= 1 && dz.abs() <= 1);
+                assert!(point.manhattan(*neighbour) > 0);
+            }
+
+            let mut unique = around.clone();
+            unique.sort_unstable_by_key(|point| point.absolute());
+            unique.dedup();
+            assert_eq!(unique.len(), 26);
+        }
+    }
+
+    #[test]
+    fn offset() {
+        let a = Point::from_absolute(15, -1, 16).unwrap();
+        let b = a.offset((-20, 40, 3)).unwrap();
+        assert_eq!(b.absolute(), (-5, 39, 19));
+        assert_eq!(a.delta(b), (-20, 40, 3));
+        assert_eq!(b.delta(a), (20, -40, -3));
+        assert_eq!(a.distance_squared(b), 400 + 1600 + 9);
+        assert_eq!(a.manhattan(b), 63);
+        assert_eq!(a.offset((0, 0, 0)), Some(a));
+        assert_eq!(a.offset((i32::MAX, 0, 0)), None);
+        assert_eq!(a.offset((0, -10_000, 0)), None);
+    }
 }
@@ -0,0 +1,201 @@
+use crate::{point::WorldPoint, side::Side};
+
+/// A ray in absolute world coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    /// The direction of the ray, it doesn't have to be normalized.
+    pub dir: [f32; 3],
+}
+
+/// A block hit by a ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    pub point: WorldPoint,
+    /// The side of the block through which the ray entered it.
+    /// It's `None` if the ray starts inside the block.
+    pub side: Option<Side>,
+    /// The distance from the ray origin to the hit.
+    pub distance: f32,
+}
+
+/// Walks blocks along the `ray` up to the `max` distance and returns the first
+/// one for which `solid` returns `true`.
+///
+/// Blocks are visited in the order the ray crosses them, so it never skips
+/// a block even if the ray passes only through its corner.
+/// Points outside of the world are skipped.
+///
+/// # Panics
+///
+/// Panics if `max` is not finite.
+pub fn cast<S>(ray: Ray, max: f32, mut solid: S) -> Option<Hit>
+where
+    S: FnMut(WorldPoint) -> bool,
+{
+    assert!(max.is_finite());
+
+    let Ray { origin, dir } = ray;
+    let len = dir.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if !len.is_normal() || origin.iter().any(|v| !v.is_finite()) {
+        return None;
+    }
+
+    let dir = dir.map(|v| v / len);
+    let mut block = origin.map(|v| v.floor() as i32);
+    let mut step = [0; 3];
+    let mut delta = [f32::INFINITY; 3];
+    let mut next = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        let (o, d) = (origin[axis], dir[axis]);
+        if d > 0. {
+            step[axis] = 1;
+            delta[axis] = 1. / d;
+            next[axis] = (o.floor() + 1. - o) / d;
+        } else if d < 0. {
+            step[axis] = -1;
+            delta[axis] = -1. / d;
+            next[axis] = (o - o.floor()) / -d;
+        }
+    }
+
+    let mut side = None;
+    let mut distance = 0.;
+    loop {
+        let [x, y, z] = block;
+        if let Some(point) = WorldPoint::from_absolute(x, y, z) {
+            if solid(point) {
+                return Some(Hit {
+                    point,
+                    side,
+                    distance,
+                });
+            }
+        }
+
+        // Step through the nearest face
+        let axis = (0..3)
+            .min_by(|&a, &b| next[a].total_cmp(&next[b]))
+            .expect("axis");
+
+        distance = next[axis];
+        if distance > max {
+            return None;
+        }
+
+        block[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        // The ray enters the block through the face opposite to the step
+        side = Some(match (axis, step[axis]) {
+            (0, 1) => Side::Right,
+            (0, _) => Side::Left,
+            (1, 1) => Side::Down,
+            (1, _) => Side::Up,
+            (2, 1) => Side::Back,
+            (_, _) => Side::Forth,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(x: i32, y: i32, z: i32) -> WorldPoint {
+        WorldPoint::from_absolute(x, y, z).unwrap()
+    }
+
+    fn ray(origin: [f32; 3], dir: [f32; 3]) -> Ray {
+        Ray { origin, dir }
+    }
+
+    fn assert_hit(hit: Option<Hit>, point: (i32, i32, i32), side: Option<Side>, distance: f32) {
+        let hit = hit.expect("hit");
+        assert_eq!(hit.point.absolute(), point);
+        assert_eq!(hit.side, side);
+        assert!(
+            (hit.distance - distance).abs() < 1e-5,
+            "{} != {distance}",
+            hit.distance,
+        );
+    }
+
+    #[test]
+    fn axis_aligned() {
+        let wall = |point: WorldPoint| point.absolute() == (0, 0, 0);
+        let origin = [0.5, 0.5, 0.5];
+        for (dir, side) in [
+            ([-1., 0., 0.], Side::Left),
+            ([1., 0., 0.], Side::Right),
+            ([0., -1., 0.], Side::Up),
+            ([0., 1., 0.], Side::Down),
+            ([0., 0., -1.], Side::Forth),
+            ([0., 0., 1.], Side::Back),
+        ] {
+            // Start 3.5 blocks away and look back at the block
+            let origin = [0, 1, 2].map(|i| origin[i] - dir[i] * 3.);
+            let hit = cast(ray(origin, dir), 10., wall);
+            assert_hit(hit, (0, 0, 0), Some(side), 2.5);
+        }
+    }
+
+    #[test]
+    fn diagonal() {
+        let target = |point: WorldPoint| point.absolute() == (3, 3, 0);
+        let hit = cast(ray([0.5, 0.5, 0.5], [1., 1., 0.]), 10., target);
+
+        // The ray passes exactly through corners, the X step is taken first
+        assert_hit(hit, (3, 3, 0), Some(Side::Down), 2.5 * 2f32.sqrt());
+
+        let target = |point: WorldPoint| point.absolute() == (2, 1, 0);
+        let hit = cast(ray([0.5, 0.5, 0.5], [2., 1., 0.]), 10., target);
+        assert_hit(hit, (2, 1, 0), Some(Side::Right), 1.5 * 1.25f32.sqrt());
+
+        // Every visited block shares a face with the previous one
+        let mut visited = vec![];
+        cast(ray([0.2, 0.7, 0.4], [3., -2., 1.]), 20., |point| {
+            visited.push(point);
+            false
+        });
+
+        assert!(visited.len() > 20);
+        for pair in visited.windows(2) {
+            assert_eq!(pair[0].manhattan(pair[1]), 1);
+        }
+    }
+
+    #[test]
+    fn across_chunks() {
+        let target = |point: WorldPoint| point.absolute() == (-20, -1, 17);
+        let hit = cast(ray([-3.5, -0.5, 17.5], [-1., 0., 0.]), 100., target);
+        assert_hit(hit, (-20, -1, 17), Some(Side::Left), 15.5);
+        assert_ne!(
+            hit.unwrap().point.chunk_point(),
+            world(-3, -1, 17).chunk_point()
+        );
+    }
+
+    #[test]
+    fn inside() {
+        let hit = cast(ray([1.5, 2.5, 3.5], [0., 1., 0.]), 10., |_| true);
+        assert_hit(hit, (1, 2, 3), None, 0.);
+    }
+
+    #[test]
+    fn miss() {
+        let wall = |point: WorldPoint| point.absolute().0 == 10;
+        let origin = [0.5, 0.5, 0.5];
+        assert!(cast(ray(origin, [1., 0., 0.]), 9., wall).is_none());
+        assert_hit(
+            cast(ray(origin, [1., 0., 0.]), 9.5, wall),
+            (10, 0, 0),
+            Some(Side::Right),
+            9.5,
+        );
+
+        assert!(cast(ray(origin, [-1., 0., 0.]), 100., wall).is_none());
+        assert!(cast(ray(origin, [0., 0., 0.]), 100., |_| true).is_none());
+        assert!(cast(ray(origin, [f32::NAN, 0., 0.]), 100., |_| true).is_none());
+    }
+}
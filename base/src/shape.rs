@@ -1,57 +1,81 @@
 use {
     crate::{
         graphics::{MeshData, Vert},
-        side::Side,
+        side::{Side, Sides},
     },
     serde::Deserialize,
     std::fmt,
 };
 
-#[derive(Clone, Copy, Debug, Deserialize)]
+/// A block shape.
+///
+/// Every shape fits in a unit cube from `[0, 0, 0]` to `[1, 1, 1]`.
+/// Faces are counter-clockwise when they're seen from their front side.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
 #[serde(try_from = "u8")]
 pub enum Shape {
+    /// A horizontal quad at half height.
     S0 = 0,
+    /// A full cube.
+    S1 = 1,
+    /// A bottom slab.
+    S2 = 2,
+    /// A top slab.
+    S3 = 3,
+    /// Stairs rising to the forth side.
+    S4 = 4,
+    /// A cross of two vertical quads, like a plant.
+    S5 = 5,
+    /// A thin vertical post.
+    S6 = 6,
+    /// A slope rising to the forth side.
+    S7 = 7,
 }
 
 impl Shape {
     pub const fn from_id(id: u8) -> Result<Self, ShapeIdError> {
         let shape = match id {
             0 => Self::S0,
+            1 => Self::S1,
+            2 => Self::S2,
+            3 => Self::S3,
+            4 => Self::S4,
+            5 => Self::S5,
+            6 => Self::S6,
+            7 => Self::S7,
             _ => return Err(ShapeIdError(())),
         };
 
         Ok(shape)
     }
 
-    pub const fn data(self) -> &'static [Data] {
-        const S0: Data = Data {
-            // TODO: Add faces
-            mesh: MeshData {
-                verts: &[
-                    Vert {
-                        pos: [0., 0.5, 0.],
-                        tex: [0., 0.],
-                    },
-                    Vert {
-                        pos: [1., 0.5, 0.],
-                        tex: [1., 0.],
-                    },
-                    Vert {
-                        pos: [1., 0.5, 1.],
-                        tex: [1., 1.],
-                    },
-                    Vert {
-                        pos: [0., 0.5, 1.],
-                        tex: [0., 1.],
-                    },
-                ],
-                faces: &[[0, 1, 2], [1, 3, 0]],
-            },
-            side: Side::Up,
-        };
+    pub const fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub const fn data(self) -> &'static [Data<'static>] {
+        match self {
+            Self::S0 => S0,
+            Self::S1 => S1,
+            Self::S2 => S2,
+            Self::S3 => S3,
+            Self::S4 => S4,
+            Self::S5 => S5,
+            Self::S6 => S6,
+            Self::S7 => S7,
+        }
+    }
 
+    /// Returns sides which the shape covers completely.
+    ///
+    /// A neighbour's face adjacent to a covered side is hidden.
+    pub fn covers(self) -> Sides {
         match self {
-            Self::S0 => &[S0],
+            Self::S0 | Self::S5 | Self::S6 => Sides::empty(),
+            Self::S1 => Sides::all(),
+            Self::S2 => Side::Down.into(),
+            Self::S3 => Side::Up.into(),
+            Self::S4 | Self::S7 => Side::Down | Side::Forth,
         }
     }
 }
@@ -72,7 +96,317 @@ impl fmt::Display for ShapeIdError {
     }
 }
 
-pub struct Data {
-    pub mesh: MeshData<'static>,
-    pub side: Side,
+/// A face of a shape, it's a part of a kit shape as well.
+#[derive(Clone, Copy)]
+pub struct Data<'a> {
+    pub mesh: MeshData<'a>,
+    /// The side of the face.
+    ///
+    /// The face is hidden if the neighbour on this side covers it. If the face
+    /// doesn't lie on a side of the block, it's `None` and it's never hidden.
+    pub side: Option<Side>,
+}
+
+/// Returns texture coordinates of the point on a face which looks to the `facing` side.
+///
+/// Texture coordinates start from the top left corner of a sprite.
+/// A sprite of a vertical face is upright, and a sprite of a horizontal
+/// face is oriented as it's seen from the forth side.
+const fn uv(facing: Side, [x, y, z]: [f32; 3]) -> [f32; 2] {
+    match facing {
+        Side::Left => [1. - z, 1. - y],
+        Side::Right => [z, 1. - y],
+        Side::Up => [x, z],
+        Side::Down => [x, 1. - z],
+        Side::Forth => [x, 1. - y],
+        Side::Back => [1. - x, 1. - y],
+    }
+}
+
+const fn vert(facing: Side, pos: [f32; 3]) -> Vert {
+    Vert {
+        pos,
+        tex: uv(facing, pos),
+        light: 1.,
+        rect: Vert::FULL_RECT,
+    }
+}
+
+/// Makes a quad which looks to the `facing` side.
+macro_rules! quad {
+    ($facing:expr, $side:expr; $a:expr, $b:expr, $c:expr, $d:expr $(,)?) => {
+        Data {
+            mesh: MeshData {
+                verts: &[
+                    vert($facing, $a),
+                    vert($facing, $b),
+                    vert($facing, $c),
+                    vert($facing, $d),
+                ],
+                faces: &[[0, 1, 2], [0, 2, 3]],
+            },
+            side: $side,
+        }
+    };
+}
+
+/// Makes a triangle which looks to the `facing` side.
+macro_rules! tri {
+    ($facing:expr, $side:expr; $a:expr, $b:expr, $c:expr $(,)?) => {
+        Data {
+            mesh: MeshData {
+                verts: &[vert($facing, $a), vert($facing, $b), vert($facing, $c)],
+                faces: &[[0, 1, 2]],
+            },
+            side: $side,
+        }
+    };
+}
+
+/// Makes a box from `[x0, y0, z0]` to `[x1, y1, z1]`.
+///
+/// The faces lying on the block sides are tagged with them.
+macro_rules! cuboid {
+    ([$x0:expr, $y0:expr, $z0:expr], [$x1:expr, $y1:expr, $z1:expr]) => {
+        [
+            quad!(
+                Side::Left, side_of(Side::Left, $x1);
+                [$x1, $y0, $z1], [$x1, $y0, $z0], [$x1, $y1, $z0], [$x1, $y1, $z1],
+            ),
+            quad!(
+                Side::Right, side_of(Side::Right, $x0);
+                [$x0, $y0, $z0], [$x0, $y0, $z1], [$x0, $y1, $z1], [$x0, $y1, $z0],
+            ),
+            quad!(
+                Side::Up, side_of(Side::Up, $y1);
+                [$x0, $y1, $z1], [$x1, $y1, $z1], [$x1, $y1, $z0], [$x0, $y1, $z0],
+            ),
+            quad!(
+                Side::Down, side_of(Side::Down, $y0);
+                [$x0, $y0, $z0], [$x1, $y0, $z0], [$x1, $y0, $z1], [$x0, $y0, $z1],
+            ),
+            quad!(
+                Side::Forth, side_of(Side::Forth, $z1);
+                [$x0, $y0, $z1], [$x1, $y0, $z1], [$x1, $y1, $z1], [$x0, $y1, $z1],
+            ),
+            quad!(
+                Side::Back, side_of(Side::Back, $z0);
+                [$x1, $y0, $z0], [$x0, $y0, $z0], [$x0, $y1, $z0], [$x1, $y1, $z0],
+            ),
+        ]
+    };
+}
+
+/// Returns the `side` if a face at the `plane` coordinate lies on it.
+const fn side_of(side: Side, plane: f32) -> Option<Side> {
+    let on_side = match side {
+        Side::Left | Side::Up | Side::Forth => plane == 1.,
+        Side::Right | Side::Down | Side::Back => plane == 0.,
+    };
+
+    if on_side {
+        Some(side)
+    } else {
+        None
+    }
+}
+
+const S0: &[Data] = &[quad!(
+    Side::Up, None;
+    [0., 0.5, 1.], [1., 0.5, 1.], [1., 0.5, 0.], [0., 0.5, 0.],
+)];
+
+const S1: &[Data] = &cuboid!([0., 0., 0.], [1., 1., 1.]);
+
+const S2: &[Data] = &cuboid!([0., 0., 0.], [1., 0.5, 1.]);
+
+const S3: &[Data] = &cuboid!([0., 0.5, 0.], [1., 1., 1.]);
+
+const S4: &[Data] = &[
+    // The lower step
+    quad!(
+        Side::Down, Some(Side::Down);
+        [0., 0., 0.], [1., 0., 0.], [1., 0., 1.], [0., 0., 1.],
+    ),
+    quad!(
+        Side::Back, Some(Side::Back);
+        [1., 0., 0.], [0., 0., 0.], [0., 0.5, 0.], [1., 0.5, 0.],
+    ),
+    quad!(
+        Side::Up, None;
+        [0., 0.5, 0.5], [1., 0.5, 0.5], [1., 0.5, 0.], [0., 0.5, 0.],
+    ),
+    quad!(
+        Side::Left, Some(Side::Left);
+        [1., 0., 1.], [1., 0., 0.], [1., 0.5, 0.], [1., 0.5, 1.],
+    ),
+    quad!(
+        Side::Right, Some(Side::Right);
+        [0., 0., 0.], [0., 0., 1.], [0., 0.5, 1.], [0., 0.5, 0.],
+    ),
+    // The upper step
+    quad!(
+        Side::Forth, Some(Side::Forth);
+        [0., 0., 1.], [1., 0., 1.], [1., 1., 1.], [0., 1., 1.],
+    ),
+    quad!(
+        Side::Back, None;
+        [1., 0.5, 0.5], [0., 0.5, 0.5], [0., 1., 0.5], [1., 1., 0.5],
+    ),
+    quad!(
+        Side::Up, Some(Side::Up);
+        [0., 1., 1.], [1., 1., 1.], [1., 1., 0.5], [0., 1., 0.5],
+    ),
+    quad!(
+        Side::Left, Some(Side::Left);
+        [1., 0.5, 1.], [1., 0.5, 0.5], [1., 1., 0.5], [1., 1., 1.],
+    ),
+    quad!(
+        Side::Right, Some(Side::Right);
+        [0., 0.5, 0.5], [0., 0.5, 1.], [0., 1., 1.], [0., 1., 0.5],
+    ),
+];
+
+const S5: &[Data] = &[
+    quad!(
+        Side::Forth, None;
+        [0., 0., 0.], [1., 0., 1.], [1., 1., 1.], [0., 1., 0.],
+    ),
+    quad!(
+        Side::Back, None;
+        [1., 0., 1.], [0., 0., 0.], [0., 1., 0.], [1., 1., 1.],
+    ),
+    quad!(
+        Side::Back, None;
+        [1., 0., 0.], [0., 0., 1.], [0., 1., 1.], [1., 1., 0.],
+    ),
+    quad!(
+        Side::Forth, None;
+        [0., 0., 1.], [1., 0., 0.], [1., 1., 0.], [0., 1., 1.],
+    ),
+];
+
+const S6: &[Data] = &cuboid!([0.375, 0., 0.375], [0.625, 1., 0.625]);
+
+const S7: &[Data] = &[
+    quad!(
+        Side::Down, Some(Side::Down);
+        [0., 0., 0.], [1., 0., 0.], [1., 0., 1.], [0., 0., 1.],
+    ),
+    quad!(
+        Side::Forth, Some(Side::Forth);
+        [0., 0., 1.], [1., 0., 1.], [1., 1., 1.], [0., 1., 1.],
+    ),
+    quad!(
+        Side::Up, None;
+        [0., 0., 0.], [0., 1., 1.], [1., 1., 1.], [1., 0., 0.],
+    ),
+    tri!(
+        Side::Left, Some(Side::Left);
+        [1., 0., 1.], [1., 0., 0.], [1., 1., 1.],
+    ),
+    tri!(
+        Side::Right, Some(Side::Right);
+        [0., 0., 0.], [0., 0., 1.], [0., 1., 1.],
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shapes() -> impl Iterator<Item = Shape> {
+        (0..=u8::MAX).filter_map(|id| Shape::from_id(id).ok())
+    }
+
+    fn normal(side: Side) -> [f32; 3] {
+        match side {
+            Side::Left => [1., 0., 0.],
+            Side::Right => [-1., 0., 0.],
+            Side::Up => [0., 1., 0.],
+            Side::Down => [0., -1., 0.],
+            Side::Forth => [0., 0., 1.],
+            Side::Back => [0., 0., -1.],
+        }
+    }
+
+    fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+    }
+
+    fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+    }
+
+    fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+        a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+    }
+
+    #[test]
+    fn ids() {
+        for shape in shapes() {
+            assert!(matches!(Shape::from_id(shape.id()), Ok(s) if s == shape));
+        }
+
+        assert_eq!(shapes().count(), 8);
+    }
+
+    #[test]
+    fn faces() {
+        for shape in shapes() {
+            for data in shape.data() {
+                let verts = data.mesh.verts;
+                for vert in verts {
+                    assert!(vert.pos.iter().all(|&v| (0. ..=1.).contains(&v)));
+                    assert!(vert.tex.iter().all(|&v| (0. ..=1.).contains(&v)));
+                }
+
+                let side = match data.side {
+                    Some(side) => side,
+                    None => continue,
+                };
+
+                // A face on a side lies on its plane and looks outside
+                let axis = normal(side).iter().position(|&v| v != 0.).unwrap();
+                let plane = side_of(side, 1.).map_or(0., |_| 1.);
+                assert!(verts.iter().all(|vert| vert.pos[axis] == plane));
+
+                for face in data.mesh.faces {
+                    let [a, b, c] = face.map(|i| verts[i as usize].pos);
+                    let n = cross(sub(b, a), sub(c, a));
+                    assert!(dot(n, normal(side)) > 0., "{shape:?} {side:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn covers() {
+        // A covered side is filled by faces of that side completely
+        for shape in shapes() {
+            for side in Sides::all() {
+                let area: f32 = shape
+                    .data()
+                    .iter()
+                    .filter(|data| data.side == Some(side))
+                    .flat_map(|data| {
+                        data.mesh.faces.iter().map(|face| {
+                            let [a, b, c] = face.map(|i| data.mesh.verts[i as usize].pos);
+                            dot(cross(sub(b, a), sub(c, a)), normal(side)) / 2.
+                        })
+                    })
+                    .sum();
+
+                assert_eq!(
+                    shape.covers().contains(side),
+                    area == 1.,
+                    "{shape:?} {side:?}"
+                );
+            }
+        }
+    }
 }
@@ -1,6 +1,10 @@
-use std::{fmt, ops};
+use {
+    serde::Deserialize,
+    std::{fmt, ops},
+};
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum Side {
     Left = 0,
     Right = 1,
@@ -1,10 +1,107 @@
+pub mod mipmap;
 mod pack;
 mod spritemap;
 
-pub use self::spritemap::SpriteMap;
+pub use self::spritemap::{Error, SpriteMap};
 
-#[derive(Clone, Copy, Default)]
+/// An algorithm to pack sprites.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Packer {
+    /// Sprites are placed in rows sorted by height.
+    ///
+    /// It's fast, but wastes space if sprites have different sizes.
+    Shelf,
+    /// Sprites are placed in the best fitting free rectangles.
+    #[default]
+    MaxRects,
+}
+
+/// Options of a sprite atlas.
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub packer: Packer,
+    /// Empty pixels between sprites.
+    pub padding: u16,
+    /// Pixels around each sprite which repeat its border.
+    ///
+    /// They prevent bleeding of neighbour sprites when the atlas is filtered.
+    pub extrude: u16,
+    /// The maximum width and height of an atlas page.
+    ///
+    /// Sprites which don't fit in a page overflow into next ones.
+    /// It's rounded down to a power of two.
+    pub max_size: u16,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            packer: Packer::default(),
+            padding: 0,
+            extrude: 0,
+            // The default texture size limit of wgpu
+            max_size: 8192,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct Rect {
     pub pos: (u16, u16),
     pub size: (u16, u16),
+    /// The index of the atlas page.
+    pub page: u16,
+}
+
+impl Rect {
+    /// Returns normalized texture coordinates of the rect in its page of the `size`.
+    pub fn uv(self, (width, height): (u32, u32)) -> Uv {
+        let (width, height) = (width as f32, height as f32);
+        Uv {
+            page: self.page,
+            pos: [
+                f32::from(self.pos.0) / width,
+                f32::from(self.pos.1) / height,
+            ],
+            size: [
+                f32::from(self.size.0) / width,
+                f32::from(self.size.1) / height,
+            ],
+        }
+    }
+}
+
+/// Normalized texture coordinates of a sprite in an atlas page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uv {
+    pub page: u16,
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+}
+
+/// The whole first page.
+impl Default for Uv {
+    fn default() -> Self {
+        Self {
+            page: 0,
+            pos: [0., 0.],
+            size: [1., 1.],
+        }
+    }
+}
+
+impl Uv {
+    /// Returns the rect in the page as `[x, y, width, height]`.
+    pub fn rect(self) -> [f32; 4] {
+        let ([x, y], [width, height]) = (self.pos, self.size);
+        [x, y, width, height]
+    }
+
+    /// Maps texture coordinates of the sprite to the atlas ones.
+    pub fn map(self, [u, v]: [f32; 2]) -> [f32; 2] {
+        [
+            self.pos[0] + u * self.size[0],
+            self.pos[1] + v * self.size[1],
+        ]
+    }
 }
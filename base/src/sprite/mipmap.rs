@@ -0,0 +1,177 @@
+use {
+    crate::sprite::Rect,
+    image::{Rgba, RgbaImage as Image},
+};
+
+/// Returns the number of levels of a full mip chain for the `size`, including the base one.
+pub fn levels((width, height): (u32, u32)) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// Generates mip levels which follow the `image`, so the result has `levels - 1` images.
+///
+/// Every pixel of a level is the average of a 2x2 block of the previous one.
+/// Pixels of different `areas` are never averaged together, so sprites of an atlas
+/// don't bleed into each other. Pixels outside of all areas are averaged only
+/// with each other. The page of an area rect is ignored.
+///
+/// Colors are averaged in linear space and weighted by alpha,
+/// so transparent pixels don't darken edges of sprites.
+pub fn generate(image: &Image, areas: &[Rect], levels: u32) -> Vec<Image> {
+    const NONE: u32 = u32::MAX;
+
+    let (width, height) = image.dimensions();
+    let mut owners = vec![NONE; width as usize * height as usize];
+    for (n, area) in areas.iter().enumerate() {
+        let (x, y) = (u32::from(area.pos.0), u32::from(area.pos.1));
+        let (w, h) = (u32::from(area.size.0), u32::from(area.size.1));
+        for y in y..(y + h).min(height) {
+            for x in x..(x + w).min(width) {
+                owners[(y * width + x) as usize] = n as u32;
+            }
+        }
+    }
+
+    // The owner of a pixel is the owner of its top left base pixel,
+    // so one of the four source pixels always has the same owner
+    let owner = |level: u32, x: u32, y: u32| owners[((y << level) * width + (x << level)) as usize];
+
+    let linear: Vec<_> = (0..=u8::MAX).map(to_linear).collect();
+    let mut mips: Vec<Image> = Vec::with_capacity(levels.saturating_sub(1) as usize);
+    for level in 1..levels {
+        let prev = mips.last().unwrap_or(image);
+        let (prev_width, prev_height) = prev.dimensions();
+        let mip = Image::from_fn((prev_width / 2).max(1), (prev_height / 2).max(1), |x, y| {
+            let own = owner(level, x, y);
+            let mut color = [0.; 3];
+            let mut weight = 0.;
+            let mut alpha = 0.;
+            let mut n = 0;
+            for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let (sx, sy) = (x * 2 + sx, y * 2 + sy);
+                if sx >= prev_width || sy >= prev_height || owner(level - 1, sx, sy) != own {
+                    continue;
+                }
+
+                let Rgba([r, g, b, a]) = *prev.get_pixel(sx, sy);
+                let a = f32::from(a) / 255.;
+                for (c, v) in color.iter_mut().zip([r, g, b]) {
+                    // Fully transparent pixels still count if there are no others
+                    *c += linear[usize::from(v)] * a.max(f32::EPSILON);
+                }
+
+                weight += a.max(f32::EPSILON);
+                alpha += a;
+                n += 1;
+            }
+
+            let [r, g, b] = color.map(|c| to_srgb(c / weight));
+            let a = (alpha / n as f32 * 255.).round() as u8;
+            Rgba([r, g, b, a])
+        });
+
+        mips.push(mip);
+    }
+
+    mips
+}
+
+fn to_linear(v: u8) -> f32 {
+    let v = f32::from(v) / 255.;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn to_srgb(v: f32) -> u8 {
+    let v = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1. / 2.4) - 0.055
+    };
+
+    (v.clamp(0., 1.) * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_count() {
+        assert_eq!(levels((1, 1)), 1);
+        assert_eq!(levels((2, 1)), 2);
+        assert_eq!(levels((16, 8)), 5);
+        assert_eq!(levels((64, 256)), 9);
+    }
+
+    #[test]
+    fn sizes() {
+        let image = Image::new(16, 4);
+        let mips = generate(&image, &[], levels(image.dimensions()));
+        let sizes: Vec<_> = mips.iter().map(Image::dimensions).collect();
+        assert_eq!(sizes, [(8, 2), (4, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn round_trip() {
+        for v in 0..=u8::MAX {
+            assert_eq!(to_srgb(to_linear(v)), v);
+        }
+    }
+
+    #[test]
+    fn average() {
+        let black = Rgba([0, 0, 0, 255]);
+        let white = Rgba([255, 255, 255, 255]);
+        let image = Image::from_fn(2, 2, |x, _| if x == 0 { black } else { white });
+        let mips = generate(&image, &[], 2);
+
+        // The half of the linear intensity
+        assert_eq!(mips[0].get_pixel(0, 0), &Rgba([188, 188, 188, 255]));
+
+        // Transparent pixels don't affect the color
+        let red = Rgba([255, 0, 0, 255]);
+        let clear = Rgba([0, 0, 0, 0]);
+        let image = Image::from_fn(2, 2, |x, _| if x == 0 { red } else { clear });
+        let mips = generate(&image, &[], 2);
+        assert_eq!(mips[0].get_pixel(0, 0), &Rgba([255, 0, 0, 128]));
+
+        let image = Image::from_pixel(2, 2, clear);
+        let mips = generate(&image, &[], 2);
+        assert_eq!(mips[0].get_pixel(0, 0), &clear);
+    }
+
+    #[test]
+    fn no_bleeding() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+
+        // Two sprites which touch at an odd position
+        let image = Image::from_fn(16, 8, |x, _| if x < 5 { red } else { blue });
+        let areas = [
+            Rect {
+                pos: (0, 0),
+                size: (5, 8),
+                page: 0,
+            },
+            Rect {
+                pos: (5, 0),
+                size: (11, 8),
+                page: 0,
+            },
+        ];
+
+        for mip in generate(&image, &areas, levels(image.dimensions())) {
+            assert!(mip.pixels().all(|pixel| *pixel == red || *pixel == blue));
+        }
+
+        // Without areas the colors are mixed
+        let mips = generate(&image, &[], 2);
+        assert!(mips[0]
+            .pixels()
+            .any(|pixel| *pixel != red && *pixel != blue));
+    }
+}
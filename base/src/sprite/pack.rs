@@ -1,84 +1,189 @@
 use {
-    crate::sprite::Rect,
-    std::{cmp::Eq, collections::HashMap, hash::Hash},
+    crate::sprite::{Options, Packer, Rect},
+    std::{
+        cmp::{Eq, Reverse},
+        collections::HashMap,
+        hash::Hash,
+    },
 };
 
 pub(super) struct Packed<K> {
     pub frames: HashMap<K, Rect>,
-    pub side: u16,
+    /// Sizes of atlas pages.
+    pub pages: Vec<(u16, u16)>,
 }
 
 impl<K> Packed<K> {
-    pub fn pack<F>(frames: F) -> Self
+    /// Packs frames into power-of-two sized pages.
+    ///
+    /// Frames are packed in a single page while it fits in the maximum size,
+    /// the rest of them overflows into next pages. Returned rects don't include
+    /// the padding and the extrusion, so they're rects of the frames themselves.
+    ///
+    /// Returns the key of a frame if it's larger than a page.
+    pub fn pack<F>(frames: F, options: Options) -> Result<Self, K>
     where
         F: IntoIterator<Item = (K, (u16, u16))>,
         K: Eq + Hash + Clone,
     {
+        let Options {
+            packer,
+            padding,
+            extrude,
+            max_size,
+        } = options;
+
+        let margin = u32::from(extrude) * 2 + u32::from(padding);
         let mut frames: Vec<_> = frames
             .into_iter()
-            .map(|(key, size)| Frame { size, key })
+            .map(|(key, (width, height))| Frame {
+                size: (u32::from(width) + margin, u32::from(height) + margin),
+                key,
+            })
             .collect();
 
-        let (frames, side) = pack_frames(&mut frames);
-        Self { frames, side }
-    }
-}
+        let try_pack = match packer {
+            Packer::Shelf => {
+                frames.sort_by_key(|frame| Reverse((frame.height(), frame.width())));
+                shelf
+            }
+            Packer::MaxRects => {
+                frames.sort_by_key(|frame| {
+                    let (width, height) = frame.size;
+                    Reverse((width.max(height), width.min(height)))
+                });
+
+                max_rects
+            }
+        };
+
+        // The padding after the last frame in a row or a column may lie outside
+        let padding = u32::from(padding);
+        let max_size = max_side(max_size);
+        if let Some(frame) = frames
+            .iter()
+            .find(|frame| frame.width().max(frame.height()) > max_size + padding)
+        {
+            return Err(frame.key.clone());
+        }
+
+        let extrude = u32::from(extrude);
+        let mut packed = Self {
+            frames: HashMap::with_capacity(frames.len()),
+            pages: vec![],
+        };
+
+        while !frames.is_empty() {
+            let (mut width, mut height) = calc_size(&frames, padding, max_size);
+            let places = loop {
+                let places = try_pack(&frames, (width + padding, height + padding));
+                if places.iter().all(Option::is_some) {
+                    break places;
+                }
+
+                if width <= height && width < max_size {
+                    width <<= 1;
+                } else if height < max_size {
+                    height <<= 1;
+                } else {
+                    // The page is full, so the rest of frames overflows into the next one
+                    break places;
+                }
+            };
 
-fn pack_frames<K>(frames: &mut [Frame<K>]) -> (HashMap<K, Rect>, u16)
-where
-    K: Eq + Hash + Clone,
-{
-    use std::cmp::Reverse;
+            let page = packed.pages.len() as u16;
+            packed.pages.push((width as u16, height as u16));
 
-    frames.sort_by_key(|frame| Reverse((frame.size.1, frame.size.0)));
+            let mut rest = vec![];
+            for (frame, place) in frames.into_iter().zip(places) {
+                let (x, y) = match place {
+                    Some(place) => place,
+                    None => {
+                        rest.push(frame);
+                        continue;
+                    }
+                };
+
+                let rect = Rect {
+                    pos: ((x + extrude) as u16, (y + extrude) as u16),
+                    size: (
+                        (frame.width() - margin) as u16,
+                        (frame.height() - margin) as u16,
+                    ),
+                    page,
+                };
+
+                packed.frames.insert(frame.key, rect);
+            }
 
-    let mut side = calc_side(frames);
-    loop {
-        match try_pack(frames, side) {
-            Some(rects) => return (rects, side),
-            None => side <<= 1,
+            frames = rest;
         }
+
+        Ok(packed)
     }
 }
 
+/// The largest side of a page.
+pub(super) const MAX_SIDE: u32 = 1 << 15;
+
+/// Returns the largest power-of-two side which isn't larger than the `max_size`.
+fn max_side(max_size: u16) -> u32 {
+    let max_size = u32::from(max_size).clamp(1, MAX_SIDE);
+    1 << max_size.ilog2()
+}
+
 struct Frame<K> {
-    size: (u16, u16),
+    size: (u32, u32),
     key: K,
 }
 
 impl<K> Frame<K> {
-    fn width(&self) -> u16 {
+    fn width(&self) -> u32 {
         self.size.0
     }
 
-    fn height(&self) -> u16 {
+    fn height(&self) -> u32 {
         self.size.1
     }
 }
 
-fn calc_side<K>(frames: &[Frame<K>]) -> u16 {
-    use std::cmp::max;
+/// Returns the smallest power-of-two size which may contain all frames.
+///
+/// The size is never larger than the `max_size`.
+fn calc_size<K>(frames: &[Frame<K>], padding: u32, max_size: u32) -> (u32, u32) {
+    let side = |len: u32| {
+        len.saturating_sub(padding)
+            .max(1)
+            .next_power_of_two()
+            .min(max_size)
+    };
 
-    let max_size = frames
-        .iter()
-        .map(|frame| max(frame.width(), frame.height()))
-        .max()
-        .unwrap_or_default();
+    let max_width = frames.iter().map(Frame::width).max().unwrap_or_default();
+    let max_height = frames.iter().map(Frame::height).max().unwrap_or_default();
+    let (mut width, mut height) = (side(max_width), side(max_height));
 
-    let area: u32 = frames
+    let area: u64 = frames
         .iter()
-        .map(|frame| u32::from(frame.width()) * u32::from(frame.height()))
+        .map(|frame| u64::from(frame.width()) * u64::from(frame.height()))
         .sum();
 
-    let area_sqrt = (area as f32).sqrt().ceil() as u16;
-    let side = max(max_size, area_sqrt);
-    side.next_power_of_two()
+    while u64::from(width + padding) * u64::from(height + padding) < area {
+        if width <= height && width < max_size {
+            width <<= 1;
+        } else if height < max_size {
+            height <<= 1;
+        } else {
+            break;
+        }
+    }
+
+    (width, height)
 }
 
-fn try_pack<K>(frames: &[Frame<K>], side: u16) -> Option<HashMap<K, Rect>>
-where
-    K: Eq + Hash + Clone,
-{
+/// Places frames in rows.
+///
+/// Returns positions of frames in the same order or `None` for frames which don't fit.
+fn shelf<K>(frames: &[Frame<K>], (width, height): (u32, u32)) -> Vec<Option<(u32, u32)>> {
     let mut x = 0;
     let mut y = 0;
     let mut max_height = 0;
@@ -86,29 +191,346 @@ where
     frames
         .iter()
         .map(|frame| {
-            let width = frame.width();
-            let height = frame.height();
-            max_height = max_height.max(height);
-
-            if x + width > side {
+            if x + frame.width() > width {
                 x = 0;
                 y += max_height;
                 max_height = 0;
             }
 
-            if y + height > side {
+            if x + frame.width() > width || y + frame.height() > height {
                 return None;
             }
 
-            let res = (
-                frame.key.clone(),
-                Rect {
-                    pos: (x, y),
-                    size: (width, height),
-                },
-            );
-            x += width;
-            Some(res)
+            max_height = max_height.max(frame.height());
+            let pos = (x, y);
+            x += frame.width();
+            Some(pos)
         })
         .collect()
 }
+
+/// Places frames with the MaxRects algorithm.
+///
+/// It keeps a list of maximal free rectangles and puts every frame in the one
+/// which leaves the shortest side after the placement.
+/// Returns positions of frames in the same order or `None` for frames which don't fit.
+fn max_rects<K>(frames: &[Frame<K>], (width, height): (u32, u32)) -> Vec<Option<(u32, u32)>> {
+    #[derive(Clone, Copy, PartialEq)]
+    struct Free {
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    }
+
+    impl Free {
+        fn contains(self, other: Self) -> bool {
+            self.x <= other.x
+                && self.y <= other.y
+                && other.x + other.w <= self.x + self.w
+                && other.y + other.h <= self.y + self.h
+        }
+    }
+
+    let mut free = vec![Free {
+        x: 0,
+        y: 0,
+        w: width,
+        h: height,
+    }];
+
+    let mut places = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let (fw, fh) = frame.size;
+        let best = free
+            .iter()
+            .filter(|rect| fw <= rect.w && fh <= rect.h)
+            .min_by_key(|rect| {
+                let (dw, dh) = (rect.w - fw, rect.h - fh);
+                (dw.min(dh), dw.max(dh))
+            });
+
+        let best = match best {
+            Some(best) => best,
+            None => {
+                places.push(None);
+                continue;
+            }
+        };
+
+        let placed = Free {
+            x: best.x,
+            y: best.y,
+            w: fw,
+            h: fh,
+        };
+
+        places.push(Some((placed.x, placed.y)));
+
+        // Split free rects which intersect the placed one
+        let mut split = Vec::with_capacity(free.len() + 4);
+        for rect in free {
+            let intersects = placed.x < rect.x + rect.w
+                && rect.x < placed.x + placed.w
+                && placed.y < rect.y + rect.h
+                && rect.y < placed.y + placed.h;
+
+            if !intersects {
+                split.push(rect);
+                continue;
+            }
+
+            if placed.x > rect.x {
+                split.push(Free {
+                    w: placed.x - rect.x,
+                    ..rect
+                });
+            }
+
+            if placed.x + placed.w < rect.x + rect.w {
+                split.push(Free {
+                    x: placed.x + placed.w,
+                    w: rect.x + rect.w - (placed.x + placed.w),
+                    ..rect
+                });
+            }
+
+            if placed.y > rect.y {
+                split.push(Free {
+                    h: placed.y - rect.y,
+                    ..rect
+                });
+            }
+
+            if placed.y + placed.h < rect.y + rect.h {
+                split.push(Free {
+                    y: placed.y + placed.h,
+                    h: rect.y + rect.h - (placed.y + placed.h),
+                    ..rect
+                });
+            }
+        }
+
+        // Remove rects contained in others
+        free = Vec::with_capacity(split.len());
+        for (n, &rect) in split.iter().enumerate() {
+            let contained = split
+                .iter()
+                .enumerate()
+                .any(|(m, &other)| m != n && other.contains(rect) && (other != rect || m < n));
+
+            if !contained {
+                free.push(rect);
+            }
+        }
+    }
+
+    places
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns pseudo-random frame sizes.
+    fn sizes(n: usize, max: u16) -> Vec<(u16, u16)> {
+        let mut state: u32 = 0x2545_f491;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % u32::from(max)) as u16 + 1
+        };
+
+        (0..n).map(|_| (next(), next())).collect()
+    }
+
+    fn check_pages(sizes: &[(u16, u16)], options: Options) -> Vec<(u16, u16)> {
+        let Packed { frames, pages } =
+            Packed::pack(sizes.iter().copied().enumerate(), options).expect("packed");
+
+        let max_size = max_side(options.max_size);
+        for &(width, height) in &pages {
+            assert!(width.is_power_of_two() && u32::from(width) <= max_size);
+            assert!(height.is_power_of_two() && u32::from(height) <= max_size);
+        }
+
+        assert_eq!(frames.len(), sizes.len());
+
+        let extrude = u32::from(options.extrude);
+        let padding = u32::from(options.padding);
+        let bounds: Vec<_> = (0..sizes.len())
+            .map(|n| {
+                let rect = frames[&n];
+                assert_eq!(rect.size, sizes[n]);
+
+                // The rect with extruded borders
+                let x = u32::from(rect.pos.0);
+                let y = u32::from(rect.pos.1);
+                let bound = (
+                    x.checked_sub(extrude).expect("extruded x"),
+                    y.checked_sub(extrude).expect("extruded y"),
+                    x + u32::from(rect.size.0) + extrude,
+                    y + u32::from(rect.size.1) + extrude,
+                );
+
+                let (width, height) = pages[usize::from(rect.page)];
+                assert!(bound.2 <= u32::from(width));
+                assert!(bound.3 <= u32::from(height));
+                (rect.page, bound)
+            })
+            .collect();
+
+        for (n, (page, a)) in bounds.iter().enumerate() {
+            for (_, b) in bounds[n + 1..].iter().filter(|(p, _)| p == page) {
+                let apart = a.2 + padding <= b.0
+                    || b.2 + padding <= a.0
+                    || a.3 + padding <= b.1
+                    || b.3 + padding <= a.1;
+
+                assert!(apart, "{a:?} and {b:?} overlap");
+            }
+        }
+
+        pages
+    }
+
+    /// Checks the frames are packed in a single page and returns its size.
+    fn check(sizes: &[(u16, u16)], options: Options) -> (u16, u16) {
+        match check_pages(sizes, options)[..] {
+            [] => (0, 0),
+            [size] => size,
+            _ => panic!("a single page expected"),
+        }
+    }
+
+    #[test]
+    fn no_overlaps() {
+        for packer in [Packer::Shelf, Packer::MaxRects] {
+            for (padding, extrude) in [(0, 0), (1, 0), (0, 1), (2, 3)] {
+                let options = Options {
+                    packer,
+                    padding,
+                    extrude,
+                    ..Options::default()
+                };
+
+                check(&sizes(200, 40), options);
+                check(&[(16, 16); 16], options);
+                check(&[(1, 1)], options);
+                check(&[], options);
+            }
+        }
+    }
+
+    #[test]
+    fn non_square() {
+        for packer in [Packer::Shelf, Packer::MaxRects] {
+            let options = Options {
+                packer,
+                ..Options::default()
+            };
+
+            assert_eq!(check(&[(16, 16), (16, 16)], options), (32, 16));
+            assert_eq!(check(&[(4, 64)], options), (4, 64));
+        }
+    }
+
+    #[test]
+    fn exact_fit() {
+        // The padding after the last frame may lie outside
+        let options = Options {
+            padding: 2,
+            ..Options::default()
+        };
+
+        assert_eq!(check(&[(16, 16)], options), (16, 16));
+        assert_eq!(check(&[(14, 14); 4], options), (32, 32));
+    }
+
+    #[test]
+    fn max_rects_is_denser() {
+        let area = |(width, height): (u16, u16)| u32::from(width) * u32::from(height);
+        let sizes = [(64, 8), (8, 64), (32, 32), (16, 8), (8, 16), (4, 4), (4, 4)];
+        let shelf = check(
+            &sizes,
+            Options {
+                packer: Packer::Shelf,
+                ..Options::default()
+            },
+        );
+
+        let max_rects = check(
+            &sizes,
+            Options {
+                packer: Packer::MaxRects,
+                ..Options::default()
+            },
+        );
+
+        assert!(area(max_rects) < area(shelf));
+    }
+
+    #[test]
+    fn pages() {
+        for packer in [Packer::Shelf, Packer::MaxRects] {
+            for (padding, extrude) in [(0, 0), (2, 1)] {
+                let options = Options {
+                    packer,
+                    padding,
+                    extrude,
+                    max_size: 64,
+                };
+
+                let pages = check_pages(&sizes(100, 30), options);
+                assert!(pages.len() > 1);
+
+                // Frames overflow only from full pages
+                let full = &pages[..pages.len() - 1];
+                assert!(full.iter().all(|&size| size == (64, 64)));
+            }
+        }
+    }
+
+    #[test]
+    fn max_size() {
+        assert_eq!(max_side(0), 1);
+        assert_eq!(max_side(100), 64);
+        assert_eq!(max_side(u16::MAX), MAX_SIDE);
+
+        let options = Options {
+            max_size: 100,
+            ..Options::default()
+        };
+
+        assert_eq!(check_pages(&[(64, 64); 3], options), [(64, 64); 3]);
+    }
+
+    #[test]
+    fn too_large() {
+        let options = Options {
+            max_size: 64,
+            ..Options::default()
+        };
+
+        assert!(matches!(
+            Packed::pack([(0, (64, 64)), (1, (65, 1))], options),
+            Err(1),
+        ));
+
+        // The padding after a frame may lie outside
+        let options = Options {
+            padding: 2,
+            ..options
+        };
+
+        assert!(Packed::pack([(0, (64, 64))], options).is_ok());
+
+        let options = Options {
+            extrude: 1,
+            ..options
+        };
+
+        assert!(matches!(Packed::pack([(0, (64, 64))], options), Err(0)));
+    }
+}
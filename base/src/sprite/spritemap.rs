@@ -1,42 +1,319 @@
 use {
-    crate::sprite::{pack::Packed, Rect},
+    crate::sprite::{mipmap, pack::Packed, Options, Rect, Uv},
     image::{GenericImage, GenericImageView, RgbaImage as Image, SubImage},
+    std::fmt,
 };
 
 pub struct SpriteMap {
     pub rects: Vec<Rect>,
-    pub image: Image,
+    pub pages: Vec<Image>,
+    extrude: u16,
 }
 
 impl SpriteMap {
-    pub fn new(images: &[SubImage<&Image>]) -> Self {
-        use std::iter::zip;
-
+    /// Packs `images` into atlas pages.
+    ///
+    /// The rect of every image has the same index as the image.
+    pub fn new(images: &[SubImage<&Image>], options: Options) -> Result<Self, Error> {
         let mut rects = vec![Rect::default(); images.len()];
 
-        let sizes = images.iter().map(|image| {
-            (
-                u16::try_from(image.width()).expect("too large image"),
-                u16::try_from(image.height()).expect("too large image"),
-            )
-        });
-
-        let Packed { frames, side } = Packed::pack(zip(0.., sizes));
-        let mut image = {
-            let side = u32::from(side);
-            Image::new(side, side)
+        let too_large = |index: usize| {
+            let image = &images[index];
+            Error::TooLarge {
+                index,
+                size: image.dimensions(),
+                max_size: options.max_size,
+            }
         };
 
+        let sizes = images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                let (width, height) = image.dimensions();
+                match (u16::try_from(width), u16::try_from(height)) {
+                    (Ok(width), Ok(height)) => Ok((index, (width, height))),
+                    _ => Err(too_large(index)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let Packed { frames, pages } = Packed::pack(sizes, options).map_err(too_large)?;
+        let mut pages: Vec<_> = pages
+            .into_iter()
+            .map(|(width, height)| Image::new(u32::from(width), u32::from(height)))
+            .collect();
+
         for (index, rect) in frames {
             rects[index] = rect;
 
             let view = *images[index];
+            let page = &mut pages[usize::from(rect.page)];
             let Rect { pos: (x, y), .. } = rect;
-            image
-                .copy_from(&view, u32::from(x), u32::from(y))
+            page.copy_from(&view, u32::from(x), u32::from(y))
                 .expect("copy");
+
+            extrude(page, rect, options.extrude);
+        }
+
+        Ok(Self {
+            rects,
+            pages,
+            extrude: options.extrude,
+        })
+    }
+
+    /// Returns normalized texture coordinates of the sprite with the `index`.
+    pub fn uv(&self, index: usize) -> Option<Uv> {
+        let rect = self.rects.get(index)?;
+        let page = &self.pages[usize::from(rect.page)];
+        Some(rect.uv(page.dimensions()))
+    }
+
+    /// Returns the number of pixels which repeat the border around every sprite.
+    pub fn extrude(&self) -> u16 {
+        self.extrude
+    }
+
+    /// Generates `levels - 1` mip levels of the page.
+    ///
+    /// Every sprite is downsampled together with its extrusion,
+    /// but separately from other sprites.
+    pub fn mipmaps(&self, page: usize, levels: u32) -> Vec<Image> {
+        let extrude = self.extrude;
+        let areas: Vec<_> = self
+            .rects
+            .iter()
+            .filter(|rect| usize::from(rect.page) == page)
+            .map(|rect| Rect {
+                pos: (rect.pos.0 - extrude, rect.pos.1 - extrude),
+                size: (rect.size.0 + extrude * 2, rect.size.1 + extrude * 2),
+                page: rect.page,
+            })
+            .collect();
+
+        mipmap::generate(&self.pages[page], &areas, levels)
+    }
+}
+
+/// Repeats border pixels of the `rect` in the `image` around it.
+fn extrude(image: &mut Image, rect: Rect, extrude: u16) {
+    if extrude == 0 || rect.size.0 == 0 || rect.size.1 == 0 {
+        return;
+    }
+
+    let (x, y) = (u32::from(rect.pos.0), u32::from(rect.pos.1));
+    let (width, height) = (u32::from(rect.size.0), u32::from(rect.size.1));
+    let extrude = u32::from(extrude);
+
+    for dy in 0..height + extrude * 2 {
+        for dx in 0..width + extrude * 2 {
+            let inside = (extrude..extrude + width).contains(&dx)
+                && (extrude..extrude + height).contains(&dy);
+
+            if inside {
+                continue;
+            }
+
+            let sx = dx.saturating_sub(extrude).min(width - 1);
+            let sy = dy.saturating_sub(extrude).min(height - 1);
+            let pixel = *image.get_pixel(x + sx, y + sy);
+            image.put_pixel(x + dx - extrude, y + dy - extrude, pixel);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The image with the `index` doesn't fit in an atlas page.
+    TooLarge {
+        index: usize,
+        size: (u32, u32),
+        max_size: u16,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLarge {
+                size: (width, height),
+                max_size,
+                ..
+            } => write!(
+                f,
+                "the image {width}x{height} doesn't fit in an atlas page {max_size}x{max_size}",
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, image::Rgba};
+
+    fn overlaps(a: Rect, b: Rect) -> bool {
+        let range = |pos: u16, len: u16| u32::from(pos)..u32::from(pos) + u32::from(len);
+        let (ax, ay) = (range(a.pos.0, a.size.0), range(a.pos.1, a.size.1));
+        let (bx, by) = (range(b.pos.0, b.size.0), range(b.pos.1, b.size.1));
+        ax.start < bx.end && bx.start < ax.end && ay.start < by.end && by.start < ay.end
+    }
+
+    #[test]
+    fn pack() {
+        let sizes = [(16, 16), (8, 8), (4, 12), (32, 8), (8, 8), (16, 4), (1, 1)];
+        let images: Vec<_> = sizes
+            .iter()
+            .enumerate()
+            .map(|(n, &(width, height))| Image::from_pixel(width, height, Rgba([n as u8; 4])))
+            .collect();
+
+        let views: Vec<_> = images
+            .iter()
+            .map(|image| image.view(0, 0, image.width(), image.height()))
+            .collect();
+
+        let map = SpriteMap::new(&views, Options::default()).unwrap();
+        let (width, height) = map.pages[0].dimensions();
+        for (n, &rect) in map.rects.iter().enumerate() {
+            assert_eq!((u32::from(rect.size.0), u32::from(rect.size.1)), sizes[n],);
+
+            assert!(u32::from(rect.pos.0) + u32::from(rect.size.0) <= width);
+            assert!(u32::from(rect.pos.1) + u32::from(rect.size.1) <= height);
+            for &other in &map.rects[n + 1..] {
+                assert!(!overlaps(rect, other));
+            }
+
+            // The sprite is copied to its rect
+            let (x, y) = (u32::from(rect.pos.0), u32::from(rect.pos.1));
+            assert_eq!(map.pages[0].get_pixel(x, y), &Rgba([n as u8; 4]));
+
+            let uv = map.uv(n).unwrap();
+            assert_eq!(
+                uv.map([0., 0.]),
+                [x as f32 / width as f32, y as f32 / height as f32]
+            );
+        }
+
+        assert!(map.uv(sizes.len()).is_none());
+    }
+
+    #[test]
+    fn extruded() {
+        let mut sprite = Image::new(2, 2);
+        for (x, y, pixel) in sprite.enumerate_pixels_mut() {
+            *pixel = Rgba([x as u8, y as u8, 0, 255]);
+        }
+
+        let options = Options {
+            extrude: 2,
+            ..Options::default()
+        };
+
+        let views = [sprite.view(0, 0, 2, 2)];
+        let map = SpriteMap::new(&views, options).unwrap();
+        let Rect { pos: (x, y), .. } = map.rects[0];
+        let (x, y) = (u32::from(x), u32::from(y));
+        assert!(x >= 2 && y >= 2);
+
+        for dy in 0..6 {
+            for dx in 0..6 {
+                let expected = Rgba([dx.clamp(2, 3) as u8 - 2, dy.clamp(2, 3) as u8 - 2, 0, 255]);
+                assert_eq!(map.pages[0].get_pixel(x + dx - 2, y + dy - 2), &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn pages() {
+        let images: Vec<_> = (0..5)
+            .map(|n| Image::from_pixel(16, 12 + n, Rgba([n as u8; 4])))
+            .collect();
+
+        let views: Vec<_> = images
+            .iter()
+            .map(|image| image.view(0, 0, image.width(), image.height()))
+            .collect();
+
+        let options = Options {
+            max_size: 32,
+            ..Options::default()
+        };
+
+        let map = SpriteMap::new(&views, options).unwrap();
+        assert!(map.pages.len() > 1);
+        for page in &map.pages {
+            assert!(page.width() <= 32 && page.height() <= 32);
         }
 
-        Self { rects, image }
+        for (n, rect) in map.rects.iter().enumerate() {
+            let page = &map.pages[usize::from(rect.page)];
+            let (x, y) = (u32::from(rect.pos.0), u32::from(rect.pos.1));
+            assert_eq!(page.get_pixel(x, y), &Rgba([n as u8; 4]));
+            assert_eq!(map.uv(n).unwrap().page, rect.page);
+        }
+
+        let large = Image::new(64, 1);
+        let views = [large.view(0, 0, 64, 1)];
+        assert!(matches!(
+            SpriteMap::new(&views, options),
+            Err(Error::TooLarge {
+                index: 0,
+                size: (64, 1),
+                max_size: 32,
+            }),
+        ));
+    }
+
+    #[test]
+    fn too_large() {
+        let small = Image::new(1, 1);
+        let large = Image::new(u32::from(u16::MAX) + 1, 1);
+        let views = [small.view(0, 0, 1, 1), large.view(0, 0, large.width(), 1)];
+        assert!(matches!(
+            SpriteMap::new(&views, Options::default()),
+            Err(Error::TooLarge {
+                index: 1,
+                size: (65536, 1),
+                ..
+            }),
+        ));
+    }
+
+    #[test]
+    fn mipmaps() {
+        use crate::sprite::mipmap;
+
+        let colors: Vec<_> = (0..6)
+            .map(|n| Rgba([n * 40, 255 - n * 40, n, 255]))
+            .collect();
+        let images: Vec<_> = colors
+            .iter()
+            .enumerate()
+            .map(|(n, &color)| Image::from_pixel(3 + n as u32, 5, color))
+            .collect();
+
+        let views: Vec<_> = images
+            .iter()
+            .map(|image| image.view(0, 0, image.width(), image.height()))
+            .collect();
+
+        let options = Options {
+            extrude: 1,
+            ..Options::default()
+        };
+
+        let map = SpriteMap::new(&views, options).unwrap();
+        let levels = mipmap::levels(map.pages[0].dimensions());
+        let mips = map.mipmaps(0, levels);
+        assert_eq!(mips.len() as u32, levels - 1);
+
+        // Every pixel is a color of some sprite or empty
+        let clear = Rgba([0; 4]);
+        for mip in &mips {
+            assert!(mip
+                .pixels()
+                .all(|pixel| *pixel == clear || colors.contains(pixel)));
+        }
     }
 }
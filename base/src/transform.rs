@@ -0,0 +1,279 @@
+use {
+    crate::side::{Side, Sides},
+    serde::Deserialize,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+/// An orientation of a block.
+///
+/// It's a combination of quarter turns and mirroring, so it maps the unit
+/// block onto itself. A quarter turn around an axis maps the next axis to
+/// the one after it: a turn around X maps Y to Z, a turn around Y maps Z to X
+/// and a turn around Z maps X to Y.
+///
+/// In a tile it's written as numbers of quarter turns around each axis and
+/// an optional mirrored axis, like `{ y: 1, mirror: 'x' }`. The mirroring is
+/// applied first, then the turns around X, Y and Z in this order.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(from = "Source")]
+pub struct Transform {
+    /// Source axes of each result axis and whether they are negated.
+    axes: [(Axis, bool); 3],
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        axes: [(Axis::X, false), (Axis::Y, false), (Axis::Z, false)],
+    };
+
+    /// Creates a transform of quarter `turns` around the `axis`.
+    pub fn rotation(axis: Axis, turns: u8) -> Self {
+        let turn = match axis {
+            Axis::X => Self {
+                axes: [(Axis::X, false), (Axis::Z, true), (Axis::Y, false)],
+            },
+            Axis::Y => Self {
+                axes: [(Axis::Z, false), (Axis::Y, false), (Axis::X, true)],
+            },
+            Axis::Z => Self {
+                axes: [(Axis::Y, true), (Axis::X, false), (Axis::Z, false)],
+            },
+        };
+
+        (0..turns % 4).fold(Self::IDENTITY, |tr, _| tr.then(turn))
+    }
+
+    /// Creates a transform which mirrors the `axis`.
+    pub fn mirror(axis: Axis) -> Self {
+        let mut tr = Self::IDENTITY;
+        tr.axes[axis as usize].1 = true;
+        tr
+    }
+
+    /// Returns a transform which applies `self` and then the `next` one.
+    pub fn then(self, next: Self) -> Self {
+        Self {
+            axes: next.axes.map(|(axis, neg)| {
+                let (src, src_neg) = self.axes[axis as usize];
+                (src, neg != src_neg)
+            }),
+        }
+    }
+
+    pub fn is_identity(self) -> bool {
+        self == Self::IDENTITY
+    }
+
+    /// Checks if the transform changes handedness.
+    ///
+    /// Faces of a mirrored shape must have the reversed winding
+    /// to keep facing outside.
+    pub fn is_mirrored(self) -> bool {
+        let negs = self.axes.iter().filter(|(_, neg)| *neg).count();
+        let [a, b, c] = self.axes.map(|(axis, _)| axis as u8);
+        let odd = !matches!((a, b, c), (0, 1, 2) | (1, 2, 0) | (2, 0, 1));
+        (negs % 2 == 1) != odd
+    }
+
+    /// Transforms a position in the block around its center.
+    pub fn pos(self, pos: [f32; 3]) -> [f32; 3] {
+        self.axes.map(|(axis, neg)| {
+            let v = pos[axis as usize];
+            if neg {
+                1. - v
+            } else {
+                v
+            }
+        })
+    }
+
+    pub fn side(self, side: Side) -> Side {
+        let (axis, positive) = match side {
+            Side::Left => (Axis::X, true),
+            Side::Right => (Axis::X, false),
+            Side::Up => (Axis::Y, true),
+            Side::Down => (Axis::Y, false),
+            Side::Forth => (Axis::Z, true),
+            Side::Back => (Axis::Z, false),
+        };
+
+        let (n, &(_, neg)) = self
+            .axes
+            .iter()
+            .enumerate()
+            .find(|(_, (src, _))| *src == axis)
+            .expect("the transform is a permutation of axes");
+
+        match (n, positive != neg) {
+            (0, true) => Side::Left,
+            (0, false) => Side::Right,
+            (1, true) => Side::Up,
+            (1, false) => Side::Down,
+            (2, true) => Side::Forth,
+            _ => Side::Back,
+        }
+    }
+
+    pub fn sides(self, sides: Sides) -> Sides {
+        sides.into_iter().map(|side| self.side(side)).collect()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Source {
+    mirror: Option<Axis>,
+    x: u8,
+    y: u8,
+    z: u8,
+}
+
+impl From<Source> for Transform {
+    fn from(Source { mirror, x, y, z }: Source) -> Self {
+        mirror
+            .map_or(Self::IDENTITY, Self::mirror)
+            .then(Self::rotation(Axis::X, x))
+            .then(Self::rotation(Axis::Y, y))
+            .then(Self::rotation(Axis::Z, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AXES: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    /// Returns all 48 orientations of a block.
+    fn all() -> Vec<Transform> {
+        let mut all = vec![];
+        for mirror in [None, Some(Axis::X)] {
+            for x in 0..4 {
+                for y in 0..4 {
+                    for z in 0..4 {
+                        let tr = Transform::from(Source { mirror, x, y, z });
+                        if !all.contains(&tr) {
+                            all.push(tr);
+                        }
+                    }
+                }
+            }
+        }
+
+        all
+    }
+
+    #[test]
+    fn orientations() {
+        let all = all();
+        assert_eq!(all.len(), 48);
+        assert_eq!(all.iter().filter(|tr| tr.is_mirrored()).count(), 24);
+    }
+
+    #[test]
+    fn four_turns() {
+        for axis in AXES {
+            let turn = Transform::rotation(axis, 1);
+            assert!(!turn.is_identity());
+            assert!(!turn.is_mirrored());
+            assert_eq!(Transform::rotation(axis, 4), Transform::IDENTITY);
+
+            for tr in all() {
+                let turned = (0..4).fold(tr, |tr, _| tr.then(turn));
+                assert_eq!(turned, tr);
+
+                for side in Sides::all() {
+                    let turned = (0..4).fold(tr.side(side), |side, _| turn.side(side));
+                    assert_eq!(turned, tr.side(side));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn turns() {
+        let turn = Transform::rotation(Axis::Y, 1);
+        assert_eq!(turn.side(Side::Forth), Side::Left);
+        assert_eq!(turn.side(Side::Left), Side::Back);
+        assert_eq!(turn.side(Side::Up), Side::Up);
+        assert_eq!(turn.pos([0., 0., 1.]), [1., 0., 1.]);
+        assert_eq!(turn.pos([1., 0.5, 0.25]), [0.25, 0.5, 0.]);
+
+        let mirror = Transform::mirror(Axis::X);
+        assert_eq!(mirror.side(Side::Left), Side::Right);
+        assert_eq!(mirror.side(Side::Up), Side::Up);
+        assert_eq!(mirror.pos([0.25, 0., 1.]), [0.75, 0., 1.]);
+        assert_eq!(mirror.then(mirror), Transform::IDENTITY);
+    }
+
+    #[test]
+    fn opposite() {
+        for tr in all() {
+            for side in Sides::all() {
+                assert_eq!(tr.side(side.opposite()), tr.side(side).opposite());
+            }
+
+            // The side mapping is a bijection
+            let sides: Sides = Sides::all().into_iter().map(|side| tr.side(side)).collect();
+            assert_eq!(sides, Sides::all());
+        }
+    }
+
+    #[test]
+    fn sides() {
+        for tr in all() {
+            for side in Sides::all() {
+                let sides = side | side.opposite();
+                assert_eq!(tr.sides(sides), tr.side(side) | tr.side(side).opposite());
+            }
+
+            assert_eq!(tr.sides(Sides::empty()), Sides::empty());
+            assert_eq!(tr.sides(Sides::all()), Sides::all());
+        }
+    }
+
+    #[test]
+    fn sides_match_positions() {
+        // A point in the middle of a side moves to the middle of the transformed side
+        let center = |side| match side {
+            Side::Left => [1., 0.5, 0.5],
+            Side::Right => [0., 0.5, 0.5],
+            Side::Up => [0.5, 1., 0.5],
+            Side::Down => [0.5, 0., 0.5],
+            Side::Forth => [0.5, 0.5, 1.],
+            Side::Back => [0.5, 0.5, 0.],
+        };
+
+        for tr in all() {
+            for side in Sides::all() {
+                assert_eq!(tr.pos(center(side)), center(tr.side(side)));
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored() {
+        for tr in all() {
+            for axis in AXES {
+                let turned = tr.then(Transform::rotation(axis, 1));
+                assert_eq!(turned.is_mirrored(), tr.is_mirrored());
+
+                let mirrored = tr.then(Transform::mirror(axis));
+                assert_ne!(mirrored.is_mirrored(), tr.is_mirrored());
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use glam::{Mat4, Vec3};
+
+/// A projection of the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// A perspective projection with the vertical field of view in radians.
+    Perspective { fovy: f32, near: f32, far: f32 },
+    /// An orthographic projection with the visible height in world units.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    fn matrix(self, aspect: f32) -> Mat4 {
+        match self {
+            Self::Perspective { fovy, near, far } => Mat4::perspective_rh(fovy, aspect, near, far),
+            Self::Orthographic { height, near, far } => {
+                let (x, y) = (height * aspect / 2., height / 2.);
+                Mat4::orthographic_rh(-x, x, -y, y, near, far)
+            }
+        }
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Perspective {
+            fovy: std::f32::consts::FRAC_PI_3,
+            near: 0.1,
+            far: 1000.,
+        }
+    }
+}
+
+/// A camera of the world.
+///
+/// With zero `yaw` and `pitch` it looks along the Z axis. A positive `yaw` turns
+/// it towards the X axis and a positive `pitch` raises it towards the Y axis.
+pub struct Camera {
+    pub pos: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub projection: Projection,
+    aspect: f32,
+}
+
+impl Camera {
+    /// The pitch limit which keeps the camera from flipping over.
+    pub(crate) const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.001;
+
+    pub fn new(projection: Projection) -> Self {
+        Self {
+            pos: Vec3::ZERO,
+            yaw: 0.,
+            pitch: 0.,
+            projection,
+            aspect: 1.,
+        }
+    }
+
+    /// Sets the aspect ratio from the viewport size.
+    pub fn resize(&mut self, (width, height): (u32, u32)) {
+        self.aspect = width.max(1) as f32 / height.max(1) as f32;
+    }
+
+    /// Turns the camera by the given angles in radians.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        use std::f32::consts::TAU;
+
+        self.yaw = (self.yaw + yaw).rem_euclid(TAU);
+        self.pitch = (self.pitch + pitch).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+
+    /// Returns the unit direction of the view.
+    pub fn dir(&self) -> Vec3 {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        Vec3::new(sy * cp, sp, cy * cp)
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_to_rh(self.pos, self.dir(), Vec3::Y)
+    }
+
+    pub fn proj(&self) -> Mat4 {
+        self.projection.matrix(self.aspect)
+    }
+
+    pub fn view_proj(&self) -> Mat4 {
+        self.proj() * self.view()
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Projection::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::Vec4Swizzles};
+
+    fn project(camera: &Camera, point: Vec3) -> Vec3 {
+        let clip = camera.view_proj() * point.extend(1.);
+        clip.xyz() / clip.w
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).abs().max_element() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn dir() {
+        use std::f32::consts::FRAC_PI_2;
+
+        let mut camera = Camera::default();
+        assert_near(camera.dir(), Vec3::Z);
+
+        camera.rotate(FRAC_PI_2, 0.);
+        assert_near(camera.dir(), Vec3::X);
+
+        // The pitch is limited
+        camera.rotate(0., 10.);
+        assert!(camera.pitch < FRAC_PI_2);
+        assert!(camera.dir().y > 0.999);
+    }
+
+    #[test]
+    fn perspective() {
+        let mut camera = Camera {
+            pos: Vec3::new(1., 2., 3.),
+            ..Camera::default()
+        };
+
+        camera.resize((800, 600));
+
+        // A point in front of the camera is in the middle of the screen
+        let center = project(&camera, Vec3::new(1., 2., 13.));
+        assert_near(center * Vec3::new(1., 1., 0.), Vec3::ZERO);
+        assert!((0. ..1.).contains(&center.z));
+
+        // Farther points have greater depth
+        let far = project(&camera, Vec3::new(1., 2., 23.));
+        assert!(far.z > center.z);
+
+        // The X axis points left when the camera looks along the Z axis
+        let left = project(&camera, Vec3::new(2., 2., 13.));
+        assert!(left.x < 0.);
+    }
+
+    #[test]
+    fn orthographic() {
+        let mut camera = Camera::new(Projection::Orthographic {
+            height: 10.,
+            near: 0.,
+            far: 100.,
+        });
+
+        camera.resize((200, 100));
+
+        // The view is 20 units wide and 10 units high
+        assert_near(
+            project(&camera, Vec3::new(-10., 5., 50.)),
+            Vec3::new(1., 1., 0.5),
+        );
+        assert_near(
+            project(&camera, Vec3::new(-10., 5., 1.)),
+            Vec3::new(1., 1., 0.01),
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use {
-    crate::{state::State, view::View},
+    crate::{camera::Camera, input::Input, state::State, view::View},
+    base::kit::Model as Kit,
     render::ClientRender as Render,
     std::time::Instant,
 };
@@ -19,12 +20,36 @@ impl Engine {
         }
     }
 
+    pub fn input(&mut self, input: Input) {
+        self.state.input(input);
+    }
+
+    /// Checks if the cursor must be grabbed by the window.
+    pub fn cursor_grabbed(&self) -> bool {
+        self.state.controls().is_grabbed()
+    }
+
+    /// Sets the kit of the world, all chunks are meshed with its blocks.
+    pub fn set_kit(&mut self, kit: &Kit) {
+        self.view.set_kit(kit);
+        self.state.change_all();
+    }
+
     pub fn update(&mut self) {
         let delta = self.time.delta();
         self.state.update(delta);
+        self.view.update_chunks(&mut self.state);
         self.view.render_state(&self.state);
     }
 
+    pub fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        self.view.camera_mut()
+    }
+
     pub fn resize(&mut self, size: (u32, u32)) {
         self.view.resize(size);
     }
@@ -0,0 +1,284 @@
+use fxhash::{FxHashMap as Map, FxHashSet as Set};
+
+/// A keyboard key.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Space,
+    Shift,
+    Control,
+    Tab,
+    Escape,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+/// An input event passed to the engine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Key {
+        key: Key,
+        pressed: bool,
+    },
+    Button {
+        button: Button,
+        pressed: bool,
+    },
+    /// A raw mouse motion in pixels.
+    Motion {
+        dx: f32,
+        dy: f32,
+    },
+    /// The window gained or lost focus.
+    Focus(bool),
+}
+
+/// An action of the player.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    /// Jumps in the walk mode or ascends in the fly mode.
+    Up,
+    /// Descends in the fly mode.
+    Down,
+    /// Switches between the walk and fly modes.
+    ToggleFly,
+    /// Releases the grabbed cursor.
+    Release,
+}
+
+/// Key bindings of actions.
+pub struct Bindings {
+    keys: Map<Key, Action>,
+    /// Radians of the view turn per pixel of the mouse motion.
+    pub sensitivity: f32,
+}
+
+impl Bindings {
+    /// Creates bindings without any keys.
+    pub fn empty() -> Self {
+        Self {
+            keys: Map::default(),
+            sensitivity: 0.003,
+        }
+    }
+
+    /// Binds the `key` to the `action`, replacing the previous binding of the key.
+    pub fn bind(&mut self, key: Key, action: Action) {
+        self.keys.insert(key, action);
+    }
+
+    pub fn unbind(&mut self, key: Key) {
+        self.keys.remove(&key);
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.keys.get(&key).copied()
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Self::empty();
+        for (key, action) in [
+            (Key::W, Action::Forward),
+            (Key::S, Action::Back),
+            (Key::A, Action::Left),
+            (Key::D, Action::Right),
+            (Key::Space, Action::Up),
+            (Key::Shift, Action::Down),
+            (Key::F, Action::ToggleFly),
+            (Key::Escape, Action::Release),
+        ] {
+            bindings.bind(key, action);
+        }
+
+        bindings
+    }
+}
+
+/// The state of controls collected from input events.
+///
+/// The mouse turns the view only while the cursor is grabbed.
+/// A click grabs it, the release action or a focus loss releases it.
+#[derive(Default)]
+pub struct Controls {
+    bindings: Bindings,
+    held: Set<Action>,
+    pressed: Vec<Action>,
+    look: (f32, f32),
+    grabbed: bool,
+}
+
+impl Controls {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            ..Self::default()
+        }
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    pub fn handle(&mut self, input: Input) {
+        match input {
+            Input::Key { key, pressed } => {
+                let action = match self.bindings.action(key) {
+                    Some(action) => action,
+                    None => return,
+                };
+
+                if !pressed {
+                    self.held.remove(&action);
+                } else if action == Action::Release {
+                    self.release();
+                } else if self.held.insert(action) {
+                    // Repeated presses of a held key are ignored
+                    self.pressed.push(action);
+                }
+            }
+            Input::Button {
+                button: Button::Left,
+                pressed: true,
+            } => self.grabbed = true,
+            Input::Button { .. } => {}
+            Input::Motion { dx, dy } => {
+                if self.grabbed {
+                    let sensitivity = self.bindings.sensitivity;
+                    self.look.0 += dx * sensitivity;
+                    self.look.1 += dy * sensitivity;
+                }
+            }
+            Input::Focus(false) => {
+                self.release();
+                self.held.clear();
+            }
+            Input::Focus(true) => {}
+        }
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    pub fn is_grabbed(&self) -> bool {
+        self.grabbed
+    }
+
+    /// Takes the mouse motion in radians since the last call.
+    pub fn take_look(&mut self) -> (f32, f32) {
+        std::mem::take(&mut self.look)
+    }
+
+    /// Takes actions pressed since the last call.
+    pub fn take_pressed(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.pressed)
+    }
+
+    fn release(&mut self) {
+        self.grabbed = false;
+        self.look = (0., 0.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, pressed: bool) -> Input {
+        Input::Key { key, pressed }
+    }
+
+    #[test]
+    fn bindings() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.action(Key::W), Some(Action::Forward));
+        assert_eq!(bindings.action(Key::Q), None);
+
+        bindings.bind(Key::W, Action::Up);
+        bindings.unbind(Key::S);
+        assert_eq!(bindings.action(Key::W), Some(Action::Up));
+        assert_eq!(bindings.action(Key::S), None);
+    }
+
+    #[test]
+    fn held() {
+        let mut controls = Controls::default();
+        controls.handle(key(Key::W, true));
+        controls.handle(key(Key::W, true));
+        controls.handle(key(Key::Q, true));
+        assert!(controls.is_held(Action::Forward));
+        assert_eq!(controls.take_pressed(), [Action::Forward]);
+        assert!(controls.take_pressed().is_empty());
+
+        controls.handle(key(Key::W, false));
+        assert!(!controls.is_held(Action::Forward));
+
+        // Losing the focus releases all keys
+        controls.handle(key(Key::A, true));
+        controls.handle(Input::Focus(false));
+        assert!(!controls.is_held(Action::Left));
+    }
+
+    #[test]
+    fn grab() {
+        let mut controls = Controls::default();
+        let motion = Input::Motion { dx: 10., dy: -5. };
+
+        // The mouse doesn't turn the view until the cursor is grabbed
+        controls.handle(motion);
+        assert_eq!(controls.take_look(), (0., 0.));
+
+        controls.handle(Input::Button {
+            button: Button::Left,
+            pressed: true,
+        });
+
+        assert!(controls.is_grabbed());
+        controls.handle(motion);
+        controls.handle(motion);
+        let (yaw, pitch) = controls.take_look();
+        assert!((yaw - 0.06).abs() < 1e-6);
+        assert!((pitch + 0.03).abs() < 1e-6);
+
+        controls.handle(key(Key::Escape, true));
+        assert!(!controls.is_grabbed());
+        controls.handle(motion);
+        assert_eq!(controls.take_look(), (0., 0.));
+    }
+}
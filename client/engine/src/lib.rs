@@ -1,5 +1,14 @@
+mod camera;
 mod engine;
+mod input;
+mod player;
 mod state;
 mod view;
 
-pub use crate::engine::Engine;
+pub use crate::{
+    camera::{Camera, Projection},
+    engine::Engine,
+    input::{Action, Bindings, Button, Controls, Input, Key},
+    player::{Mode, Player},
+    state::{Chunk, State},
+};
@@ -0,0 +1,276 @@
+use {
+    crate::{
+        camera::Camera,
+        input::{Action, Controls},
+    },
+    glam::{IVec3, Vec3},
+};
+
+/// A movement mode of the player.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// The player flies freely through blocks.
+    Fly,
+    /// The player falls, jumps and collides with solid blocks.
+    Walk,
+}
+
+/// A player controller.
+///
+/// The position is the center of the player's feet.
+pub struct Player {
+    pub pos: Vec3,
+    pub vel: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub mode: Mode,
+    on_ground: bool,
+}
+
+impl Player {
+    pub const WIDTH: f32 = 0.6;
+    pub const HEIGHT: f32 = 1.8;
+    pub const EYE_HEIGHT: f32 = 1.6;
+    const FLY_SPEED: f32 = 10.;
+    const WALK_SPEED: f32 = 4.5;
+    const JUMP_SPEED: f32 = 8.;
+    const GRAVITY: f32 = 25.;
+    const MAX_FALL_SPEED: f32 = 50.;
+
+    /// The longest move at once, so the player never passes through a block.
+    const MAX_STEP: f32 = 0.25;
+    const EPSILON: f32 = 1e-4;
+
+    pub fn new(pos: Vec3, mode: Mode) -> Self {
+        Self {
+            pos,
+            vel: Vec3::ZERO,
+            yaw: 0.,
+            pitch: 0.,
+            mode,
+            on_ground: false,
+        }
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        self.pos + Vec3::Y * Self::EYE_HEIGHT
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// Moves the player by the `controls` in `delta` seconds.
+    ///
+    /// The `solid` function checks if a block at the given point blocks the movement.
+    pub fn update<S>(&mut self, controls: &mut Controls, delta: f32, solid: S)
+    where
+        S: Fn(IVec3) -> bool,
+    {
+        for action in controls.take_pressed() {
+            if action == Action::ToggleFly {
+                self.mode = match self.mode {
+                    Mode::Fly => Mode::Walk,
+                    Mode::Walk => Mode::Fly,
+                };
+
+                self.vel = Vec3::ZERO;
+            }
+        }
+
+        // Moving the mouse right turns the view right, which is towards -X
+        let (yaw, pitch) = controls.take_look();
+        self.yaw = (self.yaw - yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch - pitch).clamp(-Camera::MAX_PITCH, Camera::MAX_PITCH);
+
+        let axis = |pos, neg| f32::from(controls.is_held(pos)) - f32::from(controls.is_held(neg));
+        let (sin, cos) = self.yaw.sin_cos();
+        let forward = Vec3::new(sin, 0., cos);
+        let right = Vec3::new(-cos, 0., sin);
+        let wish = (forward * axis(Action::Forward, Action::Back)
+            + right * axis(Action::Right, Action::Left))
+        .normalize_or_zero();
+
+        match self.mode {
+            Mode::Fly => {
+                let up = axis(Action::Up, Action::Down);
+                self.vel = (wish + Vec3::Y * up) * Self::FLY_SPEED;
+                self.pos += self.vel * delta;
+                self.on_ground = false;
+            }
+            Mode::Walk => {
+                let fall = (self.vel.y - Self::GRAVITY * delta).max(-Self::MAX_FALL_SPEED);
+                let jump = self.on_ground && controls.is_held(Action::Up);
+                self.vel = wish * Self::WALK_SPEED;
+                self.vel.y = if jump { Self::JUMP_SPEED } else { fall };
+                self.on_ground = false;
+
+                let shift = self.vel * delta;
+                for axis in [1, 0, 2] {
+                    if self.move_axis(axis, shift[axis], &solid) {
+                        if axis == 1 && shift.y < 0. {
+                            self.on_ground = true;
+                        }
+
+                        self.vel[axis] = 0.;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves the player along the `axis` until it hits a solid block.
+    ///
+    /// Returns `true` if the player has hit a block.
+    fn move_axis<S>(&mut self, axis: usize, shift: f32, solid: S) -> bool
+    where
+        S: Fn(IVec3) -> bool,
+    {
+        let steps = (shift.abs() / Self::MAX_STEP).ceil().max(1.);
+        let step = shift / steps;
+        for _ in 0..steps as u32 {
+            let mut pos = self.pos;
+            pos[axis] += step;
+            let (min, max) = Self::bounds(pos);
+            let lo = (min + Self::EPSILON).floor().as_ivec3();
+            let hi = (max - Self::EPSILON).floor().as_ivec3();
+            let hit = (lo.x..=hi.x)
+                .any(|x| (lo.y..=hi.y).any(|y| (lo.z..=hi.z).any(|z| solid(IVec3::new(x, y, z)))));
+
+            if hit {
+                // Stop at the boundary of the hit block
+                let offset = if step > 0. {
+                    max[axis].floor() - max[axis]
+                } else {
+                    min[axis].ceil() - min[axis]
+                };
+
+                self.pos[axis] = pos[axis] + offset;
+                return true;
+            }
+
+            self.pos = pos;
+        }
+
+        false
+    }
+
+    fn bounds(pos: Vec3) -> (Vec3, Vec3) {
+        let half = Vec3::new(Self::WIDTH / 2., 0., Self::WIDTH / 2.);
+        (pos - half, pos + half + Vec3::Y * Self::HEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::input::{Input, Key},
+    };
+
+    fn floor(point: IVec3) -> bool {
+        point.y < 0
+    }
+
+    fn press(controls: &mut Controls, key: Key, pressed: bool) {
+        controls.handle(Input::Key { key, pressed });
+    }
+
+    #[test]
+    fn fly() {
+        let mut controls = Controls::default();
+        let mut player = Player::new(Vec3::ZERO, Mode::Fly);
+        press(&mut controls, Key::W, true);
+        press(&mut controls, Key::Space, true);
+        player.update(&mut controls, 0.5, floor);
+        assert_eq!(player.pos, Vec3::new(0., 5., 5.));
+
+        // Flying passes through blocks
+        press(&mut controls, Key::Space, false);
+        press(&mut controls, Key::Shift, true);
+        player.update(&mut controls, 1., floor);
+        assert!(player.pos.y < 0.);
+    }
+
+    #[test]
+    fn walk() {
+        let mut controls = Controls::default();
+        let mut player = Player::new(Vec3::new(0.5, 3., 0.5), Mode::Walk);
+
+        // The player falls on the floor
+        for _ in 0..60 {
+            player.update(&mut controls, 1. / 60., floor);
+        }
+
+        assert!(player.on_ground());
+        assert_eq!(player.pos.y, 0.);
+
+        // And jumps from it
+        press(&mut controls, Key::Space, true);
+        player.update(&mut controls, 1. / 60., floor);
+        assert!(player.pos.y > 0.);
+        assert!(!player.on_ground());
+        press(&mut controls, Key::Space, false);
+        for _ in 0..120 {
+            player.update(&mut controls, 1. / 60., floor);
+        }
+
+        assert!(player.on_ground());
+        assert_eq!(player.pos.y, 0.);
+    }
+
+    #[test]
+    fn wall() {
+        let wall = |point: IVec3| point.y < 0 || point.z >= 3;
+        let mut controls = Controls::default();
+        let mut player = Player::new(Vec3::new(0.5, 0., 0.5), Mode::Walk);
+        press(&mut controls, Key::W, true);
+        for _ in 0..120 {
+            player.update(&mut controls, 1. / 60., wall);
+        }
+
+        // The player stops at the wall
+        let front = player.pos.z + Player::WIDTH / 2.;
+        assert!((front - 3.).abs() < 1e-4);
+        assert_eq!(player.pos.x, 0.5);
+    }
+
+    #[test]
+    fn look() {
+        use {crate::input::Button, std::f32::consts::FRAC_PI_2};
+
+        let mut controls = Controls::default();
+        let mut player = Player::new(Vec3::ZERO, Mode::Fly);
+        controls.handle(Input::Button {
+            button: Button::Left,
+            pressed: true,
+        });
+
+        // Turn right by a quarter, so forward is towards -X
+        let dx = FRAC_PI_2 / controls.bindings_mut().sensitivity;
+        controls.handle(Input::Motion { dx, dy: -1e6 });
+        press(&mut controls, Key::W, true);
+        player.update(&mut controls, 0.1, floor);
+        assert!((player.pos - Vec3::new(-1., 0., 0.)).length() < 1e-4);
+        assert_eq!(player.pitch, Camera::MAX_PITCH);
+    }
+
+    #[test]
+    fn toggle() {
+        let mut controls = Controls::default();
+        let mut player = Player::new(Vec3::new(0., 10., 0.), Mode::Fly);
+        press(&mut controls, Key::F, true);
+        player.update(&mut controls, 0.1, floor);
+        assert_eq!(player.mode, Mode::Walk);
+        assert!(player.pos.y < 10.);
+
+        // Holding the key doesn't toggle the mode again
+        player.update(&mut controls, 0.1, floor);
+        assert_eq!(player.mode, Mode::Walk);
+
+        press(&mut controls, Key::F, false);
+        press(&mut controls, Key::F, true);
+        player.update(&mut controls, 0.1, floor);
+        assert_eq!(player.mode, Mode::Fly);
+    }
+}
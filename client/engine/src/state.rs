@@ -1,9 +1,194 @@
-pub struct State {}
+use {
+    crate::{
+        input::{Controls, Input},
+        player::{Mode, Player},
+    },
+    base::{
+        chunk::ChunkData,
+        cluster::Cluster,
+        point::{ChunkPoint, WorldPoint},
+        side::Sides,
+    },
+    fxhash::FxHashSet as Set,
+    glam::{IVec3, Vec3},
+};
+
+pub type Chunk = ChunkData<u16>;
+
+pub struct State {
+    chunks: Cluster<u16>,
+    /// Chunks changed since they were taken last time.
+    changed: Set<ChunkPoint>,
+    controls: Controls,
+    player: Player,
+}
 
 impl State {
     pub fn new() -> Self {
-        Self {}
+        const SPAWN: Vec3 = Vec3::new(0., 0., 3.);
+
+        // Look at the origin
+        let mut player = Player::new(SPAWN, Mode::Fly);
+        player.yaw = std::f32::consts::PI;
+        player.pitch = -0.4;
+
+        Self {
+            chunks: Cluster::new(),
+            changed: Set::default(),
+            controls: Controls::default(),
+            player,
+        }
+    }
+
+    pub fn input(&mut self, input: Input) {
+        self.controls.handle(input);
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        let Self {
+            chunks,
+            controls,
+            player,
+            ..
+        } = self;
+
+        // Empty blocks and unloaded chunks don't block the player
+        let solid = |point: IVec3| {
+            WorldPoint::from_absolute(point.x, point.y, point.z)
+                .and_then(|point| chunks.block(point))
+                .is_some_and(|&block| block != 0)
+        };
+
+        player.update(controls, delta, solid);
+    }
+
+    pub fn controls(&self) -> &Controls {
+        &self.controls
+    }
+
+    pub fn controls_mut(&mut self) -> &mut Controls {
+        &mut self.controls
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn player_mut(&mut self) -> &mut Player {
+        &mut self.player
+    }
+
+    pub fn chunks(&self) -> &Cluster<u16> {
+        &self.chunks
+    }
+
+    pub fn chunk(&self, point: ChunkPoint) -> Option<&Chunk> {
+        self.chunks.get(point)
+    }
+
+    pub fn set_chunk(&mut self, point: ChunkPoint, chunk: Chunk) {
+        self.chunks.insert(point, chunk);
+        self.change(point);
     }
 
-    pub fn update(&mut self, _: f32) {}
+    pub fn remove_chunk(&mut self, point: ChunkPoint) {
+        self.chunks.remove(point);
+        self.change(point);
+    }
+
+    /// Takes points of chunks changed since the last call.
+    ///
+    /// A chunk is changed with its adjacent chunks, since faces on their
+    /// border depend on each other. Some of the points may be not loaded.
+    pub fn take_changed(&mut self) -> Vec<ChunkPoint> {
+        self.changed.drain().collect()
+    }
+
+    /// Marks all loaded chunks as changed.
+    pub fn change_all(&mut self) {
+        self.changed
+            .extend(self.chunks.iter().map(|(point, _)| point));
+    }
+
+    fn change(&mut self, point: ChunkPoint) {
+        self.changed.insert(point);
+        self.changed
+            .extend(Sides::all().into_iter().filter_map(|side| point.to(side)));
+    }
+
+    /// Sets the block at the point.
+    ///
+    /// Returns `false` if the point's chunk isn't loaded.
+    pub fn set_block(&mut self, point: WorldPoint, block: u16) -> bool {
+        match self.chunks.block_mut(point) {
+            Some(place) => {
+                *place = block;
+                self.change(point.chunk_point());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::input::Key,
+        base::{chunk::size, point::BlockPoint},
+    };
+
+    #[test]
+    fn walk_on_chunk() {
+        let mut state = State::new();
+        let mut chunk = Chunk::new(0);
+        for z in 0..size::DEPTH as u8 {
+            for x in 0..size::WIDTH as u8 {
+                chunk[BlockPoint::new(x, 0, z).expect("block point")] = 1;
+            }
+        }
+
+        state.set_chunk(ChunkPoint::new(0, 0, 0).expect("chunk point"), chunk);
+        state.player_mut().pos = Vec3::new(4.5, 5., 4.5);
+        state.input(Input::Key {
+            key: Key::F,
+            pressed: true,
+        });
+
+        for _ in 0..120 {
+            state.update(1. / 60.);
+        }
+
+        // The player stands on the top of the ground layer
+        let player = state.player();
+        assert_eq!(player.mode, Mode::Walk);
+        assert!(player.on_ground());
+        assert_eq!(player.pos.y, 1.);
+    }
+
+    #[test]
+    fn changed() {
+        let mut state = State::new();
+        let origin = ChunkPoint::new(0, 0, 0).expect("chunk point");
+        state.set_chunk(origin, Chunk::new(0));
+
+        let changed = state.take_changed();
+        assert_eq!(changed.len(), 7);
+        assert!(changed.contains(&origin));
+        assert!(state.take_changed().is_empty());
+
+        let point = WorldPoint::new(BlockPoint::new(1, 2, 3).expect("block point"), origin);
+        assert!(state.set_block(point, 1));
+        assert_eq!(state.take_changed().len(), 7);
+
+        state.change_all();
+        assert_eq!(state.take_changed(), [origin]);
+    }
 }
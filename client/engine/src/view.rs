@@ -1,5 +1,15 @@
 use {
-    crate::state::State,
+    crate::{
+        camera::{Camera, Projection},
+        state::{Chunk, State},
+    },
+    base::{
+        chunk::{size, ChunkData},
+        kit::model::{Model as Kit, Palette, PaletteBlock},
+        mesher::{MeshBuffer, Pass},
+        point::{BlockPoint, ChunkPoint},
+    },
+    fxhash::FxHashMap as Map,
     render::{ClientRender as Render, Mesh, Texture},
 };
 
@@ -8,66 +18,262 @@ struct Model {
     texture: Texture,
 }
 
+/// Meshes of chunks made from blocks of the kit.
+struct World {
+    palette: Palette,
+    /// Textures of the kit atlas pages
+    pages: Vec<Texture>,
+    chunks: Map<ChunkPoint, Vec<ChunkMesh>>,
+}
+
+struct ChunkMesh {
+    mesh: Mesh,
+    pass: Pass,
+    page: u16,
+}
+
 pub struct View {
     render: Render,
+    camera: Camera,
     models: Vec<Model>,
+    world: Option<World>,
 }
 
 impl View {
     pub fn new(mut render: Render) -> Self {
-        use base::graphics::{MeshData, TextureData, Vert};
-        use image::GenericImageView;
+        use base::{
+            graphics::{Filter, MeshData, TextureData, Vert},
+            sprite::mipmap,
+        };
 
         let mesh = render.make_mesh(MeshData {
             verts: &[
                 Vert {
                     pos: [-0.5, -0.5, 0.],
                     tex: [0., 1.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
                 },
                 Vert {
                     pos: [-0.5, 0.5, 0.],
                     tex: [0., 0.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
                 },
                 Vert {
                     pos: [0.5, 0.5, 0.],
                     tex: [1., 0.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
                 },
                 Vert {
                     pos: [0.5, -0.5, 0.],
                     tex: [1., 1.],
+                    light: 1.,
+                    rect: Vert::FULL_RECT,
                 },
             ],
             faces: &[[0, 2, 1], [0, 3, 2]],
         });
 
         let raw_image = include_bytes!("../texture.png");
-        let image = image::load_from_memory(raw_image).expect("load image");
+        let image = image::load_from_memory(raw_image)
+            .expect("load image")
+            .to_rgba8();
+        let mipmaps = mipmap::generate(&image, &[], mipmap::levels(image.dimensions()));
+        let mipmaps: Vec<_> = mipmaps.iter().map(|mip| mip.as_raw().as_slice()).collect();
         let texture = render.make_texture(TextureData {
-            bytes: &image.to_rgba8(),
+            bytes: &image,
             size: image.dimensions(),
+            mipmaps: &mipmaps,
+            filter: Filter::Mipmap,
         });
 
         Self {
             render,
+            camera: Camera::new(Projection::default()),
             models: vec![Model { mesh, texture }],
+            world: None,
+        }
+    }
+
+    /// Sets the kit of the world, chunks must be meshed again after that.
+    pub fn set_kit(&mut self, kit: &Kit) {
+        use base::graphics::{Filter, TextureData};
+
+        self.clear_world();
+        let sprites = &kit.tile_sprites;
+        let pages = sprites
+            .pages()
+            .iter()
+            .enumerate()
+            .map(|(n, page)| {
+                let mipmaps = sprites.mipmaps(n);
+                let mipmaps: Vec<_> = mipmaps.iter().map(|mip| mip.as_raw().as_slice()).collect();
+                self.render.make_texture(TextureData {
+                    bytes: page,
+                    size: page.dimensions(),
+                    mipmaps: &mipmaps,
+                    filter: Filter::Mipmap,
+                })
+            })
+            .collect();
+
+        self.world = Some(World {
+            palette: Palette::new(kit),
+            pages,
+            chunks: Map::default(),
+        });
+    }
+
+    /// Meshes chunks of the `state` changed since the last update.
+    ///
+    /// Changes are kept until the kit is set.
+    pub fn update_chunks(&mut self, state: &mut State) {
+        use base::{
+            mesher::{self, Mode, Neighbours},
+            side::Sides,
+        };
+
+        let Some(world) = &mut self.world else {
+            return;
+        };
+
+        for point in state.take_changed() {
+            for ChunkMesh { mesh, .. } in world.chunks.remove(&point).into_iter().flatten() {
+                self.render.delete_mesh(mesh);
+            }
+
+            let Some(chunk) = state.chunk(point) else {
+                continue;
+            };
+
+            let palette = &world.palette;
+            let adjacent: Vec<_> = Sides::all()
+                .into_iter()
+                .filter_map(|side| {
+                    let chunk = state.chunk(point.to(side)?)?;
+                    Some((side, palette_chunk(palette, chunk)))
+                })
+                .collect();
+
+            let neighbours = adjacent
+                .iter()
+                .fold(Neighbours::new(), |neighbours, (side, chunk)| {
+                    neighbours.with(*side, chunk)
+                });
+
+            let chunk = palette_chunk(palette, chunk);
+            let meshes = mesher::mesh(&chunk, &neighbours, Mode::Greedy)
+                .iter()
+                .map(|buffer| ChunkMesh {
+                    mesh: make_chunk_mesh(&mut self.render, point, buffer),
+                    pass: buffer.pass(),
+                    page: buffer.page(),
+                })
+                .collect();
+
+            world.chunks.insert(point, meshes);
+        }
+    }
+
+    fn clear_world(&mut self) {
+        let Some(world) = self.world.take() else {
+            return;
+        };
+
+        for ChunkMesh { mesh, .. } in world.chunks.into_values().flatten() {
+            self.render.delete_mesh(mesh);
+        }
+
+        for texture in world.pages {
+            self.render.delete_texture(texture);
         }
     }
 
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
     pub fn resize(&mut self, (width, height): (u32, u32)) {
         use std::num::NonZeroU32;
 
+        self.camera.resize((width, height));
         self.render.resize((
             NonZeroU32::new(width).unwrap_or(NonZeroU32::new(1).expect("non zero")),
             NonZeroU32::new(height).unwrap_or(NonZeroU32::new(1).expect("non zero")),
         ));
     }
 
-    pub fn render_state(&mut self, _: &State) {
+    pub fn render_state(&mut self, state: &State) {
+        // The camera follows the player's eyes
+        let player = state.player();
+        self.camera.pos = player.eye();
+        self.camera.yaw = player.yaw;
+        self.camera.pitch = player.pitch;
+
+        let view_proj = self.camera.view_proj().to_cols_array_2d();
+        self.render.set_camera(&view_proj);
         self.render.draw_frame(|frame| {
             for model in &self.models {
                 frame.bind_texture(model.texture);
                 frame.draw_mesh(model.mesh);
             }
+
+            let Some(world) = &self.world else {
+                return;
+            };
+
+            // Alpha tested faces are drawn after opaque ones
+            for pass in [Pass::Solid, Pass::Cutout] {
+                frame.set_pass(pass);
+                let meshes = world.chunks.values().flatten();
+                for chunk in meshes.filter(|chunk| chunk.pass == pass) {
+                    frame.bind_texture(world.pages[usize::from(chunk.page)]);
+                    frame.draw_mesh(chunk.mesh);
+                }
+            }
         });
     }
 }
+
+/// Maps block ids of the `chunk` to blocks of the `palette`.
+fn palette_chunk<'a>(palette: &'a Palette, chunk: &Chunk) -> ChunkData<PaletteBlock<'a>> {
+    let mut blocks = ChunkData::new(palette.get(0));
+    for z in 0..size::DEPTH as u8 {
+        for y in 0..size::HEIGHT as u8 {
+            for x in 0..size::WIDTH as u8 {
+                let point = BlockPoint::new(x, y, z).expect("block point");
+                blocks[point] = palette.get(chunk[point]);
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Makes a mesh of the `buffer` translated to the chunk position in the world.
+fn make_chunk_mesh(render: &mut Render, point: ChunkPoint, buffer: &MeshBuffer) -> Mesh {
+    use base::graphics::{MeshData, Vert};
+
+    let (x, y, z) = point.into();
+    let origin = [
+        f32::from(x) * size::WIDTH as f32,
+        f32::from(y) * size::HEIGHT as f32,
+        f32::from(z) * size::DEPTH as f32,
+    ];
+
+    let verts: Vec<_> = buffer
+        .verts()
+        .iter()
+        .map(|&vert| Vert {
+            pos: [0, 1, 2].map(|i| vert.pos[i] + origin[i]),
+            ..vert
+        })
+        .collect();
+
+    render.make_mesh(MeshData {
+        verts: &verts,
+        faces: buffer.faces(),
+    })
+}
@@ -1,18 +1,31 @@
 struct VertInput {
     @location(0) pos: vec3<f32>,
     @location(1) tex: vec2<f32>,
+    @location(2) light: f32,
+    @location(3) rect: vec4<f32>,
 };
 
 struct VertOutput {
     @builtin(position) pos: vec4<f32>,
     @location(0) tex: vec2<f32>,
+    @location(1) light: f32,
+    @location(2) rect: vec4<f32>,
 };
 
+struct Camera {
+    view_proj: mat4x4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
 @vertex
 fn vs_main(vert: VertInput) -> VertOutput {
     var out: VertOutput;
-    out.pos = vec4<f32>(vert.pos, 1.);
+    out.pos = camera.view_proj * vec4<f32>(vert.pos, 1.);
     out.tex = vert.tex;
+    out.light = vert.light;
+    out.rect = vert.rect;
     return out;
 }
 
@@ -21,7 +34,26 @@ var tex: texture_2d<f32>;
 @group(0) @binding(1)
 var sam: sampler;
 
+// Samples the sprite with texture coordinates wrapped inside of its rect.
+// Gradients are taken before the wrapping, so seams don't drop to the smallest mip level.
+fn sprite_color(coords: vec2<f32>, rect: vec4<f32>) -> vec4<f32> {
+    let uv = rect.xy + fract(coords) * rect.zw;
+    let scaled = coords * rect.zw;
+    return textureSampleGrad(tex, sam, uv, dpdx(scaled), dpdy(scaled));
+}
+
 @fragment
 fn fs_main(in: VertOutput) -> @location(0) vec4<f32> {
-    return textureSample(tex, sam, in.tex);
+    let color = sprite_color(in.tex, in.rect);
+    return vec4<f32>(color.rgb * in.light, color.a);
+}
+
+@fragment
+fn fs_cutout(in: VertOutput) -> @location(0) vec4<f32> {
+    let color = sprite_color(in.tex, in.rect);
+    if (color.a < 0.5) {
+        discard;
+    }
+
+    return vec4<f32>(color.rgb * in.light, 1.);
 }
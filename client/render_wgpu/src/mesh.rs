@@ -42,7 +42,8 @@ impl Mesh {
             wgpu::{BufferAddress, VertexStepMode},
         };
 
-        const ATTRIBS: [VertexAttribute; 2] = vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+        const ATTRIBS: [VertexAttribute; 4] =
+            vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32, 3 => Float32x4];
 
         VertexBufferLayout {
             array_stride: mem::size_of::<Vert>() as BufferAddress,
@@ -1,10 +1,13 @@
 use {
     crate::{mesh::Mesh as InternalMesh, storage::Storage, texture::Texture as InternalTexture},
-    base::graphics::{MeshData, Size, TextureData},
+    base::{
+        graphics::{AsBytes, Mat, MeshData, Size, TextureData},
+        mesher::Pass,
+    },
     raw_window_handle::HasRawWindowHandle,
     wgpu::{
-        BindGroupLayout, Device, Queue, RenderPass, RenderPipeline, Surface, SurfaceConfiguration,
-        SurfaceError,
+        BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPass, RenderPipeline, Surface,
+        SurfaceConfiguration, SurfaceError, TextureFormat, TextureView,
     },
 };
 
@@ -12,8 +15,10 @@ pub struct Render {
     surface: Surface,
     surface_config: SurfaceConfiguration,
     connection: Connection,
-    pipeline: RenderPipeline,
+    pipelines: Pipelines,
     bind_group_layout: BindGroupLayout,
+    camera: Camera,
+    depth: Option<TextureView>,
     resources: Resources,
 }
 
@@ -24,12 +29,12 @@ impl Render {
     {
         use wgpu::{
             Backends, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BlendState,
-            ColorTargetState, ColorWrites, DeviceDescriptor, Face, Features, FragmentState,
-            FrontFace, Instance, Limits, MultisampleState, PipelineLayoutDescriptor, PolygonMode,
-            PowerPreference, PresentMode, PrimitiveState, PrimitiveTopology,
-            RenderPipelineDescriptor, RequestAdapterOptions, SamplerBindingType,
-            ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureSampleType, TextureUsages,
-            TextureViewDimension, VertexState,
+            ColorTargetState, ColorWrites, CompareFunction, DepthBiasState, DepthStencilState,
+            DeviceDescriptor, Face, Features, FragmentState, FrontFace, Instance, Limits,
+            MultisampleState, PipelineLayoutDescriptor, PolygonMode, PowerPreference, PresentMode,
+            PrimitiveState, PrimitiveTopology, RenderPipelineDescriptor, RequestAdapterOptions,
+            SamplerBindingType, ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilState,
+            TextureSampleType, TextureUsages, TextureViewDimension, VertexState,
         };
 
         let instance = Instance::new(Backends::all());
@@ -106,57 +111,74 @@ impl Render {
                     label: Some("bind group layout"),
                 });
 
+        let camera = Camera::new(&connection);
         let pipeline_layout = connection
             .device
             .create_pipeline_layout(&PipelineLayoutDescriptor {
                 label: Some("pipeline layout"),
-                bind_group_layouts: &[&bind_group_layout],
+                bind_group_layouts: &[&bind_group_layout, &camera.layout],
                 push_constant_ranges: &[],
             });
 
-        let pipeline = connection
-            .device
-            .create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[InternalMesh::layout()],
-                },
-                fragment: Some(FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(ColorTargetState {
-                        format: surface_config.format,
-                        blend: Some(BlendState::REPLACE),
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: FrontFace::Ccw,
-                    cull_mode: Some(Face::Back),
-                    polygon_mode: PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            });
+        let create_pipeline = |label, fragment_entry_point| {
+            connection
+                .device
+                .create_render_pipeline(&RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[InternalMesh::layout()],
+                    },
+                    fragment: Some(FragmentState {
+                        module: &shader,
+                        entry_point: fragment_entry_point,
+                        targets: &[Some(ColorTargetState {
+                            format: surface_config.format,
+                            blend: Some(BlendState::REPLACE),
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: PrimitiveState {
+                        topology: PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: FrontFace::Ccw,
+                        cull_mode: Some(Face::Back),
+                        polygon_mode: PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(DepthStencilState {
+                        format: DEPTH_FORMAT,
+                        depth_write_enabled: true,
+                        depth_compare: CompareFunction::Less,
+                        stencil: StencilState::default(),
+                        bias: DepthBiasState::default(),
+                    }),
+                    multisample: MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                })
+        };
+
+        // Alpha tested faces discard transparent fragments in their own pipeline
+        let pipelines = Pipelines {
+            solid: create_pipeline("solid pipeline", "fs_main"),
+            cutout: create_pipeline("cutout pipeline", "fs_cutout"),
+        };
 
         Self {
             surface,
             surface_config,
             connection,
-            pipeline,
+            pipelines,
             bind_group_layout,
+            camera,
+            depth: None,
             resources: Resources::default(),
         }
     }
@@ -169,6 +191,31 @@ impl Render {
 
         self.surface
             .configure(&self.connection.device, &self.surface_config);
+
+        self.depth = Some(self.create_depth());
+    }
+
+    /// Creates a depth buffer of the surface size.
+    fn create_depth(&self) -> TextureView {
+        use wgpu::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureUsages, TextureViewDescriptor,
+        };
+
+        let texture = self.connection.device.create_texture(&TextureDescriptor {
+            size: Extent3d {
+                width: self.surface_config.width.max(1),
+                height: self.surface_config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            label: Some("depth texture"),
+        });
+
+        texture.create_view(&TextureViewDescriptor::default())
     }
 
     fn draw_frame<D>(&mut self, draw_fn: D)
@@ -177,7 +224,7 @@ impl Render {
     {
         use wgpu::{
             Color, CommandEncoderDescriptor, LoadOp, Operations, RenderPassColorAttachment,
-            RenderPassDescriptor, TextureViewDescriptor,
+            RenderPassDepthStencilAttachment, RenderPassDescriptor, TextureViewDescriptor,
         };
 
         let output = loop {
@@ -193,6 +240,10 @@ impl Render {
             .texture
             .create_view(&TextureViewDescriptor::default());
 
+        if self.depth.is_none() {
+            self.depth = Some(self.create_depth());
+        }
+
         let mut encoder =
             self.connection
                 .device
@@ -216,13 +267,22 @@ impl Render {
                         store: true,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: self.depth.as_ref().expect("depth buffer"),
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
             });
 
-            pass.set_pipeline(&self.pipeline);
+            pass.set_pipeline(&self.pipelines.solid);
+            pass.set_bind_group(1, &self.camera.bind_group, &[]);
 
             let mut frame = Frame {
                 pass,
+                pipelines: &self.pipelines,
                 resources: &self.resources,
             };
             draw_fn(&mut frame);
@@ -233,21 +293,106 @@ impl Render {
     }
 }
 
+const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+struct Pipelines {
+    solid: RenderPipeline,
+    cutout: RenderPipeline,
+}
+
 pub struct Connection {
     pub device: Device,
     pub queue: Queue,
 }
 
+/// A uniform buffer of the camera view projection matrix.
+struct Camera {
+    buffer: Buffer,
+    layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl Camera {
+    fn new(connection: &Connection) -> Self {
+        use wgpu::{
+            util::{BufferInitDescriptor, DeviceExt},
+            BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+            BindingType, BufferBindingType, BufferUsages, ShaderStages,
+        };
+
+        let buffer = connection.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("camera buffer"),
+            contents: IDENTITY.as_bytes(),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let layout = connection
+            .device
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("camera bind group layout"),
+            });
+
+        let bind_group = connection.device.create_bind_group(&BindGroupDescriptor {
+            layout: &layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("camera bind group"),
+        });
+
+        Self {
+            buffer,
+            layout,
+            bind_group,
+        }
+    }
+
+    fn set(&self, connection: &Connection, view_proj: &Mat) {
+        connection
+            .queue
+            .write_buffer(&self.buffer, 0, view_proj.as_bytes());
+    }
+}
+
+const IDENTITY: Mat = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
 /// The struct represented a current frame
 /// and exists during a frame render.
 ///
 /// It has an drawing functions which calls by the engine.
 pub struct Frame<'d> {
     pass: RenderPass<'d>,
+    pipelines: &'d Pipelines,
     resources: &'d Resources,
 }
 
 impl<'d> Frame<'d> {
+    /// Sets the pipeline of the `pass` for next meshes, it's the solid one by default.
+    pub fn set_pass(&mut self, pass: Pass) {
+        let pipeline = match pass {
+            Pass::Solid => &self.pipelines.solid,
+            Pass::Cutout => &self.pipelines.cutout,
+        };
+
+        self.pass.set_pipeline(pipeline);
+    }
+
     pub fn bind_texture(&mut self, texture: Texture) {
         let texture = self.resources.textures.get(texture.0);
         self.pass.set_bind_group(0, texture.bind_group(), &[]);
@@ -311,6 +456,11 @@ impl ClientRender {
     pub fn resize(&mut self, size: Size) {
         self.render.resize(Some(size));
     }
+
+    /// Sets the view projection matrix of the camera.
+    pub fn set_camera(&mut self, view_proj: &Mat) {
+        self.render.camera.set(&self.render.connection, view_proj);
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -1,6 +1,6 @@
 use {
     crate::render::Connection,
-    base::graphics::TextureData,
+    base::graphics::{Filter, TextureData},
     wgpu::{BindGroup, BindGroupLayout},
 };
 
@@ -27,9 +27,10 @@ impl Texture {
             depth_or_array_layers: 1,
         };
 
+        let mip_level_count = data.mipmaps.len() as u32 + 1;
         let texture = connection.device.create_texture(&TextureDescriptor {
             size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: TextureDimension::D2,
             format: TextureFormat::Rgba8UnormSrgb,
@@ -37,21 +38,35 @@ impl Texture {
             label: Some("texture"),
         });
 
-        connection.queue.write_texture(
-            ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                aspect: TextureAspect::All,
-            },
-            data.bytes,
-            ImageDataLayout {
-                offset: 0,
-                bytes_per_row: NonZeroU32::new(width * 4),
-                rows_per_image: NonZeroU32::new(height),
-            },
-            size,
-        );
+        let levels = [data.bytes].into_iter().chain(data.mipmaps.iter().copied());
+        for (level, bytes) in (0..).zip(levels) {
+            let width = (width >> level).max(1);
+            let height = (height >> level).max(1);
+            connection.queue.write_texture(
+                ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                bytes,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(width * 4),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let (min_filter, mipmap_filter) = match data.filter {
+            Filter::Nearest => (FilterMode::Nearest, FilterMode::Nearest),
+            Filter::Mipmap => (FilterMode::Nearest, FilterMode::Linear),
+        };
 
         let view = texture.create_view(&TextureViewDescriptor::default());
         let sampler = connection.device.create_sampler(&SamplerDescriptor {
@@ -59,8 +74,8 @@ impl Texture {
             address_mode_v: AddressMode::ClampToEdge,
             address_mode_w: AddressMode::ClampToEdge,
             mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
+            min_filter,
+            mipmap_filter,
             ..Default::default()
         });
 
@@ -0,0 +1,72 @@
+use {
+    engine::{Button, Input, Key},
+    winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+};
+
+/// Converts a window event to an engine input if the engine handles it.
+pub fn from_window_event(event: &WindowEvent) -> Option<Input> {
+    match *event {
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    virtual_keycode: Some(code),
+                    state,
+                    ..
+                },
+            ..
+        } => Some(Input::Key {
+            key: key(code)?,
+            pressed: state == ElementState::Pressed,
+        }),
+        WindowEvent::MouseInput { state, button, .. } => Some(Input::Button {
+            button: match button {
+                MouseButton::Left => Button::Left,
+                MouseButton::Right => Button::Right,
+                MouseButton::Middle => Button::Middle,
+                MouseButton::Other(_) => return None,
+            },
+            pressed: state == ElementState::Pressed,
+        }),
+        WindowEvent::Focused(focused) => Some(Input::Focus(focused)),
+        _ => None,
+    }
+}
+
+fn key(code: VirtualKeyCode) -> Option<Key> {
+    let key = match code {
+        VirtualKeyCode::A => Key::A,
+        VirtualKeyCode::B => Key::B,
+        VirtualKeyCode::C => Key::C,
+        VirtualKeyCode::D => Key::D,
+        VirtualKeyCode::E => Key::E,
+        VirtualKeyCode::F => Key::F,
+        VirtualKeyCode::G => Key::G,
+        VirtualKeyCode::H => Key::H,
+        VirtualKeyCode::I => Key::I,
+        VirtualKeyCode::J => Key::J,
+        VirtualKeyCode::K => Key::K,
+        VirtualKeyCode::L => Key::L,
+        VirtualKeyCode::M => Key::M,
+        VirtualKeyCode::N => Key::N,
+        VirtualKeyCode::O => Key::O,
+        VirtualKeyCode::P => Key::P,
+        VirtualKeyCode::Q => Key::Q,
+        VirtualKeyCode::R => Key::R,
+        VirtualKeyCode::S => Key::S,
+        VirtualKeyCode::T => Key::T,
+        VirtualKeyCode::U => Key::U,
+        VirtualKeyCode::V => Key::V,
+        VirtualKeyCode::W => Key::W,
+        VirtualKeyCode::X => Key::X,
+        VirtualKeyCode::Y => Key::Y,
+        VirtualKeyCode::Z => Key::Z,
+        VirtualKeyCode::Space => Key::Space,
+        VirtualKeyCode::LShift | VirtualKeyCode::RShift => Key::Shift,
+        VirtualKeyCode::LControl | VirtualKeyCode::RControl => Key::Control,
+        VirtualKeyCode::Tab => Key::Tab,
+        VirtualKeyCode::Escape => Key::Escape,
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -1,9 +1,19 @@
+mod input;
+mod net;
 mod scheduler;
 
 use {
-    crate::scheduler::Scheduler,
-    engine::Engine,
+    crate::{
+        net::{Connection, Event as NetEvent},
+        scheduler::Scheduler,
+    },
+    base::{
+        kit::{model, Model},
+        point::ChunkPoint,
+    },
+    engine::{Engine, Input},
     render::{ClientRender, Render},
+    std::{fmt, fs, io, path::Path},
 };
 
 fn main() -> ! {
@@ -14,19 +24,21 @@ fn main() -> ! {
 fn start() -> ! {
     use winit::{
         dpi::PhysicalSize,
-        event::{Event, WindowEvent},
+        event::{DeviceEvent, Event, WindowEvent},
         event_loop::{ControlFlow, EventLoop},
         window::WindowBuilder,
     };
 
     const WINDOW_SIZE: (u32, u32) = (800, 600);
     const WINDOW_TITLE: &str = "Germina";
+    const KITS_CACHE: &str = "cache/kits";
 
     let scheduler = Scheduler::new();
-    // Example of tasks creation
-    for i in 0..10 {
-        scheduler.spawn(async move { i * i });
-    }
+
+    // Connect to the server if its address is passed
+    let connection = std::env::args()
+        .nth(1)
+        .map(|addr| Connection::connect(&scheduler, addr, KITS_CACHE.into()));
 
     let (window, el) = {
         let el = EventLoop::new();
@@ -51,26 +63,131 @@ fn start() -> ! {
         engine
     };
 
+    let mut cursor_grabbed = false;
     el.run(move |ev, _, flow| match ev {
         Event::WindowEvent { event, window_id } if window_id == window.id() => match event {
-            WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
+            WindowEvent::CloseRequested => {
+                if let Some(connection) = &connection {
+                    connection.quit();
+                }
+
+                *flow = ControlFlow::Exit;
+            }
             WindowEvent::Resized(size)
             | WindowEvent::ScaleFactorChanged {
                 new_inner_size: &mut size,
                 ..
             } => engine.resize(size.into()),
-            _ => {}
+            event => {
+                if let Some(input) = input::from_window_event(&event) {
+                    engine.input(input);
+                }
+            }
         },
+        Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+            ..
+        } => engine.input(Input::Motion {
+            dx: dx as f32,
+            dy: dy as f32,
+        }),
         Event::MainEventsCleared => {
+            if let Some(connection) = &connection {
+                for event in connection.events() {
+                    match event {
+                        NetEvent::Connected { kit } => {
+                            println!("connected with kit {}", kit.display());
+                            match load_kit(&kit) {
+                                Ok(kit) => engine.set_kit(&kit),
+                                Err(err) => {
+                                    eprintln!("failed to load kit {}: {err}", kit.display())
+                                }
+                            }
+
+                            // Request chunks around the spawn
+                            for x in -1..=1 {
+                                for z in -1..=1 {
+                                    let point = ChunkPoint::new(x, 0, z).expect("chunk point");
+                                    connection.request_chunk(point);
+                                }
+                            }
+                        }
+                        NetEvent::Chunk {
+                            point,
+                            chunk: Some(chunk),
+                        } => engine.state_mut().set_chunk(point, *chunk),
+                        NetEvent::Chunk { point, chunk: None } => {
+                            engine.state_mut().remove_chunk(point)
+                        }
+                        NetEvent::ChunkError { point } => {
+                            eprintln!("the server failed to load chunk {point:?}");
+                        }
+                        NetEvent::BlockChanged { point, block } => {
+                            engine.state_mut().set_block(point, block);
+                        }
+                    }
+                }
+            }
+
             engine.update();
 
+            if engine.cursor_grabbed() != cursor_grabbed {
+                cursor_grabbed = engine.cursor_grabbed();
+                if let Err(err) = window.set_cursor_grab(cursor_grabbed) {
+                    eprintln!("failed to grab the cursor: {err}");
+                }
+
+                window.set_cursor_visible(!cursor_grabbed);
+            }
+
             // Process reports of ready tasks
             for report in scheduler.ready() {
-                let id = report.id;
-                let value: &i32 = report.value.downcast_ref().expect("downcast");
-                println!("{id:?}: {value}");
+                let is_connection = connection
+                    .as_ref()
+                    .is_some_and(|connection| connection.task() == report.id);
+
+                if is_connection {
+                    let res: &Result<(), net::Error> =
+                        report.value.downcast_ref().expect("downcast");
+
+                    if let Err(err) = res {
+                        eprintln!("connection error: {err}");
+                    }
+                }
             }
         }
         _ => {}
     })
 }
+
+fn load_kit(path: &Path) -> Result<Model, KitError> {
+    let archive = fs::read(path)?;
+    let kit = Model::load(&archive, |_| {})?;
+    Ok(kit)
+}
+
+enum KitError {
+    Io(io::Error),
+    Model(model::Error),
+}
+
+impl From<io::Error> for KitError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<model::Error> for KitError {
+    fn from(err: model::Error) -> Self {
+        Self::Model(err)
+    }
+}
+
+impl fmt::Display for KitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Model(err) => write!(f, "{err}"),
+        }
+    }
+}
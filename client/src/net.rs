@@ -0,0 +1,292 @@
+use {
+    crate::scheduler::{Scheduler, TaskId},
+    async_std::{channel, fs, io, net::TcpStream, prelude::*},
+    base::{
+        chunk::codec::DecodeError,
+        kit::{Hash, Key},
+        net::{self, ClientMessage, Message, Reason, ServerMessage},
+        point::{ChunkPoint, WorldPoint},
+    },
+    engine::Chunk,
+    std::{
+        fmt,
+        future::Future,
+        path::PathBuf,
+        sync::mpsc,
+        time::{Duration, Instant},
+    },
+};
+
+/// The time to wait for the session to send the quit message.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub enum Event {
+    /// The handshake is done and the server's kit is in the cache.
+    Connected {
+        kit: PathBuf,
+    },
+    /// A received chunk, or `None` if the server doesn't have it.
+    Chunk {
+        point: ChunkPoint,
+        chunk: Option<Box<Chunk>>,
+    },
+    /// The server failed to load the chunk.
+    ChunkError {
+        point: ChunkPoint,
+    },
+    BlockChanged {
+        point: WorldPoint,
+        block: u16,
+    },
+}
+
+/// A connection to the server.
+///
+/// The session runs as a scheduler task, so it never blocks the event loop.
+/// Received data is polled with `events` and the session result is reported
+/// by the scheduler as `Result<(), Error>` when the connection is closed.
+pub struct Connection {
+    task: TaskId,
+    requests: channel::Sender<ClientMessage>,
+    events: mpsc::Receiver<Event>,
+}
+
+impl Connection {
+    /// Connects to the server at the `addr`.
+    ///
+    /// Downloaded kits are stored in the `cache` directory.
+    pub fn connect(scheduler: &Scheduler, addr: String, cache: PathBuf) -> Self {
+        let (requests, requests_receiver) = channel::unbounded();
+        let (events_sender, events) = mpsc::channel();
+        let task = scheduler.spawn(session(addr, cache, requests_receiver, events_sender));
+
+        Self {
+            task,
+            requests,
+            events,
+        }
+    }
+
+    pub fn task(&self) -> TaskId {
+        self.task
+    }
+
+    pub fn request_chunk(&self, point: ChunkPoint) {
+        self.send(ClientMessage::RequestChunk(point));
+    }
+
+    /// Sends the quit message and waits until the session is over,
+    /// but not longer than `QUIT_TIMEOUT`.
+    pub fn quit(&self) {
+        self.send(ClientMessage::Disconnect(Reason::Quit));
+
+        // The events sender is dropped when the session is over
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            if self.events.recv_timeout(timeout).is_err() {
+                break;
+            }
+        }
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = Event> + '_ {
+        (0..).map_while(|_| self.events.try_recv().ok())
+    }
+
+    fn send(&self, message: ClientMessage) {
+        // The channel is closed only when the session is over,
+        // which is reported by the scheduler
+        let _ = self.requests.try_send(message);
+    }
+}
+
+async fn session(
+    addr: String,
+    cache: PathBuf,
+    requests: channel::Receiver<ClientMessage>,
+    events: mpsc::Sender<Event>,
+) -> Result<(), Error> {
+    let mut stream = TcpStream::connect(&addr).await?;
+    write(
+        &mut stream,
+        &ClientMessage::Hello {
+            version: net::VERSION,
+        },
+    )
+    .await?;
+
+    let (kit, hash) = match read(&mut stream).await? {
+        ServerMessage::Welcome { kit, hash } => (kit, hash),
+        message => return Err(unexpected(message)),
+    };
+
+    // The kit name is a part of the cache path, so check it's a valid key
+    let mut path = cache.join(hash.to_string());
+    match kit.parse::<Key>() {
+        Ok(key) => path.push(key.get()),
+        Err(_) => return Err(Error::KitName(kit)),
+    }
+
+    path.set_extension("kit");
+    let cached = match fs::read(&path).await {
+        Ok(archive) => Hash::new(&archive) == hash,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+        Err(err) => return Err(err.into()),
+    };
+
+    if !cached {
+        let archive = download(&mut stream, hash).await?;
+        fs::create_dir_all(path.parent().expect("parent")).await?;
+
+        // Write to a temporary file first, so an interrupted write never leaves a broken kit
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, archive).await?;
+        fs::rename(&tmp, &path).await?;
+    }
+
+    if events.send(Event::Connected { kit: path }).is_err() {
+        return Ok(());
+    }
+
+    // The session is over when either half is done
+    first(
+        send_requests(stream.clone(), requests),
+        receive(stream, events),
+    )
+    .await
+}
+
+/// Polls both futures and returns the output of the first completed one.
+async fn first<A, B, T>(a: A, b: B) -> T
+where
+    A: Future<Output = T>,
+    B: Future<Output = T>,
+{
+    use std::{future, pin::pin, task::Poll};
+
+    let (mut a, mut b) = (pin!(a), pin!(b));
+    future::poll_fn(|cx| match a.as_mut().poll(cx) {
+        Poll::Ready(value) => Poll::Ready(value),
+        Poll::Pending => b.as_mut().poll(cx),
+    })
+    .await
+}
+
+async fn receive(mut stream: TcpStream, events: mpsc::Sender<Event>) -> Result<(), Error> {
+    loop {
+        let event = match read(&mut stream).await? {
+            ServerMessage::Chunk { point, data } => Event::Chunk {
+                point,
+                chunk: data
+                    .map(|data| Chunk::decode(&data).map(Box::new))
+                    .transpose()?,
+            },
+            ServerMessage::ChunkError { point } => Event::ChunkError { point },
+            ServerMessage::BlockChanged { point, block } => Event::BlockChanged { point, block },
+            message => return Err(unexpected(message)),
+        };
+
+        if events.send(event).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+async fn download(stream: &mut TcpStream, hash: Hash) -> Result<Vec<u8>, Error> {
+    write(stream, &ClientMessage::RequestKit).await?;
+
+    let mut archive = vec![];
+    loop {
+        match read(stream).await? {
+            ServerMessage::KitPart { size, data } => {
+                archive.extend(data);
+                if archive.len() >= size as usize {
+                    break;
+                }
+            }
+            message => return Err(unexpected(message)),
+        }
+    }
+
+    if Hash::new(&archive) != hash {
+        return Err(Error::KitHash);
+    }
+
+    Ok(archive)
+}
+
+async fn send_requests(
+    mut stream: TcpStream,
+    requests: channel::Receiver<ClientMessage>,
+) -> Result<(), Error> {
+    while let Ok(message) = requests.recv().await {
+        write(&mut stream, &message).await?;
+        if let ClientMessage::Disconnect(_) = message {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+async fn write(stream: &mut TcpStream, message: &ClientMessage) -> Result<(), Error> {
+    stream.write_all(&net::frame(message)).await?;
+    Ok(())
+}
+
+async fn read(stream: &mut TcpStream) -> Result<ServerMessage, Error> {
+    let mut header = [0; 4];
+    stream.read_exact(&mut header).await?;
+    let mut buf = vec![0; net::frame_len(header)?];
+    stream.read_exact(&mut buf).await?;
+    Ok(ServerMessage::decode(&buf)?)
+}
+
+fn unexpected(message: ServerMessage) -> Error {
+    match message {
+        ServerMessage::Disconnect(reason) => Error::Disconnected(reason),
+        _ => Error::Unexpected,
+    }
+}
+
+pub enum Error {
+    Io(io::Error),
+    Net(net::Error),
+    Decode(DecodeError),
+    Disconnected(Reason),
+    Unexpected,
+    KitName(String),
+    KitHash,
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<net::Error> for Error {
+    fn from(err: net::Error) -> Self {
+        Self::Net(err)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {err}"),
+            Self::Net(err) => write!(f, "{err}"),
+            Self::Decode(err) => write!(f, "failed to decode a chunk: {err}"),
+            Self::Disconnected(reason) => write!(f, "disconnected by the server: {reason}"),
+            Self::Unexpected => write!(f, "unexpected message from the server"),
+            Self::KitName(name) => write!(f, "invalid kit name {name}"),
+            Self::KitHash => write!(f, "the downloaded kit doesn't match its hash"),
+        }
+    }
+}
@@ -1,12 +1,19 @@
 use {
-    crate::error::JsonError,
+    crate::error::StyledJson,
+    base::kit::{self, JsonError},
     serde::Deserialize,
-    std::{fmt, io, path::Path},
+    std::{
+        fmt, io,
+        path::{Path, PathBuf},
+    },
 };
 
 #[derive(Deserialize)]
 pub struct Config {
     pub net: Net,
+    /// The directory where worlds are stored
+    #[serde(default = "default_worlds")]
+    pub worlds: PathBuf,
 }
 
 impl Config {
@@ -14,16 +21,16 @@ impl Config {
         use std::fs;
 
         let content = fs::read_to_string(path)?;
-        let config = json::from_str(&content).map_err(|err| JsonError {
-            err,
-            src: content,
-            filename: None,
-        })?;
+        let config = kit::from_json(&content, None)?;
 
         Ok(config)
     }
 }
 
+fn default_worlds() -> PathBuf {
+    PathBuf::from("worlds")
+}
+
 #[derive(Deserialize)]
 pub struct Net {
     ip: String,
@@ -64,7 +71,7 @@ impl From<JsonError> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Json(json) => write!(f, "{json}"),
+            Self::Json(json) => write!(f, "{}", StyledJson(json)),
             Self::NotFound => write!(f, "file not found"),
             Self::PermissionDenied => write!(f, "permission denied"),
             Self::Other => write!(f, "unknown file handling error"),
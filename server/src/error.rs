@@ -1,11 +1,14 @@
 use {
-    crate::{config, load},
+    crate::{config, load, world},
+    base::kit::{model, JsonError},
     std::{fmt, io, path::PathBuf},
 };
 
 pub enum Error {
     Config { err: config::Error, path: PathBuf },
     Load { err: load::Error, path: PathBuf },
+    World { err: world::Error, name: String },
+    Net { err: io::Error, addr: String },
 }
 
 impl Error {
@@ -28,6 +31,14 @@ impl Error {
                 );
                 eprint!("{err}");
             }
+            Self::World { err, name } => {
+                eprintln!("in world {}", name.bold());
+                eprint!("{err}");
+            }
+            Self::Net { err, addr } => {
+                eprintln!("on address {}", addr.bold());
+                eprint!("{}", IoError { err, path: None });
+            }
         }
 
         std::process::exit(1)
@@ -68,50 +79,51 @@ impl fmt::Display for IoError {
     }
 }
 
-pub struct JsonError {
-    pub err: json::Error,
-    pub src: String,
-    pub filename: Option<String>,
-}
+/// A kit model error with styled filenames.
+pub struct StyledModel<'a>(pub &'a model::Error);
 
-impl fmt::Display for JsonError {
+impl fmt::Display for StyledModel<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use crossterm::style::Stylize;
 
-        const SHOW_LINES: usize = 3;
+        match self.0 {
+            model::Error::Tile { err, filename } => {
+                write!(f, "in tile {}: {err}", filename.as_str().bold())
+            }
+            model::Error::Shape { err, filename } => {
+                write!(f, "in shape {}: {err}", filename.as_str().bold())
+            }
+            model::Error::Json(json) => write!(f, "{}", StyledJson(json)),
+            err => write!(f, "{err}"),
+        }
+    }
+}
+
+/// A JSON error with a styled filename and error mark.
+pub struct StyledJson<'a>(pub &'a JsonError);
 
-        let Self {
-            err: json::Error::Message { msg, location },
-            src,
-            filename,
-        } = self;
+impl fmt::Display for StyledJson<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crossterm::style::Stylize;
 
+        let json = self.0;
         write!(f, "while parsing")?;
-        if let Some(filename) = filename {
-            write!(f, " {}", filename.as_str().bold())?;
+        if let Some(filename) = json.filename() {
+            write!(f, " {}", filename.bold())?;
         }
 
-        match location {
-            Some(location) if !src.trim().is_empty() => {
-                let n_line = location.line;
-                writeln!(f, " at line {n_line}:")?;
-
-                let start = n_line.saturating_sub(SHOW_LINES);
-                for line in src.lines().skip(start).take(n_line.min(SHOW_LINES)) {
+        match json.location() {
+            Some((line, column)) => {
+                writeln!(f, " at line {line}:")?;
+                for line in json.excerpt() {
                     writeln!(f, "{line}")?;
                 }
 
-                let column = location.column;
                 writeln!(f, "{:>column$}{}", "", '^'.yellow().bold())?;
             }
-            _ => writeln!(f, ":")?,
+            None => writeln!(f, ":")?,
         }
 
-        // Trim pest error format
-        let msg = msg
-            .rsplit_once('=')
-            .map_or_else(|| msg.as_str(), |(_, right)| right.trim());
-
-        write!(f, "{msg}")
+        write!(f, "{}", json.message())
     }
 }
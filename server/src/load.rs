@@ -1,26 +1,20 @@
-mod model;
-
 use {
-    self::model::Model,
-    crate::error::{IoError, JsonError},
-    base::kit::{Asset, Key, Kind, ParseKeyError},
-    fxhash::FxHashSet as Set,
-    std::{
-        fmt,
-        io::{self, Read},
-        path::{Path, PathBuf},
-    },
-    zip::{result::ZipError, ZipArchive},
+    crate::error::{IoError, StyledModel},
+    base::kit::{model, Hash, Key, Model, ParseKeyError},
+    std::{fmt, io, path::Path},
 };
 
 pub struct KitSource {
     pub name: Key,
+    pub hash: Hash,
+    /// The raw kit archive
+    pub archive: Vec<u8>,
     pub model: Model,
 }
 
 impl KitSource {
     pub fn load(path: &Path) -> Result<Self, Error> {
-        use std::{ffi::OsStr, fs::File, mem};
+        use std::{ffi::OsStr, fs};
 
         let name = path
             .file_name()
@@ -30,74 +24,19 @@ impl KitSource {
             .0
             .parse()?;
 
-        let mut model = Model::default();
-
-        let file = File::open(path)?;
-        let mut arch = ZipArchive::new(file)?;
-        let mut content = String::with_capacity(128);
-
-        for i in 0..arch.len() {
-            let mut file = arch.by_index(i)?;
-            if !file.is_file() {
-                continue;
-            }
-
-            let filename = file.name();
-            let Asset { name, kind } = match Asset::parse_path(filename) {
-                Some(asset) => asset,
-                None => {
-                    let kitname = path.file_name().expect("filename");
-                    log::info!("entry {filename} skipped in {kitname:?}");
-                    continue;
-                }
-            };
-
-            match kind {
-                Kind::Tile => {
-                    content.clear();
-                    file.read_to_string(&mut content)?;
-                    let tile = json::from_str(&content).map_err(|err| JsonError {
-                        err,
-                        src: mem::take(&mut content),
-                        filename: Some(file.name().into()),
-                    })?;
-                    model.tiles.insert(name, tile);
-                }
-            }
-        }
-
-        let mut path_buf = String::with_capacity(32);
-        let mut tile_sprites = Set::default();
-        for (_, tile) in model.tiles.iter() {
-            tile.sprites(|key| {
-                tile_sprites.insert(key.clone());
-            });
-        }
-
-        for sprite_key in tile_sprites {
-            model
-                .tile_sprites
-                .try_insert::<_, Error>(sprite_key, |key| {
-                    path_buf.clear();
-                    path_buf.push_str("sprites/tiles/");
-                    path_buf.push_str(key);
-                    path_buf.push_str(".png");
-
-                    let mut file = arch.by_name(&path_buf).map_err(|err| match err {
-                        ZipError::FileNotFound => Error::Io(IoError {
-                            err: io::ErrorKind::NotFound.into(),
-                            path: Some(PathBuf::from(&path_buf)),
-                        }),
-                        err => err.into(),
-                    })?;
-
-                    let mut buf = Vec::with_capacity(128);
-                    file.read_to_end(&mut buf)?;
-                    Ok(buf)
-                })?;
-        }
-
-        Ok(Self { name, model })
+        let archive = fs::read(path)?;
+        let hash = Hash::new(&archive);
+        let model = Model::load(&archive, |filename| {
+            let kitname = path.file_name().expect("filename");
+            log::info!("entry {filename} skipped in {kitname:?}");
+        })?;
+
+        Ok(Self {
+            name,
+            hash,
+            archive,
+            model,
+        })
     }
 }
 
@@ -105,8 +44,7 @@ pub enum Error {
     UndefinedName,
     ParseKey(ParseKeyError),
     Io(IoError),
-    Json(JsonError),
-    Arch(&'static str),
+    Model(model::Error),
 }
 
 impl From<ParseKeyError> for Error {
@@ -121,19 +59,9 @@ impl From<io::Error> for Error {
     }
 }
 
-impl From<JsonError> for Error {
-    fn from(err: JsonError) -> Self {
-        Self::Json(err)
-    }
-}
-
-impl From<ZipError> for Error {
-    fn from(err: ZipError) -> Self {
-        match err {
-            ZipError::Io(err) => err.into(),
-            ZipError::InvalidArchive(arch) | ZipError::UnsupportedArchive(arch) => Self::Arch(arch),
-            ZipError::FileNotFound => Self::Arch("file not found"),
-        }
+impl From<model::Error> for Error {
+    fn from(err: model::Error) -> Self {
+        Self::Model(err)
     }
 }
 
@@ -143,8 +71,7 @@ impl fmt::Display for Error {
             Self::UndefinedName => write!(f, "kit name is undefined"),
             Self::ParseKey(err) => write!(f, "failed parse a key: {err}"),
             Self::Io(io) => write!(f, "{io}"),
-            Self::Json(json) => write!(f, "{json}"),
-            Self::Arch(arch) => write!(f, "archive error: {arch}"),
+            Self::Model(err) => write!(f, "{}", StyledModel(err)),
         }
     }
 }
@@ -1,9 +1,11 @@
 mod config;
 mod error;
 mod load;
+mod net;
+mod world;
 
 use {
-    crate::{config::Config, error::Error, load::KitSource},
+    crate::{config::Config, error::Error, load::KitSource, net::Server, world::World},
     clap::{Parser, Subcommand},
 };
 
@@ -27,6 +29,18 @@ enum Command {
         /// A world name
         name: String,
     },
+    /// Open an existing world and serve it on the configured address
+    Open {
+        /// A world name
+        name: String,
+    },
+    /// List all worlds
+    List,
+    /// Compact region files of a world
+    Compact {
+        /// A world name
+        name: String,
+    },
 }
 
 fn main() {
@@ -47,24 +61,101 @@ fn run(cli: Cli) -> Result<(), Error> {
         })?
     };
 
-    let _ = config.net.addr();
-
     match cli.command {
-        Command::Make { path, .. } => {
+        Command::Make { path, name } => {
             let kit = KitSource::load(path.as_ref()).map_err(|err| Error::Load {
                 err,
                 path: path.into(),
             })?;
 
-            println!("kit: {}", kit.name);
+            println!("kit: {} ({})", kit.name, kit.hash);
             println!("tiles:");
-            for (key, _) in kit.model.tiles.iter() {
-                println!("    {key}");
+            for (key, tile) in kit.model.tiles.iter() {
+                let (width, height, depth) = tile.size();
+                let n_blocks = tile.blocks().len();
+                println!("    {key} {width}x{height}x{depth}, {n_blocks} blocks");
+            }
+
+            println!("shapes:");
+            for (key, shape) in kit.model.shapes.iter() {
+                let n_parts = shape.parts.len();
+                println!("    {key}, {n_parts} parts");
             }
 
+            let sprites = &kit.model.tile_sprites;
             println!("tile sprites:");
-            for (key, _) in kit.model.tile_sprites.iter() {
-                println!("    {key}");
+            for key in sprites.keys() {
+                let rect = sprites.rect(key).expect("sprite rect");
+                let (width, height) = rect.size;
+                println!("    {key} {width}x{height}, page {}", rect.page);
+            }
+
+            println!("atlas pages:");
+            for page in sprites.pages() {
+                let (width, height) = page.dimensions();
+                println!("    {width}x{height}");
+            }
+
+            let world = World::make(&config.worlds, &name, &kit)
+                .map_err(|err| Error::World { err, name })?;
+
+            println!("world {} created", world.name);
+            Ok(())
+        }
+        Command::Open { name } => {
+            let world = match World::open(&config.worlds, &name) {
+                Ok(world) => world,
+                Err(err) => return Err(Error::World { err, name }),
+            };
+
+            let map_err = |err| Error::World {
+                err,
+                name: name.clone(),
+            };
+
+            println!("world: {}", world.name);
+            println!("kits:");
+            for kit in &world.meta.kits {
+                println!("    {} ({})", kit.name, kit.hash);
+            }
+
+            let points = world.chunks().map_err(map_err)?;
+            for &point in &points {
+                world.load_chunk(point).map_err(map_err)?;
+            }
+
+            println!("blocks: {}", world.meta.blocks.len());
+            println!("chunks: {}", points.len());
+
+            let (ip, port) = config.net.addr();
+            let server = Server::bind((ip, port)).map_err(|err| Error::Net {
+                err,
+                addr: format!("{ip}:{port}"),
+            })?;
+
+            match server.local_addr() {
+                Ok(addr) => println!("listening on {addr}"),
+                Err(_) => println!("listening on {ip}:{port}"),
+            }
+
+            server.run(&world).map_err(map_err)
+        }
+        Command::Compact { name } => {
+            let freed = World::open(&config.worlds, &name)
+                .and_then(|world| world.compact())
+                .map_err(|err| Error::World { err, name })?;
+
+            println!("{freed} bytes freed");
+            Ok(())
+        }
+        Command::List => {
+            let names = World::list(&config.worlds).map_err(|err| Error::World {
+                err,
+                name: config.worlds.display().to_string(),
+            })?;
+
+            for name in names {
+                println!("{name}");
             }
 
             Ok(())
@@ -0,0 +1,552 @@
+mod chunks;
+
+use {
+    self::chunks::{Chunks, Interest},
+    crate::world::{self, World},
+    base::{
+        chunk::codec::Encoding,
+        net::{self, ClientMessage, Reason, ServerMessage},
+    },
+    fxhash::FxHashMap as Map,
+    std::{
+        io,
+        net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+        sync::{
+            mpsc::{self, Receiver, Sender, SyncSender},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+type ClientId = u32;
+
+/// The maximum number of messages queued for a client.
+/// A client which doesn't keep up with its queue is dropped.
+const QUEUE_LEN: usize = 1024;
+
+/// The number of recently requested chunks kept in memory for each client.
+const INTEREST_LEN: usize = 4096;
+
+/// The time after which a stalled write to a client fails.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Event {
+    Connected {
+        id: ClientId,
+        stream: TcpStream,
+    },
+    Message {
+        id: ClientId,
+        message: ClientMessage,
+    },
+    Invalid {
+        id: ClientId,
+        err: net::Error,
+    },
+    Closed {
+        id: ClientId,
+    },
+}
+
+/// A message queued for a client's writer thread.
+enum Outgoing {
+    Message(ServerMessage),
+    /// The kit archive, it's sent in `KitPart`s.
+    Kit(Arc<[u8]>),
+}
+
+struct Client {
+    queue: SyncSender<Outgoing>,
+    /// A handle to shut the connection down
+    stream: TcpStream,
+    interest: Interest,
+    welcomed: bool,
+}
+
+impl Client {
+    /// Spawns a writer thread for the client's `stream`.
+    fn new(id: ClientId, stream: TcpStream) -> io::Result<Self> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let writer = stream.try_clone()?;
+        let (queue, receiver) = mpsc::sync_channel(QUEUE_LEN);
+        thread::spawn(move || write(id, writer, receiver));
+
+        Ok(Self {
+            queue,
+            stream,
+            interest: Interest::new(INTEREST_LEN),
+            welcomed: false,
+        })
+    }
+
+    /// Queues the message to send.
+    ///
+    /// Returns `false` if the queue is full or the writer has stopped.
+    fn send(&self, out: Outgoing) -> bool {
+        self.queue.try_send(out).is_ok()
+    }
+
+    /// Closes the connection at once, dropping queued messages.
+    fn close(self, chunks: &mut Chunks) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+        chunks.forget(self.interest);
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+}
+
+impl Server {
+    pub fn bind<A>(addr: A) -> io::Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves the world.
+    ///
+    /// Connections are read and written on their own threads, but all messages
+    /// are processed one by one on the current thread, so the world is
+    /// never accessed concurrently. A world error while serving a request is
+    /// logged and affects only that request. It returns an error only if
+    /// the kit archive can't be read.
+    pub fn run(self, world: &World) -> Result<(), world::Error> {
+        let (kit, archive) = world.kit_archive()?;
+        let archive: Arc<[u8]> = archive.into();
+        let welcome = ServerMessage::Welcome {
+            kit: kit.name.clone(),
+            hash: kit.hash,
+        };
+
+        let (events, receiver) = mpsc::channel();
+        let listener = self.listener;
+        thread::spawn(move || accept(listener, events));
+
+        // Chunks are kept in memory while clients need them, changes are saved at once
+        let mut chunks = Chunks::new();
+        let mut clients: Map<ClientId, Client> = Map::default();
+        for event in receiver {
+            let (id, message) = match event {
+                Event::Connected { id, stream } => match Client::new(id, stream) {
+                    Ok(client) => {
+                        log::info!("client {id} connected");
+                        clients.insert(id, client);
+                        continue;
+                    }
+                    Err(err) => {
+                        log::warn!("client {id}: {err}");
+                        continue;
+                    }
+                },
+                Event::Message { id, message } => (id, message),
+                Event::Invalid { id, err } => {
+                    log::warn!("client {id}: {err}");
+                    disconnect(&mut clients, &mut chunks, id, Reason::Protocol);
+                    continue;
+                }
+                Event::Closed { id } => {
+                    if let Some(client) = clients.remove(&id) {
+                        log::info!("client {id} closed the connection");
+                        client.close(&mut chunks);
+                    }
+
+                    continue;
+                }
+            };
+
+            let client = match clients.get_mut(&id) {
+                Some(client) => client,
+                None => continue,
+            };
+
+            let sent = match message {
+                ClientMessage::Hello { version } if !client.welcomed => {
+                    if version != net::VERSION {
+                        log::info!("client {id} has protocol version {version}");
+                        disconnect(&mut clients, &mut chunks, id, Reason::Version);
+                        continue;
+                    }
+
+                    client.welcomed = true;
+                    client.send(Outgoing::Message(welcome.clone()))
+                }
+                ClientMessage::Disconnect(reason) => {
+                    log::info!("client {id} disconnected: {reason}");
+                    if let Some(client) = clients.remove(&id) {
+                        client.close(&mut chunks);
+                    }
+
+                    continue;
+                }
+                _ if !client.welcomed => {
+                    disconnect(&mut clients, &mut chunks, id, Reason::Protocol);
+                    continue;
+                }
+                ClientMessage::Hello { .. } => {
+                    disconnect(&mut clients, &mut chunks, id, Reason::Protocol);
+                    continue;
+                }
+                ClientMessage::RequestKit => client.send(Outgoing::Kit(Arc::clone(&archive))),
+                ClientMessage::RequestChunk(point) => {
+                    let load = |point| world.load_chunk(point);
+                    let message = match chunks.get(&mut client.interest, point, load) {
+                        Ok(chunk) => ServerMessage::Chunk {
+                            point,
+                            data: chunk.map(|chunk| {
+                                let mut data = vec![];
+                                chunk.encode(Encoding::RunLength, &mut data);
+                                data
+                            }),
+                        },
+                        Err(err) => {
+                            log::error!("failed to load chunk {point:?}: {err}");
+                            ServerMessage::ChunkError { point }
+                        }
+                    };
+
+                    client.send(Outgoing::Message(message))
+                }
+                ClientMessage::SetBlock { point, block } => {
+                    if usize::from(block) > world.meta.blocks.len() {
+                        log::warn!("client {id} sets unknown block {block}");
+                        disconnect(&mut clients, &mut chunks, id, Reason::Protocol);
+                        continue;
+                    }
+
+                    let chunk_point = point.chunk_point();
+                    let load = |point| world.load_chunk(point);
+                    let chunk = match chunks.get_or_create(&mut client.interest, chunk_point, load)
+                    {
+                        Ok(chunk) => chunk,
+                        Err(err) => {
+                            log::error!("failed to load chunk {chunk_point:?}: {err}");
+                            continue;
+                        }
+                    };
+
+                    chunk[point.block_point()] = block;
+                    if let Err(err) = world.save_chunk(chunk_point, chunk) {
+                        log::error!("failed to save chunk {chunk_point:?}: {err}");
+                    }
+
+                    let changed = ServerMessage::BlockChanged { point, block };
+                    let overflowed: Vec<_> = clients
+                        .iter()
+                        .filter(|(_, client)| client.welcomed)
+                        .filter(|(_, client)| !client.send(Outgoing::Message(changed.clone())))
+                        .map(|(&id, _)| id)
+                        .collect();
+
+                    for id in overflowed {
+                        drop_slow(&mut clients, &mut chunks, id);
+                    }
+
+                    continue;
+                }
+            };
+
+            if !sent {
+                drop_slow(&mut clients, &mut chunks, id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn accept(listener: TcpListener, events: Sender<Event>) {
+    let mut next_id: ClientId = 0;
+    for stream in listener.incoming() {
+        let stream = match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+            Ok(streams) => streams,
+            Err(err) => {
+                log::warn!("failed to accept a connection: {err}");
+                continue;
+            }
+        };
+
+        let (reader, writer) = stream;
+        let id = next_id;
+        next_id = next_id.wrapping_add(1);
+        if events
+            .send(Event::Connected { id, stream: writer })
+            .is_err()
+        {
+            return;
+        }
+
+        let events = events.clone();
+        thread::spawn(move || read(id, reader, events));
+    }
+}
+
+fn read(id: ClientId, stream: TcpStream, events: Sender<Event>) {
+    loop {
+        let event = match net::read(&stream) {
+            Ok(message) => Event::Message { id, message },
+            Err(net::Error::Io(_)) => Event::Closed { id },
+            Err(err) => Event::Invalid { id, err },
+        };
+
+        let last = !matches!(event, Event::Message { .. });
+        if events.send(event).is_err() || last {
+            return;
+        }
+    }
+}
+
+/// Writes queued messages until the queue is closed, then shuts the `stream` down.
+fn write(id: ClientId, stream: TcpStream, queue: Receiver<Outgoing>) {
+    for out in queue {
+        let res = match out {
+            Outgoing::Message(message) => net::write(&stream, &message),
+            Outgoing::Kit(archive) => {
+                let size = u32::try_from(archive.len()).expect("archive size");
+                archive.chunks(net::KIT_PART_LEN).try_for_each(|part| {
+                    let message = ServerMessage::KitPart {
+                        size,
+                        data: part.to_vec(),
+                    };
+
+                    net::write(&stream, &message)
+                })
+            }
+        };
+
+        if let Err(err) = res {
+            log::warn!("client {id}: {err}");
+            break;
+        }
+    }
+
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Removes the client and lets its writer send the disconnect message last.
+fn disconnect(
+    clients: &mut Map<ClientId, Client>,
+    chunks: &mut Chunks,
+    id: ClientId,
+    reason: Reason,
+) {
+    if let Some(client) = clients.remove(&id) {
+        log::info!("client {id} disconnected: {reason}");
+        let message = ServerMessage::Disconnect(reason);
+        if client.send(Outgoing::Message(message)) {
+            chunks.forget(client.interest);
+        } else {
+            client.close(chunks);
+        }
+    }
+}
+
+/// Drops the client whose queue has overflowed.
+fn drop_slow(clients: &mut Map<ClientId, Client>, chunks: &mut Chunks, id: ClientId) {
+    if let Some(client) = clients.remove(&id) {
+        log::warn!("client {id} doesn't keep up with messages, dropped");
+        client.close(chunks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::world::{BlockMeta, Chunk, KitMeta, Meta, EMPTY},
+        base::{
+            kit::Hash,
+            point::{BlockPoint, ChunkPoint, WorldPoint},
+        },
+        std::{env, fs, path::PathBuf, process, time::Duration},
+    };
+
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn connect(addr: SocketAddr) -> TcpStream {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        stream
+    }
+
+    fn send(mut stream: &TcpStream, message: ClientMessage) {
+        net::write(&mut stream, &message).unwrap();
+    }
+
+    fn recv(stream: &TcpStream) -> ServerMessage {
+        net::read(stream).unwrap()
+    }
+
+    fn encode(chunk: &Chunk) -> Vec<u8> {
+        let mut data = vec![];
+        chunk.encode(Encoding::RunLength, &mut data);
+        data
+    }
+
+    fn hello(stream: &TcpStream) -> ServerMessage {
+        send(
+            stream,
+            ClientMessage::Hello {
+                version: net::VERSION,
+            },
+        );
+
+        recv(stream)
+    }
+
+    #[test]
+    fn loopback() {
+        let dir = TempDir(env::temp_dir().join(format!("germina-net-{}", process::id())));
+        // An archive of several parts
+        let archive: Vec<_> = (0..net::KIT_PART_LEN * 3 / 2).map(|i| i as u8).collect();
+        let hash = Hash::new(&archive);
+        let meta = Meta {
+            kits: vec![KitMeta {
+                name: "base".into(),
+                hash,
+            }],
+            blocks: vec![BlockMeta {
+                kit: "base".into(),
+                tile: "stone".into(),
+                index: 0,
+            }],
+        };
+
+        fs::create_dir_all(dir.0.join("test/regions")).unwrap();
+        fs::create_dir_all(dir.0.join("test/kits")).unwrap();
+        fs::write(dir.0.join("test/kits/base.kit"), &archive).unwrap();
+        fs::write(
+            dir.0.join("test/world.json"),
+            json::to_string(&meta).unwrap(),
+        )
+        .unwrap();
+
+        let origin = ChunkPoint::new(0, 0, 0).unwrap();
+        let mut spawn = Chunk::new(EMPTY);
+        spawn[BlockPoint::new(0, 0, 0).unwrap()] = 1;
+        World::open(&dir.0, "test")
+            .ok()
+            .unwrap()
+            .save_chunk(origin, &spawn)
+            .ok()
+            .unwrap();
+
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let path = dir.0.clone();
+        thread::spawn(move || {
+            let world = World::open(&path, "test").ok().unwrap();
+            let _ = server.run(&world);
+        });
+
+        // Incompatible version
+        let stream = connect(addr);
+        send(&stream, ClientMessage::Hello { version: 0 });
+        assert_eq!(recv(&stream), ServerMessage::Disconnect(Reason::Version));
+
+        // Request before the handshake
+        let stream = connect(addr);
+        send(&stream, ClientMessage::RequestChunk(origin));
+        assert_eq!(recv(&stream), ServerMessage::Disconnect(Reason::Protocol));
+
+        let a = connect(addr);
+        let b = connect(addr);
+        for stream in [&a, &b] {
+            assert_eq!(
+                hello(stream),
+                ServerMessage::Welcome {
+                    kit: "base".into(),
+                    hash,
+                },
+            );
+        }
+
+        send(&a, ClientMessage::RequestKit);
+        let mut downloaded = vec![];
+        while downloaded.len() < archive.len() {
+            match recv(&a) {
+                ServerMessage::KitPart { size, data } => {
+                    assert_eq!(size as usize, archive.len());
+                    downloaded.extend(data);
+                }
+                message => panic!("unexpected message {message:?}"),
+            }
+        }
+
+        assert!(downloaded == archive);
+
+        send(&a, ClientMessage::RequestChunk(origin));
+        match recv(&a) {
+            ServerMessage::Chunk {
+                point,
+                data: Some(data),
+            } => {
+                assert_eq!(point, origin);
+                assert_eq!(data, encode(&spawn));
+            }
+            message => panic!("unexpected message {message:?}"),
+        }
+
+        let missing = ChunkPoint::new(5, 0, 0).unwrap();
+        send(&a, ClientMessage::RequestChunk(missing));
+        assert_eq!(
+            recv(&a),
+            ServerMessage::Chunk {
+                point: missing,
+                data: None,
+            },
+        );
+
+        // A broken region fails only the request
+        fs::write(dir.0.join("test/regions/1_0_0.region"), b"broken").unwrap();
+        let broken = ChunkPoint::new(8, 0, 0).unwrap();
+        send(&a, ClientMessage::RequestChunk(broken));
+        assert_eq!(recv(&a), ServerMessage::ChunkError { point: broken });
+
+        // Block changes are broadcasted to all clients
+        let point = WorldPoint::from_absolute(1, 2, 3).unwrap();
+        send(&a, ClientMessage::SetBlock { point, block: 1 });
+        for stream in [&a, &b] {
+            assert_eq!(
+                recv(stream),
+                ServerMessage::BlockChanged { point, block: 1 },
+            );
+        }
+
+        send(&b, ClientMessage::RequestChunk(origin));
+        spawn[point.block_point()] = 1;
+        match recv(&b) {
+            ServerMessage::Chunk {
+                data: Some(data), ..
+            } => assert_eq!(data, encode(&spawn)),
+            message => panic!("unexpected message {message:?}"),
+        }
+
+        send(&b, ClientMessage::SetBlock { point, block: 2 });
+        assert_eq!(recv(&b), ServerMessage::Disconnect(Reason::Protocol));
+
+        send(&a, ClientMessage::Disconnect(Reason::Quit));
+        assert!(matches!(
+            net::read::<_, ServerMessage>(&a),
+            Err(net::Error::Io(_))
+        ));
+    }
+}
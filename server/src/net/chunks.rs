@@ -0,0 +1,203 @@
+use {
+    crate::world::{Chunk, EMPTY},
+    base::{cluster::Cluster, point::ChunkPoint},
+    fxhash::FxHashMap as Map,
+    std::collections::VecDeque,
+};
+
+/// Chunks recently requested by a client, the oldest first.
+pub struct Interest {
+    recent: VecDeque<ChunkPoint>,
+    len: usize,
+}
+
+impl Interest {
+    /// Creates an interest in at most `len` chunks.
+    pub fn new(len: usize) -> Self {
+        assert!(len > 0);
+
+        Self {
+            recent: VecDeque::with_capacity(len),
+            len,
+        }
+    }
+}
+
+/// Chunks loaded for connected clients.
+///
+/// A chunk stays in memory while some client's `Interest` contains it
+/// and it's dropped as soon as no one is interested in it anymore.
+pub struct Chunks {
+    cluster: Cluster<u16>,
+    /// The number of clients interested in each chunk
+    interested: Map<ChunkPoint, u32>,
+}
+
+impl Chunks {
+    pub fn new() -> Self {
+        Self {
+            cluster: Cluster::new(),
+            interested: Map::default(),
+        }
+    }
+
+    /// Returns the chunk from memory or loads it by the `load` function.
+    /// The chunk is added to the `interest`.
+    pub fn get<L, E>(
+        &mut self,
+        interest: &mut Interest,
+        point: ChunkPoint,
+        load: L,
+    ) -> Result<Option<&Chunk>, E>
+    where
+        L: FnOnce(ChunkPoint) -> Result<Option<Chunk>, E>,
+    {
+        self.touch(interest, point);
+        self.fetch(point, load)?;
+        Ok(self.cluster.get(point))
+    }
+
+    /// Same as [`get`](Self::get), but creates an empty chunk if it doesn't exist.
+    pub fn get_or_create<L, E>(
+        &mut self,
+        interest: &mut Interest,
+        point: ChunkPoint,
+        load: L,
+    ) -> Result<&mut Chunk, E>
+    where
+        L: FnOnce(ChunkPoint) -> Result<Option<Chunk>, E>,
+    {
+        self.touch(interest, point);
+        let chunk = match self.fetch(point, load)? {
+            true => self.cluster.get_mut(point).expect("loaded chunk"),
+            false => self.cluster.get_or_create(point, EMPTY),
+        };
+
+        Ok(chunk)
+    }
+
+    /// Removes the `interest` of a disconnected client.
+    pub fn forget(&mut self, interest: Interest) {
+        for point in interest.recent {
+            self.release(point);
+        }
+    }
+
+    /// Loads the chunk if it's not in memory. Returns whether the chunk exists.
+    fn fetch<L, E>(&mut self, point: ChunkPoint, load: L) -> Result<bool, E>
+    where
+        L: FnOnce(ChunkPoint) -> Result<Option<Chunk>, E>,
+    {
+        if self.cluster.contains(point) {
+            return Ok(true);
+        }
+
+        match load(point)? {
+            Some(chunk) => {
+                self.cluster.insert(point, chunk);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn touch(&mut self, interest: &mut Interest, point: ChunkPoint) {
+        let recent = &mut interest.recent;
+        if let Some(n) = recent.iter().position(|&p| p == point) {
+            recent.remove(n);
+            recent.push_back(point);
+            return;
+        }
+
+        recent.push_back(point);
+        *self.interested.entry(point).or_default() += 1;
+        if recent.len() > interest.len {
+            let oldest = recent.pop_front().expect("oldest point");
+            self.release(oldest);
+        }
+    }
+
+    fn release(&mut self, point: ChunkPoint) {
+        let count = self.interested.get_mut(&point).expect("interested");
+        *count -= 1;
+        if *count == 0 {
+            self.interested.remove(&point);
+            self.cluster.remove(point);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, base::point::BlockPoint, std::convert::Infallible};
+
+    fn point(x: i8) -> ChunkPoint {
+        ChunkPoint::new(x, 0, 0).unwrap()
+    }
+
+    fn load(point: ChunkPoint) -> Result<Option<Chunk>, Infallible> {
+        let (x, _, _) = point.into();
+        Ok((x >= 0).then(|| Chunk::new(x as u16)))
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let mut chunks = Chunks::new();
+        let mut interest = Interest::new(2);
+        for x in 0..3 {
+            chunks.get(&mut interest, point(x), load).unwrap();
+        }
+
+        assert_eq!(chunks.cluster.len(), 2);
+        assert!(!chunks.cluster.contains(point(0)));
+
+        // A requested chunk becomes the most recent one
+        chunks.get(&mut interest, point(1), load).unwrap();
+        chunks.get(&mut interest, point(3), load).unwrap();
+        assert!(chunks.cluster.contains(point(1)));
+        assert!(!chunks.cluster.contains(point(2)));
+    }
+
+    #[test]
+    fn shared() {
+        let mut chunks = Chunks::new();
+        let mut a = Interest::new(2);
+        let mut b = Interest::new(2);
+        chunks.get(&mut a, point(0), load).unwrap();
+        chunks.get(&mut b, point(0), load).unwrap();
+
+        // The chunk is still interesting for the client b
+        chunks.get(&mut a, point(1), load).unwrap();
+        chunks.get(&mut a, point(2), load).unwrap();
+        assert!(chunks.cluster.contains(point(0)));
+
+        chunks.forget(b);
+        assert!(!chunks.cluster.contains(point(0)));
+
+        chunks.forget(a);
+        assert_eq!(chunks.cluster.len(), 0);
+        assert!(chunks.interested.is_empty());
+    }
+
+    #[test]
+    fn create() {
+        let origin = BlockPoint::new(0, 0, 0).unwrap();
+        let mut chunks = Chunks::new();
+        let mut interest = Interest::new(4);
+        assert!(chunks
+            .get(&mut interest, point(-1), load)
+            .unwrap()
+            .is_none());
+
+        let chunk = chunks
+            .get_or_create(&mut interest, point(-1), load)
+            .unwrap();
+        assert_eq!(chunk[origin], EMPTY);
+
+        let chunk = chunks.get_or_create(&mut interest, point(5), load).unwrap();
+        assert_eq!(chunk[origin], 5);
+
+        chunks.forget(interest);
+        assert_eq!(chunks.cluster.len(), 0);
+    }
+}
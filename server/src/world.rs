@@ -0,0 +1,401 @@
+mod region;
+
+use {
+    self::region::{Region, RegionPoint},
+    crate::{
+        error::{IoError, StyledJson},
+        load::KitSource,
+    },
+    base::{
+        chunk::{
+            codec::{DecodeError, Encoding},
+            ChunkData,
+        },
+        kit::{self, Hash, JsonError, Key},
+        point::{BlockPoint, ChunkPoint},
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt, fs, io,
+        path::{Path, PathBuf},
+    },
+};
+
+pub type Chunk = ChunkData<u16>;
+
+/// The block id of an empty place.
+///
+/// Any other id `n` refers to the `n - 1` entry of the world's block table.
+pub const EMPTY: u16 = 0;
+
+const META_FILENAME: &str = "world.json";
+const REGIONS_DIRNAME: &str = "regions";
+const KITS_DIRNAME: &str = "kits";
+const KIT_EXTENSION: &str = "kit";
+const REGION_EXTENSION: &str = "region";
+
+pub struct World {
+    pub name: Key,
+    pub meta: Meta,
+    path: PathBuf,
+}
+
+impl World {
+    /// Makes a new world in the `dir` directory from the `kit`.
+    pub fn make(dir: &Path, name: &str, kit: &KitSource) -> Result<Self, Error> {
+        let name: Key = name.parse().map_err(|_| Error::InvalidName)?;
+        let path = dir.join(name.get());
+        if path.exists() {
+            return Err(Error::AlreadyExists);
+        }
+
+        let mut meta = Meta {
+            kits: vec![KitMeta {
+                name: kit.name.to_string(),
+                hash: kit.hash,
+            }],
+            blocks: vec![],
+        };
+
+        let mut spawn = Chunk::new(EMPTY);
+        let mut cursor = 0;
+        for (key, tile) in kit.model.sorted_tiles() {
+            let first_id = meta.blocks.len() + 1;
+            for index in 0..tile.blocks().len() {
+                meta.blocks.push(BlockMeta {
+                    kit: kit.name.to_string(),
+                    tile: key.to_string(),
+                    index: u16::try_from(index).expect("cast"),
+                });
+            }
+
+            // Place tiles in a row along the X axis with a gap between them
+            let (width, height, depth) = tile.size();
+            for z in 0..depth {
+                for y in 0..height {
+                    for x in 0..width {
+                        let point = match block_point(cursor + x, y, z) {
+                            Some(point) => point,
+                            None => continue,
+                        };
+
+                        if let Some(index) = tile.get((x, y, z)) {
+                            let id = first_id + usize::from(index);
+                            spawn[point] = u16::try_from(id).map_err(|_| Error::TooManyBlocks)?;
+                        }
+                    }
+                }
+            }
+
+            cursor += width + 1;
+        }
+
+        let regions_path = path.join(REGIONS_DIRNAME);
+        fs::create_dir_all(&regions_path).map_err(|err| IoError {
+            err,
+            path: Some(regions_path),
+        })?;
+
+        let world = Self { name, meta, path };
+        world.save_meta()?;
+
+        let kit_path = world.kit_path(kit.name.get());
+        fs::create_dir_all(kit_path.parent().expect("parent"))
+            .and_then(|_| fs::write(&kit_path, &kit.archive))
+            .map_err(|err| IoError {
+                err,
+                path: Some(kit_path),
+            })?;
+
+        let empty = Chunk::new(EMPTY);
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let point = ChunkPoint::new(x, 0, z).expect("chunk point");
+                if (x, z) == (0, 0) {
+                    world.save_chunk(point, &spawn)?;
+                } else {
+                    world.save_chunk(point, &empty)?;
+                }
+            }
+        }
+
+        Ok(world)
+    }
+
+    /// Opens an existing world in the `dir` directory.
+    pub fn open(dir: &Path, name: &str) -> Result<Self, Error> {
+        let name: Key = name.parse().map_err(|_| Error::InvalidName)?;
+        let path = dir.join(name.get());
+        let meta_path = path.join(META_FILENAME);
+        let src = match fs::read_to_string(&meta_path) {
+            Ok(src) => src,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(Error::NotFound),
+            Err(err) => {
+                return Err(Error::Io(IoError {
+                    err,
+                    path: Some(meta_path),
+                }))
+            }
+        };
+
+        let meta = kit::from_json(&src, Some(META_FILENAME))?;
+
+        Ok(Self { name, meta, path })
+    }
+
+    /// Lists names of all worlds in the `dir` directory.
+    pub fn list(dir: &Path) -> Result<Vec<String>, Error> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(Error::Io(IoError {
+                    err,
+                    path: Some(dir.into()),
+                }))
+            }
+        };
+
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if !path.join(META_FILENAME).is_file() {
+                continue;
+            }
+
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+
+        names.sort_unstable();
+        Ok(names)
+    }
+
+    /// Reads the archive of the world's kit.
+    ///
+    /// The world keeps a copy of the kit it was made from,
+    /// so the archive is checked against the recorded hash.
+    pub fn kit_archive(&self) -> Result<(&KitMeta, Vec<u8>), Error> {
+        let kit = self.meta.kits.first().ok_or(Error::NoKits)?;
+        let path = self.kit_path(&kit.name);
+        let archive = fs::read(&path).map_err(|err| IoError {
+            err,
+            path: Some(path.clone()),
+        })?;
+
+        if Hash::new(&archive) != kit.hash {
+            return Err(Error::Corrupted(path));
+        }
+
+        Ok((kit, archive))
+    }
+
+    /// Lists points of all saved chunks.
+    pub fn chunks(&self) -> Result<Vec<ChunkPoint>, Error> {
+        let mut points = vec![];
+        for (region_point, path) in self.regions()? {
+            let region = Region::open(&path).map_err(|err| IoError {
+                err,
+                path: Some(path.clone()),
+            })?;
+
+            for index in region.indices() {
+                let point = region::chunk_point(region_point, index)
+                    .ok_or(Error::Corrupted(path.clone()))?;
+
+                points.push(point);
+            }
+        }
+
+        Ok(points)
+    }
+
+    pub fn load_chunk(&self, point: ChunkPoint) -> Result<Option<Chunk>, Error> {
+        let (region_point, index) = region::address(point);
+        let path = self.region_path(region_point);
+        let bytes = match Region::open(&path).and_then(|mut region| region.read(index)) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::Io(IoError {
+                    err,
+                    path: Some(path),
+                }))
+            }
+        };
+
+        match Chunk::decode(&bytes) {
+            Ok(chunk) => Ok(Some(chunk)),
+            Err(err) => Err(Error::Decode { err, path }),
+        }
+    }
+
+    pub fn save_chunk(&self, point: ChunkPoint, chunk: &Chunk) -> Result<(), Error> {
+        let mut bytes = Vec::with_capacity(64);
+        chunk.encode(Encoding::RunLength, &mut bytes);
+
+        let (region_point, index) = region::address(point);
+        let path = self.region_path(region_point);
+        Region::open_or_create(&path)
+            .and_then(|mut region| region.write(index, &bytes))
+            .map_err(|err| IoError {
+                err,
+                path: Some(path),
+            })?;
+
+        Ok(())
+    }
+
+    /// Compacts all region files of the world.
+    ///
+    /// Returns the number of freed bytes.
+    pub fn compact(&self) -> Result<u64, Error> {
+        let mut freed = 0;
+        for (_, path) in self.regions()? {
+            let compact = |mut region: Region| match region.free_space()? {
+                0 => Ok(0),
+                _ => region.compact(),
+            };
+
+            freed += Region::open(&path)
+                .and_then(compact)
+                .map_err(|err| IoError {
+                    err,
+                    path: Some(path),
+                })?;
+        }
+
+        Ok(freed)
+    }
+
+    fn regions(&self) -> Result<Vec<(RegionPoint, PathBuf)>, Error> {
+        let path = self.path.join(REGIONS_DIRNAME);
+        let mut regions = vec![];
+        for entry in fs::read_dir(&path)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != REGION_EXTENSION) {
+                continue;
+            }
+
+            match path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(parse_region_name)
+            {
+                Some(point) => regions.push((point, path)),
+                None => return Err(Error::Corrupted(path)),
+            }
+        }
+
+        regions.sort_unstable_by_key(|&(point, _)| point);
+        Ok(regions)
+    }
+
+    fn save_meta(&self) -> Result<(), Error> {
+        let src = json::to_string(&self.meta).expect("serialize");
+        let path = self.path.join(META_FILENAME);
+        fs::write(&path, src).map_err(|err| IoError {
+            err,
+            path: Some(path),
+        })?;
+
+        Ok(())
+    }
+
+    fn kit_path(&self, name: &str) -> PathBuf {
+        let mut path = self.path.join(KITS_DIRNAME);
+        path.push(name);
+        path.set_extension(KIT_EXTENSION);
+        path
+    }
+
+    fn region_path(&self, (x, y, z): RegionPoint) -> PathBuf {
+        let mut path = self.path.join(REGIONS_DIRNAME);
+        path.push(format!("{x}_{y}_{z}"));
+        path.set_extension(REGION_EXTENSION);
+        path
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Meta {
+    pub kits: Vec<KitMeta>,
+    pub blocks: Vec<BlockMeta>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct KitMeta {
+    pub name: String,
+    pub hash: Hash,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct BlockMeta {
+    pub kit: String,
+    pub tile: String,
+    pub index: u16,
+}
+
+fn block_point(x: u32, y: u32, z: u32) -> Option<BlockPoint> {
+    BlockPoint::new(x.try_into().ok()?, y.try_into().ok()?, z.try_into().ok()?)
+}
+
+fn parse_region_name(name: &str) -> Option<RegionPoint> {
+    let mut coords = name.split('_').map(str::parse);
+    let x = coords.next()?.ok()?;
+    let y = coords.next()?.ok()?;
+    let z = coords.next()?.ok()?;
+    if coords.next().is_some() {
+        return None;
+    }
+
+    Some((x, y, z))
+}
+
+pub enum Error {
+    InvalidName,
+    AlreadyExists,
+    NotFound,
+    TooManyBlocks,
+    NoKits,
+    Corrupted(PathBuf),
+    Decode { err: DecodeError, path: PathBuf },
+    Io(IoError),
+    Json(JsonError),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(IoError { err, path: None })
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<JsonError> for Error {
+    fn from(err: JsonError) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidName => write!(f, "invalid world name"),
+            Self::AlreadyExists => write!(f, "the world already exists"),
+            Self::NotFound => write!(f, "the world not found"),
+            Self::TooManyBlocks => write!(f, "too many blocks in the world"),
+            Self::NoKits => write!(f, "the world has no kits"),
+            Self::Corrupted(path) => write!(f, "corrupted file {}", path.display()),
+            Self::Decode { err, path } => write!(f, "in file {}: {err}", path.display()),
+            Self::Io(io) => write!(f, "{io}"),
+            Self::Json(json) => write!(f, "{}", StyledJson(json)),
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! Region files.
+//!
+//! A region stores up to `SIDE`³ chunks in one file. The file starts with a header
+//! followed by an offset table, one entry for each chunk of the region. An entry is
+//! a pair of the first sector of the chunk data and its length in bytes.
+//! The zero length means the chunk is absent. Chunk data is aligned to sectors,
+//! so a rewritten chunk stays in place while it fits its sectors.
+
+use {
+    base::point::ChunkPoint,
+    std::{
+        fs::{self, File, OpenOptions},
+        io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+/// The number of chunks along each axis of a region.
+pub const SIDE: i8 = 8;
+
+const N_CHUNKS: usize = (SIDE as usize).pow(3);
+const MAGIC: [u8; 4] = *b"GRGN";
+const VERSION: u8 = 1;
+const PREFIX_LEN: u64 = 8;
+const ENTRY_LEN: u64 = 8;
+const SECTOR: u64 = 256;
+const HEADER_SECTORS: u32 = {
+    let len = PREFIX_LEN + N_CHUNKS as u64 * ENTRY_LEN;
+    len.div_ceil(SECTOR) as u32
+};
+
+/// A point of a region in chunks divided by `SIDE`.
+pub type RegionPoint = (i8, i8, i8);
+
+/// Splits a chunk point to the region point and the chunk index in the region.
+pub fn address(point: ChunkPoint) -> (RegionPoint, usize) {
+    let (x, y, z) = point.into();
+    let region = (x.div_euclid(SIDE), y.div_euclid(SIDE), z.div_euclid(SIDE));
+    let (lx, ly, lz) = (
+        x.rem_euclid(SIDE) as usize,
+        y.rem_euclid(SIDE) as usize,
+        z.rem_euclid(SIDE) as usize,
+    );
+
+    let side = SIDE as usize;
+    (region, (lz * side + ly) * side + lx)
+}
+
+/// Joins the region point and the chunk index back to the chunk point.
+///
+/// Returns `None` if the point is not a valid chunk point.
+pub fn chunk_point((x, y, z): RegionPoint, index: usize) -> Option<ChunkPoint> {
+    let side = SIDE as usize;
+    let coord = |region: i8, local: usize| {
+        let local = local as i8;
+        region.checked_mul(SIDE)?.checked_add(local)
+    };
+
+    ChunkPoint::new(
+        coord(x, index % side)?,
+        coord(y, index / side % side)?,
+        coord(z, index / side / side)?,
+    )
+}
+
+#[derive(Clone, Copy, Default)]
+struct Entry {
+    sector: u32,
+    len: u32,
+}
+
+impl Entry {
+    fn is_empty(self) -> bool {
+        self.len == 0
+    }
+
+    fn sectors(self) -> u32 {
+        sectors(u64::from(self.len))
+    }
+}
+
+fn sectors(len: u64) -> u32 {
+    len.div_ceil(SECTOR) as u32
+}
+
+/// Returns sorted sector ranges of stored chunks as pairs of the first sector
+/// and the number of sectors.
+fn used_sectors(table: &[Entry]) -> Vec<(u32, u32)> {
+    let mut used: Vec<_> = table
+        .iter()
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| (entry.sector, entry.sectors()))
+        .collect();
+
+    used.sort_unstable();
+    used
+}
+
+pub struct Region {
+    file: File,
+    /// Whether the file is opened for writing.
+    writable: bool,
+    path: PathBuf,
+    table: Vec<Entry>,
+}
+
+impl Region {
+    /// Opens an existing region file.
+    ///
+    /// The file is opened read-only until the first write.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut prefix = [0; PREFIX_LEN as usize];
+        file.read_exact(&mut prefix)?;
+        if prefix[..4] != MAGIC || prefix[4] != VERSION {
+            return Err(invalid_data("wrong region header"));
+        }
+
+        let mut raw = vec![0; N_CHUNKS * ENTRY_LEN as usize];
+        file.read_exact(&mut raw)?;
+
+        let file_sectors = sectors(file.metadata()?.len());
+        let table = raw
+            .chunks_exact(ENTRY_LEN as usize)
+            .map(|raw| {
+                let entry = Entry {
+                    sector: u32::from_le_bytes(raw[..4].try_into().expect("u32")),
+                    len: u32::from_le_bytes(raw[4..].try_into().expect("u32")),
+                };
+
+                let end = u64::from(entry.sector) + u64::from(entry.sectors());
+                let in_bounds = entry.is_empty()
+                    || entry.sector >= HEADER_SECTORS && end <= u64::from(file_sectors);
+
+                if in_bounds {
+                    Ok(entry)
+                } else {
+                    Err(invalid_data("region entry out of bounds"))
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let overlaps = used_sectors(&table)
+            .windows(2)
+            .any(|pair| pair[0].0 + pair[0].1 > pair[1].0);
+
+        if overlaps {
+            return Err(invalid_data("region entries overlap"));
+        }
+
+        Ok(Self {
+            file,
+            writable: false,
+            path: path.to_owned(),
+            table,
+        })
+    }
+
+    /// Creates a new empty region file.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+
+        let mut region = Self {
+            file,
+            writable: true,
+            path: path.to_owned(),
+            table: vec![Entry::default(); N_CHUNKS],
+        };
+
+        region.write_header()?;
+        Ok(region)
+    }
+
+    /// Opens a region file or creates it if it doesn't exist.
+    pub fn open_or_create(path: &Path) -> io::Result<Self> {
+        match Self::open(path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Self::create(path),
+            res => res,
+        }
+    }
+
+    /// Returns indices of all stored chunks.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.table
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_empty())
+            .map(|(index, _)| index)
+    }
+
+    pub fn read(&mut self, index: usize) -> io::Result<Option<Vec<u8>>> {
+        let entry = self.table[index];
+        if entry.is_empty() {
+            return Ok(None);
+        }
+
+        let mut buf = vec![0; entry.len as usize];
+        self.file
+            .seek(SeekFrom::Start(u64::from(entry.sector) * SECTOR))?;
+        self.file.read_exact(&mut buf)?;
+        Ok(Some(buf))
+    }
+
+    /// Writes chunk data.
+    ///
+    /// If the data fits the sectors of the old one, it's overwritten in place.
+    /// Otherwise the first free gap or the end of the file is used.
+    pub fn write(&mut self, index: usize, bytes: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|_| invalid_data("too large chunk"))?;
+        if len == 0 {
+            return self.remove(index);
+        }
+
+        let old = self.table[index];
+        let needed = sectors(u64::from(len));
+        let sector = if !old.is_empty() && needed <= old.sectors() {
+            old.sector
+        } else {
+            self.table[index] = Entry::default();
+            self.find_free(needed)
+        };
+
+        let file = self.writable_file()?;
+        file.seek(SeekFrom::Start(u64::from(sector) * SECTOR))?;
+        file.write_all(bytes)?;
+
+        // Pad the last sector to keep the file aligned
+        let padding = u64::from(needed) * SECTOR - u64::from(len);
+        file.write_all(&vec![0; padding as usize])?;
+
+        self.table[index] = Entry { sector, len };
+        self.write_entry(index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> io::Result<()> {
+        self.table[index] = Entry::default();
+        self.write_entry(index)
+    }
+
+    /// Returns the number of bytes occupied by unused sectors.
+    pub fn free_space(&self) -> io::Result<u64> {
+        let used: u64 = self
+            .table
+            .iter()
+            .map(|entry| u64::from(entry.sectors()))
+            .sum();
+        let total = sectors(self.file.metadata()?.len());
+        Ok((u64::from(total) - u64::from(HEADER_SECTORS) - used) * SECTOR)
+    }
+
+    /// Rewrites the region without gaps between chunks.
+    ///
+    /// Returns the number of freed bytes.
+    pub fn compact(&mut self) -> io::Result<u64> {
+        let old_len = self.file.metadata()?.len();
+
+        let mut chunks = Vec::new();
+        for index in self.indices().collect::<Vec<_>>() {
+            let bytes = self.read(index)?.expect("chunk");
+            chunks.push((index, bytes));
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        if tmp_path.exists() {
+            fs::remove_file(&tmp_path)?;
+        }
+
+        let mut region = Self::create(&tmp_path)?;
+        for (index, bytes) in chunks {
+            region.write(index, &bytes)?;
+        }
+
+        region.file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        region.path = self.path.clone();
+        *self = region;
+
+        let new_len = self.file.metadata()?.len();
+        Ok(old_len.saturating_sub(new_len))
+    }
+
+    fn find_free(&self, needed: u32) -> u32 {
+        let mut start = HEADER_SECTORS;
+        for (sector, len) in used_sectors(&self.table) {
+            if sector.saturating_sub(start) >= needed {
+                break;
+            }
+
+            start = start.max(sector + len);
+        }
+
+        start
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let header_len = (u64::from(HEADER_SECTORS) * SECTOR) as usize;
+        let mut header = Vec::with_capacity(header_len);
+        header.extend(MAGIC);
+        header.push(VERSION);
+        header.resize(PREFIX_LEN as usize, 0);
+        for entry in &self.table {
+            header.extend(entry.sector.to_le_bytes());
+            header.extend(entry.len.to_le_bytes());
+        }
+
+        header.resize(header_len, 0);
+        let file = self.writable_file()?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)
+    }
+
+    fn write_entry(&mut self, index: usize) -> io::Result<()> {
+        let Entry { sector, len } = self.table[index];
+        let mut raw = [0; ENTRY_LEN as usize];
+        raw[..4].copy_from_slice(&sector.to_le_bytes());
+        raw[4..].copy_from_slice(&len.to_le_bytes());
+
+        let file = self.writable_file()?;
+        file.seek(SeekFrom::Start(PREFIX_LEN + index as u64 * ENTRY_LEN))?;
+        file.write_all(&raw)
+    }
+
+    /// Reopens the file for writing if it's opened read-only.
+    fn writable_file(&mut self) -> io::Result<&mut File> {
+        if !self.writable {
+            self.file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+            self.writable = true;
+        }
+
+        Ok(&mut self.file)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let mut path = std::env::temp_dir();
+            path.push(format!("germina-{}-{name}.region", std::process::id()));
+            let _ = fs::remove_file(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn file_len(path: &TempPath) -> u64 {
+        fs::metadata(&path.0).unwrap().len()
+    }
+
+    #[test]
+    fn address() {
+        for (x, y, z) in [(0, 0, 0), (7, 8, -1), (-8, -9, 127), (-127, 100, 3)] {
+            let point = ChunkPoint::new(x, y, z).unwrap();
+            let (region, index) = super::address(point);
+            assert!(index < N_CHUNKS);
+            assert_eq!(chunk_point(region, index), Some(point));
+        }
+
+        let point = ChunkPoint::new(-1, 8, 0).unwrap();
+        assert_eq!(super::address(point), ((-1, 1, 0), 7));
+
+        // The i8::MIN coordinate is not a valid chunk point
+        assert_eq!(chunk_point((-16, 0, 0), 0), None);
+    }
+
+    #[test]
+    fn read_write() {
+        let path = TempPath::new("read-write");
+        let mut region = Region::create(&path.0).unwrap();
+        region.write(0, b"first").unwrap();
+        region.write(N_CHUNKS - 1, &[7; 1000]).unwrap();
+        drop(region);
+
+        let mut region = Region::open(&path.0).unwrap();
+        assert_eq!(region.indices().collect::<Vec<_>>(), [0, N_CHUNKS - 1]);
+        assert_eq!(region.read(0).unwrap().as_deref(), Some(&b"first"[..]));
+        assert_eq!(region.read(N_CHUNKS - 1).unwrap(), Some(vec![7; 1000]));
+        assert_eq!(region.read(1).unwrap(), None);
+
+        region.remove(0).unwrap();
+        assert_eq!(region.read(0).unwrap(), None);
+    }
+
+    #[test]
+    fn overwrite_in_place() {
+        let path = TempPath::new("in-place");
+        let mut region = Region::create(&path.0).unwrap();
+        region.write(0, &[1; 200]).unwrap();
+        region.write(1, &[2; 200]).unwrap();
+        let len = file_len(&path);
+
+        region.write(0, &[3; 250]).unwrap();
+        assert_eq!(file_len(&path), len);
+        assert_eq!(region.read(0).unwrap(), Some(vec![3; 250]));
+        assert_eq!(region.read(1).unwrap(), Some(vec![2; 200]));
+    }
+
+    #[test]
+    fn reuse_free_space() {
+        let path = TempPath::new("reuse");
+        let mut region = Region::create(&path.0).unwrap();
+        region.write(0, &[1; 600]).unwrap();
+        region.write(1, &[2; 100]).unwrap();
+
+        // The chunk 0 grows and moves to the end, leaving a gap of 3 sectors
+        region.write(0, &[1; 1000]).unwrap();
+        assert_eq!(region.free_space().unwrap(), 3 * SECTOR);
+        let len = file_len(&path);
+
+        // The new chunk fits the gap
+        region.write(2, &[3; 500]).unwrap();
+        assert_eq!(file_len(&path), len);
+        assert_eq!(region.free_space().unwrap(), SECTOR);
+        assert_eq!(region.read(0).unwrap(), Some(vec![1; 1000]));
+        assert_eq!(region.read(1).unwrap(), Some(vec![2; 100]));
+        assert_eq!(region.read(2).unwrap(), Some(vec![3; 500]));
+    }
+
+    #[test]
+    fn compact() {
+        let path = TempPath::new("compact");
+        let mut region = Region::create(&path.0).unwrap();
+        for index in 0..10 {
+            region.write(index, &[index as u8; 300]).unwrap();
+        }
+
+        for index in (0..10).step_by(2) {
+            region.remove(index).unwrap();
+        }
+
+        let saved = region.compact().unwrap();
+        assert_eq!(saved, 5 * 2 * SECTOR);
+        assert_eq!(region.free_space().unwrap(), 0);
+        drop(region);
+
+        let mut region = Region::open(&path.0).unwrap();
+        for index in 0..10 {
+            let expected = (index % 2 == 1).then(|| vec![index as u8; 300]);
+            assert_eq!(region.read(index).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn overlapping() {
+        let path = TempPath::new("overlapping");
+        let mut region = Region::create(&path.0).unwrap();
+        region.write(0, &[1; 600]).unwrap();
+        region.write(1, &[2; 100]).unwrap();
+        drop(region);
+
+        // Point the second entry into the sectors of the first one
+        let mut file = OpenOptions::new().write(true).open(&path.0).unwrap();
+        file.seek(SeekFrom::Start(PREFIX_LEN + ENTRY_LEN)).unwrap();
+        file.write_all(&(HEADER_SECTORS + 1).to_le_bytes()).unwrap();
+        drop(file);
+
+        let err = Region::open(&path.0).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn corrupted() {
+        let path = TempPath::new("corrupted");
+        fs::write(&path.0, b"not a region").unwrap();
+        let err = Region::open(&path.0).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
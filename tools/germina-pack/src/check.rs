@@ -0,0 +1,368 @@
+use {
+    crate::{
+        error::{FileError as Error, StyledJson},
+        sprite::image_size,
+    },
+    base::kit::{
+        self,
+        shape::{self, Shape},
+        tile::{BlockPointer, Layout, Tile},
+        Asset, JsonError, Key, Kind,
+    },
+    serde::de::DeserializeOwned,
+    std::{
+        fmt,
+        fs::{self, File},
+        io::{self, ErrorKind, Read},
+        path::{Path, PathBuf},
+    },
+    zip::{result::ZipError, ZipArchive},
+};
+
+pub fn check(path: &Path) -> Result<Report, Error> {
+    use std::collections::BTreeMap;
+
+    let mut source = Source::open(path)?;
+    let mut report = Report::default();
+    let mut sprites = BTreeMap::<_, Vec<_>>::new();
+    let mut shapes = BTreeMap::<_, Vec<_>>::new();
+
+    for filename in source.filenames()? {
+        let kind = match Asset::parse_path(&filename) {
+            Some(Asset { kind, .. }) => kind,
+            None => continue,
+        };
+
+        let buf = source.read(&filename)?.ok_or(Error::NotFound)?;
+        let src = match String::from_utf8(buf) {
+            Ok(src) => src,
+            Err(_) => {
+                report.push(filename, None, Problem::NotUtf8);
+                continue;
+            }
+        };
+
+        match kind {
+            Kind::Tile => {
+                report.tiles += 1;
+                let tile: Tile = match parse(&src, &filename, &mut report) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+
+                for_each_block_key(&tile.layout, |key| {
+                    if !tile.blocks.contains_key(key) {
+                        report.push(
+                            filename.clone(),
+                            find_line(&src, key),
+                            Problem::UnknownBlock(key.clone()),
+                        );
+                    }
+                });
+
+                tile.builtin_shapes(|shape| {
+                    if let Err(id) = shape {
+                        report.push(
+                            filename.clone(),
+                            find_id_line(&src, id),
+                            Problem::UnknownShapeId(id),
+                        );
+                    }
+                });
+
+                tile.sprites(|key| {
+                    sprites
+                        .entry(key.clone())
+                        .or_default()
+                        .push((filename.clone(), find_line(&src, key)));
+                });
+
+                tile.shapes(|key| {
+                    shapes
+                        .entry(key.clone())
+                        .or_default()
+                        .push((filename.clone(), find_line(&src, key)));
+                });
+            }
+            Kind::Shape => {
+                let shape: Shape = match parse(&src, &filename, &mut report) {
+                    Some(shape) => shape,
+                    None => continue,
+                };
+
+                if let Err(err) = shape.validate() {
+                    report.push(filename, None, Problem::InvalidShape(err));
+                }
+            }
+        }
+    }
+
+    for (key, refs) in shapes {
+        let path = format!("shapes/{key}.json");
+        if source.read(&path)?.is_some() {
+            continue;
+        }
+
+        for (filename, line) in refs {
+            let problem = Problem::MissingShape {
+                key: key.clone(),
+                path: path.clone(),
+            };
+
+            report.push(filename, line, problem);
+        }
+    }
+
+    for (key, refs) in sprites {
+        let path = format!("sprites/tiles/{key}.png");
+        let err = match source.read(&path)? {
+            Some(buf) => match image_size(&buf) {
+                Ok(_) => continue,
+                Err(err) => Some(err),
+            },
+            None => None,
+        };
+
+        for (filename, line) in refs {
+            let problem = match &err {
+                Some(err) => Problem::InvalidSprite {
+                    key: key.clone(),
+                    path: path.clone(),
+                    err: err.clone(),
+                },
+                None => Problem::MissingSprite {
+                    key: key.clone(),
+                    path: path.clone(),
+                },
+            };
+
+            report.push(filename, line, problem);
+        }
+    }
+
+    Ok(report)
+}
+
+fn parse<T>(src: &str, filename: &str, report: &mut Report) -> Option<T>
+where
+    T: DeserializeOwned,
+{
+    match kit::from_json(src, Some(filename)) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            report.push(filename.to_owned(), None, Problem::Json(err));
+            None
+        }
+    }
+}
+
+fn for_each_block_key<F>(layout: &Layout, mut callback: F)
+where
+    F: FnMut(&Key),
+{
+    let mut ptr = |ptr: &BlockPointer| {
+        if let BlockPointer::Key(key) = ptr {
+            callback(key);
+        }
+    };
+
+    match layout {
+        Layout::D1(p) => ptr(p),
+        Layout::D2(v) => v.iter().for_each(ptr),
+        Layout::D3(v) => v.iter().flatten().for_each(ptr),
+    }
+}
+
+/// Finds the first line where the quoted `key` occurs.
+fn find_line(src: &str, key: &str) -> Option<usize> {
+    let single = format!("'{key}'");
+    let double = format!("\"{key}\"");
+    src.lines()
+        .position(|line| line.contains(&single) || line.contains(&double))
+        .map(|n| n + 1)
+}
+
+/// Finds the first line where the shape `id` is set.
+fn find_id_line(src: &str, id: u32) -> Option<usize> {
+    let id = id.to_string();
+    let sets_id = |line: &str| {
+        let line: String = line.split_whitespace().collect();
+        ["id:", "'id':", "\"id\":"].iter().any(|field| {
+            let pattern = format!("{field}{id}");
+            line.match_indices(&pattern).any(|(n, _)| {
+                let rest = &line[n + pattern.len()..];
+                !rest.starts_with(|c: char| c.is_ascii_digit())
+            })
+        })
+    };
+
+    src.lines().position(sets_id).map(|n| n + 1)
+}
+
+enum Source {
+    Dir(PathBuf),
+    Kit(ZipArchive<File>),
+}
+
+impl Source {
+    fn open(path: &Path) -> Result<Self, Error> {
+        if path.is_dir() {
+            Ok(Self::Dir(path.to_owned()))
+        } else {
+            let file = File::open(path)?;
+            Ok(Self::Kit(ZipArchive::new(file)?))
+        }
+    }
+
+    fn filenames(&self) -> Result<Vec<String>, Error> {
+        fn visit_dirs(path: &Path, prefix: &str, names: &mut Vec<String>) -> io::Result<()> {
+            for res in fs::read_dir(path)? {
+                let entry = res?;
+                let path = entry.path();
+                let name = entry.file_name();
+                let name = match name.to_str() {
+                    Some(name) => format!("{prefix}{name}"),
+                    None => continue,
+                };
+
+                if path.is_dir() {
+                    visit_dirs(&path, &format!("{name}/"), names)?;
+                } else if path.is_file() {
+                    names.push(name);
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut names = match self {
+            Self::Dir(path) => {
+                let mut names = Vec::new();
+                visit_dirs(path, "", &mut names)?;
+                names
+            }
+            Self::Kit(arch) => arch.file_names().map(str::to_owned).collect(),
+        };
+
+        names.sort_unstable();
+        Ok(names)
+    }
+
+    fn read(&mut self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut buf = Vec::with_capacity(128);
+        match self {
+            Self::Dir(path) => match File::open(path.join(name)) {
+                Ok(mut file) => file.read_to_end(&mut buf)?,
+                Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(err) => return Err(err.into()),
+            },
+            Self::Kit(arch) => match arch.by_name(name) {
+                Ok(mut file) => file.read_to_end(&mut buf)?,
+                Err(ZipError::FileNotFound) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            },
+        };
+
+        Ok(Some(buf))
+    }
+}
+
+#[derive(Default)]
+pub struct Report {
+    tiles: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    fn push(&mut self, filename: String, line: Option<usize>, problem: Problem) {
+        self.diagnostics.push(Diagnostic {
+            filename,
+            line,
+            problem,
+        });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crossterm::style::Stylize;
+
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{diagnostic}")?;
+        }
+
+        let tiles = self.tiles;
+        match self.diagnostics.len() {
+            0 => write!(f, "{tiles} tiles checked, no problems found"),
+            1 => write!(f, "{tiles} tiles checked, {} found", "1 problem".bold()),
+            n => write!(
+                f,
+                "{tiles} tiles checked, {} found",
+                format!("{n} problems").bold()
+            ),
+        }
+    }
+}
+
+struct Diagnostic {
+    filename: String,
+    line: Option<usize>,
+    problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crossterm::style::Stylize;
+
+        write!(f, "{} ", "error:".red().bold())?;
+        if let Problem::Json(json) = &self.problem {
+            return writeln!(f, "{}", StyledJson(json));
+        }
+
+        write!(f, "in file {}", self.filename.as_str().bold())?;
+        if let Some(line) = self.line {
+            write!(f, " at line {line}")?;
+        }
+
+        writeln!(f, ":")?;
+        writeln!(f, "{}", self.problem)
+    }
+}
+
+enum Problem {
+    Json(JsonError),
+    NotUtf8,
+    UnknownBlock(Key),
+    UnknownShapeId(u32),
+    MissingSprite { key: Key, path: String },
+    InvalidSprite { key: Key, path: String, err: String },
+    InvalidShape(shape::Error),
+    MissingShape { key: Key, path: String },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Json(json) => write!(f, "{}", StyledJson(json)),
+            Self::NotUtf8 => write!(f, "the file is not valid UTF-8"),
+            Self::UnknownBlock(key) => {
+                write!(f, "the block {key} is not defined in the tile's blocks")
+            }
+            Self::UnknownShapeId(id) => write!(f, "the shape id {id} is unknown"),
+            Self::MissingSprite { key, path } => {
+                write!(f, "the sprite {key} not found, expected file {path}")
+            }
+            Self::InvalidSprite { key, path, err } => {
+                write!(f, "the sprite {key} in {path} is invalid: {err}")
+            }
+            Self::InvalidShape(err) => write!(f, "the shape is invalid: {err}"),
+            Self::MissingShape { key, path } => {
+                write!(f, "the shape {key} not found, expected file {path}")
+            }
+        }
+    }
+}
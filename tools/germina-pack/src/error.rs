@@ -1,11 +1,18 @@
 use {
     crate::{info, pack},
-    std::path::PathBuf,
+    base::kit::JsonError,
+    std::{
+        fmt,
+        io::{self, ErrorKind},
+        path::PathBuf,
+    },
+    zip::result::ZipError,
 };
 
 pub enum Error {
     Pack { err: pack::Error, path: PathBuf },
     Info { err: info::Error, path: PathBuf },
+    Check { err: FileError, path: PathBuf },
 }
 
 impl Error {
@@ -28,8 +35,83 @@ impl Error {
                 );
                 eprint!("{err}");
             }
+            Self::Check { err, path } => {
+                eprintln!(
+                    "in file {}",
+                    StyledContent::new(ContentStyle::default(), path.display()).bold()
+                );
+                eprint!("{err}");
+            }
         }
 
         std::process::exit(1)
     }
 }
+
+/// An error of reading files of a kit or its source.
+pub enum FileError {
+    Arch(&'static str),
+    NotFound,
+    PermissionDenied,
+    Other,
+}
+
+impl From<ZipError> for FileError {
+    fn from(err: ZipError) -> Self {
+        match err {
+            ZipError::Io(err) => err.into(),
+            ZipError::InvalidArchive(arch) | ZipError::UnsupportedArchive(arch) => Self::Arch(arch),
+            ZipError::FileNotFound => Self::NotFound,
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::NotFound => Self::NotFound,
+            ErrorKind::PermissionDenied => Self::PermissionDenied,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Arch(arch) => write!(f, "archive error: {arch}"),
+            Self::NotFound => write!(f, "file not found"),
+            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::Other => write!(f, "unknown file handling error"),
+        }
+    }
+}
+
+/// A JSON error with a styled filename and error mark.
+pub struct StyledJson<'a>(pub &'a JsonError);
+
+impl fmt::Display for StyledJson<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crossterm::style::Stylize;
+
+        let json = self.0;
+        write!(f, "while parsing")?;
+        if let Some(filename) = json.filename() {
+            write!(f, " {}", filename.bold())?;
+        }
+
+        match json.location() {
+            Some((line, column)) => {
+                writeln!(f, " at line {line}:")?;
+                for line in json.excerpt() {
+                    writeln!(f, "{line}")?;
+                }
+
+                writeln!(f, "{:>column$}{}", "", '^'.yellow().bold())?;
+            }
+            None => writeln!(f, ":")?,
+        }
+
+        write!(f, "{}", json.message())
+    }
+}
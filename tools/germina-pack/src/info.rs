@@ -1,44 +1,217 @@
-use std::{
-    fmt, fs,
-    io::{self, ErrorKind},
-    path::Path,
+use {
+    crate::{
+        error::{FileError, StyledJson},
+        sprite::image_size,
+    },
+    base::kit::{self, tile::Tile, Asset, JsonError, Kind},
+    serde::Serialize,
+    std::{
+        fmt,
+        fs::File,
+        io::{self, Read},
+        path::Path,
+    },
+    zip::{result::ZipError, ZipArchive},
 };
 
 pub fn info(path: &Path) -> Result<Info, Error> {
-    let _todo = fs::read_to_string(path)?;
-    Ok(Info {})
+    use std::{collections::BTreeSet, ffi::OsStr};
+
+    let name = path
+        .file_name()
+        .and_then(OsStr::to_str)
+        .and_then(|name| name.rsplit_once('.'))
+        .ok_or(Error::UndefinedName)?
+        .0
+        .to_owned();
+
+    let file = File::open(path)?;
+    let mut arch = ZipArchive::new(file)?;
+    let mut content = String::with_capacity(128);
+    let mut tiles = Vec::new();
+    let mut shapes = Vec::new();
+    let mut unknown = Vec::new();
+    let mut sprite_keys = BTreeSet::new();
+    let mut size = 0;
+    let mut compressed_size = 0;
+
+    for i in 0..arch.len() {
+        let mut file = arch.by_index(i)?;
+        size += file.size();
+        compressed_size += file.compressed_size();
+        if !file.is_file() {
+            continue;
+        }
+
+        let filename = file.name();
+        let Asset { name, kind } = match Asset::parse_path(filename) {
+            Some(asset) => asset,
+            None => {
+                unknown.push(filename.to_owned());
+                continue;
+            }
+        };
+
+        match kind {
+            Kind::Tile => {
+                content.clear();
+                file.read_to_string(&mut content)?;
+                let tile: Tile = kit::from_json(&content, Some(file.name()))?;
+
+                tile.sprites(|key| {
+                    sprite_keys.insert(key.to_string());
+                });
+
+                let mut blocks: Vec<_> = tile.blocks.keys().map(ToString::to_string).collect();
+                blocks.sort_unstable();
+                tiles.push(TileInfo {
+                    name: name.to_string(),
+                    blocks,
+                });
+            }
+            Kind::Shape => shapes.push(name.to_string()),
+        }
+    }
+
+    tiles.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    shapes.sort_unstable();
+
+    let mut sprites = Vec::with_capacity(sprite_keys.len());
+    for key in sprite_keys {
+        let path = format!("sprites/tiles/{key}.png");
+        let size = match unknown.iter().position(|filename| *filename == path) {
+            Some(n) => {
+                unknown.swap_remove(n);
+                let mut file = arch.by_name(&path)?;
+                let mut buf = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut buf)?;
+                Some(image_size(&buf).map_err(|err| Error::Image { err, path })?)
+            }
+            None => None,
+        };
+
+        sprites.push(SpriteInfo { name: key, size });
+    }
+
+    unknown.sort_unstable();
+
+    Ok(Info {
+        name,
+        tiles,
+        shapes,
+        sprites,
+        skipped: unknown,
+        size,
+        compressed_size,
+    })
 }
 
-pub struct Info {}
+#[derive(Serialize)]
+pub struct Info {
+    name: String,
+    tiles: Vec<TileInfo>,
+    shapes: Vec<String>,
+    sprites: Vec<SpriteInfo>,
+    skipped: Vec<String>,
+    size: u64,
+    compressed_size: u64,
+}
+
+impl Info {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialize")
+    }
+}
 
 impl fmt::Display for Info {
-    fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use crossterm::style::Stylize;
+
+        writeln!(f, "kit: {}", self.name.as_str().bold())?;
+
+        writeln!(f, "tiles:")?;
+        for TileInfo { name, blocks } in &self.tiles {
+            write!(f, "    {name}")?;
+            if !blocks.is_empty() {
+                write!(f, ": {}", blocks.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+
+        if !self.shapes.is_empty() {
+            writeln!(f, "shapes:")?;
+            for name in &self.shapes {
+                writeln!(f, "    {name}")?;
+            }
+        }
+
+        writeln!(f, "sprites:")?;
+        for SpriteInfo { name, size } in &self.sprites {
+            match size {
+                Some((width, height)) => writeln!(f, "    {name} {width}x{height}")?,
+                None => writeln!(f, "    {name} {}", "(missing)".yellow())?,
+            }
+        }
+
+        if !self.skipped.is_empty() {
+            writeln!(f, "skipped:")?;
+            for filename in &self.skipped {
+                writeln!(f, "    {filename}")?;
+            }
+        }
+
+        write!(
+            f,
+            "size: {} bytes ({} compressed)",
+            self.size, self.compressed_size
+        )
     }
 }
 
+#[derive(Serialize)]
+struct TileInfo {
+    name: String,
+    blocks: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SpriteInfo {
+    name: String,
+    size: Option<(u32, u32)>,
+}
+
 pub enum Error {
-    NotFound,
-    PermissionDenied,
-    Other,
+    UndefinedName,
+    Json(JsonError),
+    Image { err: String, path: String },
+    File(FileError),
+}
+
+impl From<JsonError> for Error {
+    fn from(err: JsonError) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<ZipError> for Error {
+    fn from(err: ZipError) -> Self {
+        Self::File(err.into())
+    }
 }
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        match err.kind() {
-            ErrorKind::NotFound => Self::NotFound,
-            ErrorKind::PermissionDenied => Self::PermissionDenied,
-            _ => Self::Other,
-        }
+        Self::File(err.into())
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::NotFound => write!(f, "not found"),
-            Self::PermissionDenied => write!(f, "permission denied"),
-            Self::Other => write!(f, "unknown file handling error"),
+            Self::UndefinedName => write!(f, "kit name is undefined"),
+            Self::Json(json) => write!(f, "{}", StyledJson(json)),
+            Self::Image { err, path } => write!(f, "failed to read image {path}: {err}"),
+            Self::File(err) => write!(f, "{err}"),
         }
     }
 }
@@ -1,6 +1,8 @@
+mod check;
 mod error;
 mod info;
 mod pack;
+mod sprite;
 
 use {
     crate::error::Error,
@@ -20,6 +22,14 @@ enum Command {
     Info {
         /// The kit's path
         path: String,
+        /// Prints the info in JSON format
+        #[clap(long)]
+        json: bool,
+    },
+    /// Checks a kit or its source for broken references
+    Check {
+        /// The kit's path or the source directory
+        path: String,
     },
     /// Pack a kit from source
     Pack {
@@ -45,10 +55,22 @@ fn run(cli: Cli) -> Result<(), Error> {
     use {crate::pack::Options, crossterm::style::Stylize, std::path::PathBuf};
 
     match cli.command {
-        Command::Info { path } => {
+        Command::Info { path, json } => {
             let path = PathBuf::from(path);
             let info = info::info(&path).map_err(|err| Error::Info { err, path })?;
-            println!("{info}");
+            if json {
+                println!("{}", info.to_json());
+            } else {
+                println!("{info}");
+            }
+        }
+        Command::Check { path } => {
+            let path = PathBuf::from(path);
+            let report = check::check(&path).map_err(|err| Error::Check { err, path })?;
+            println!("{report}");
+            if !report.is_ok() {
+                std::process::exit(1);
+            }
         }
         Command::Pack { src, name, rewrite } => {
             let path = PathBuf::from(src);
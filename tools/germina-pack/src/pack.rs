@@ -1,8 +1,9 @@
 use {
+    crate::error::FileError,
     std::{
         env, fmt,
         fs::{self, File},
-        io::{self, BufWriter, ErrorKind, Read, Write},
+        io::{self, BufWriter, Read, Write},
         path::{Path, PathBuf},
     },
     zip::result::ZipError,
@@ -119,29 +120,18 @@ pub enum Error {
     KitNameNotSet,
     AlreadyExists(PathBuf),
     InvalidFileName(PathBuf),
-    Arch(&'static str),
-    NotFound,
-    PermissionDenied,
-    Other,
+    File(FileError),
 }
 
 impl From<ZipError> for Error {
     fn from(err: ZipError) -> Self {
-        match err {
-            ZipError::Io(err) => err.into(),
-            ZipError::InvalidArchive(arch) | ZipError::UnsupportedArchive(arch) => Self::Arch(arch),
-            ZipError::FileNotFound => Self::NotFound,
-        }
+        Self::File(err.into())
     }
 }
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        match err.kind() {
-            ErrorKind::NotFound => Self::NotFound,
-            ErrorKind::PermissionDenied => Self::PermissionDenied,
-            _ => Self::Other,
-        }
+        Self::File(err.into())
     }
 }
 
@@ -152,10 +142,7 @@ impl fmt::Display for Error {
             Self::KitNameNotSet => write!(f, "a kit name not set"),
             Self::AlreadyExists(path) => write!(f, "already exists: {}", path.display()),
             Self::InvalidFileName(path) => write!(f, "invalid file name: {}", path.display()),
-            Self::Arch(arch) => write!(f, "archive error: {arch}"),
-            Self::Other => write!(f, "unknown file handling error"),
-            Self::NotFound => write!(f, "file not found"),
-            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::File(err) => write!(f, "{err}"),
         }
     }
 }
@@ -0,0 +1,11 @@
+/// Reads the size of a PNG sprite without decoding it.
+pub fn image_size(buf: &[u8]) -> Result<(u32, u32), String> {
+    use {
+        image::{io::Reader, ImageFormat},
+        std::io::Cursor,
+    };
+
+    Reader::with_format(Cursor::new(buf), ImageFormat::Png)
+        .into_dimensions()
+        .map_err(|err| err.to_string())
+}